@@ -1,5 +1,4 @@
-// #import bevy_pbr::mesh_vertex_output MeshVertexOutput
-#import bevy_sprite::mesh2d_vertex_output::VertexOutput
+#import bevy_sprite::mesh2d_functions as mesh_functions
 
 struct CurveMaterial {
 	thickness: f32
@@ -8,15 +7,87 @@ struct CurveMaterial {
 @group(1) @binding(0)
 var<uniform> material: CurveMaterial;
 
+struct Vertex {
+	@builtin(instance_index) instance_index: u32,
+	@location(0) position: vec3<f32>,
+	@location(1) uv: vec2<f32>,
+	@location(2) color: vec4<f32>,
+	// Thickness, pattern (0 solid, 1 dashed, 2 dotted), opacity and curve length
+	@location(3) style: vec4<f32>,
+};
+
+struct VertexOutput {
+	@builtin(position) position: vec4<f32>,
+	@location(0) uv: vec2<f32>,
+	@location(1) color: vec4<f32>,
+	@location(2) style: vec4<f32>,
+};
+
+@vertex
+fn vertex(vertex: Vertex) -> VertexOutput {
+	var out: VertexOutput;
+	let model = mesh_functions::get_model_matrix(vertex.instance_index);
+	out.position = mesh_functions::mesh2d_position_local_to_clip(model, vec4<f32>(vertex.position, 1.0));
+	out.uv = vertex.uv;
+	out.color = vertex.color;
+	out.style = vertex.style;
+	return out;
+}
+
 @fragment
 fn fragment(mesh: VertexOutput) -> @location(0) vec4<f32> {
-	return quadratic_curve(mesh.uv, mesh.color);
+	// Gradients have to be taken before anything is discarded
+	let px = dpdx(mesh.uv);
+	let py = dpdy(mesh.uv);
+	var color = mesh.color;
+	color.a *= mesh.style.z;
+
+	// Arrowheads are marked with a negative v coordinate and filled in solid
+	if mesh.uv.y < 0.0 {
+		return color;
+	}
+
+	// u runs from 0 to 1 along the curve, so scale it by the curve's length to
+	// space the pattern evenly however long the edge is
+	let thickness = material.thickness * mesh.style.x;
+	let along = mesh.uv.x * mesh.style.w;
+	if mesh.style.y > 1.5 {
+		if fract(along / (thickness * 4.0)) > 0.5 {
+			discard;
+		}
+	}
+	else if mesh.style.y > 0.5 {
+		if fract(along / 16.0) > 0.625 {
+			discard;
+		}
+	}
+
+	// Straight segments are quads with v running from 2 to 4 across them
+	if mesh.uv.y > 1.5 {
+		return straight_line(mesh.uv.y - 3.0, px.y, py.y, color, thickness);
+	}
+
+	return quadratic_curve(mesh.uv, px, py, color, thickness);
 }
 
-fn quadratic_curve(point: vec2<f32>, in_color: vec4<f32>) -> vec4<f32> {
-	// Gradients
-	let px = dpdx(point);
-	let py = dpdy(point);
+fn straight_line(across: f32, dx: f32, dy: f32, in_color: vec4<f32>, thickness: f32) -> vec4<f32> {
+	var color = in_color;
+
+	// Distance from the middle of the quad in pixels
+	let sd = across / sqrt(dx * dx + dy * dy);
+
+	let alpha = thickness - abs(sd);
+	if alpha < 0.0 {
+		discard;
+	}
+	else if alpha < 1.0 {
+		color.a *= alpha;
+	}
+
+	return color;
+}
+
+fn quadratic_curve(point: vec2<f32>, px: vec2<f32>, py: vec2<f32>, in_color: vec4<f32>, thickness: f32) -> vec4<f32> {
 	var color = in_color;
 
 	// Chain Rule
@@ -27,17 +98,17 @@ fn quadratic_curve(point: vec2<f32>, in_color: vec4<f32>) -> vec4<f32> {
 	let sd = (point.x * point.x - point.y) / sqrt(fx * fx + fy * fy);
 
 	// Linear alpha
-	let alpha = material.thickness - abs(sd);
+	let alpha = thickness - abs(sd);
 	// let alpha = 0.5 - sd;
 	if alpha > 1.0 {
-		color.a = 1.0;
+		// Fully inside the curve
 	}
 	else if alpha < 0.0 {
 		discard;
 	}
 	else {
-		color.a = alpha;
+		color.a *= alpha;
 	}
 
 	return color;
-}
\ No newline at end of file
+}
@@ -1,7 +1,27 @@
+pub mod align;
+pub mod centrality;
+pub mod coloring;
+pub mod community;
+pub mod connectivity;
+pub mod dag;
+pub mod euler;
+pub mod flow;
 pub mod event;
+pub mod export;
+pub mod generate;
+pub mod grid;
+pub mod hamilton;
+pub mod io;
+pub mod isomorphism;
+pub mod labels;
+pub mod matching;
+pub mod planarity;
+pub mod ops;
 pub mod plugin;
+pub mod shape;
+pub mod template;
 
-use std::collections::{HashMap, HashSet};
+use std::collections::{HashMap, HashSet, VecDeque};
 
 use bevy::{prelude::*, sprite::Mesh2dHandle};
 
@@ -19,11 +39,80 @@ impl Default for Grabbable {
 #[derive(Clone, Component, Default)]
 pub struct GNode;
 
+/// Text shown under a node or edge handle by an algorithm, as opposed to the
+/// user's label
+#[derive(Component)]
+pub struct Annotation;
+
+/// Text showing an edge's weight, kept up to date as the weight changes
+#[derive(Component)]
+pub struct WeightLabel;
+
 #[derive(Bundle)]
 struct GNodeBundle {
     node: GNode,
     sprite: SpriteBundle,
     grab: Grabbable,
+    style: shape::NodeStyle,
+}
+
+#[derive(Copy, Clone, Default, PartialEq, Eq)]
+pub enum EdgePattern {
+    #[default]
+    Solid,
+    Dashed,
+    Dotted,
+}
+
+impl core::fmt::Display for EdgePattern {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            EdgePattern::Solid => write!(f, "Solid"),
+            EdgePattern::Dashed => write!(f, "Dashed"),
+            EdgePattern::Dotted => write!(f, "Dotted"),
+        }
+    }
+}
+
+/// How an edge gets from its start node to its end node
+#[derive(Copy, Clone, Default, PartialEq, Eq)]
+pub enum EdgeRoute {
+    /// A curve through the handle
+    #[default]
+    Curved,
+    /// Straight segments through the bend points
+    Polyline,
+    /// Horizontal and vertical segments turning at or next to the bend points
+    Orthogonal,
+}
+
+impl core::fmt::Display for EdgeRoute {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            EdgeRoute::Curved => write!(f, "Curved"),
+            EdgeRoute::Polyline => write!(f, "Polyline"),
+            EdgeRoute::Orthogonal => write!(f, "Orthogonal"),
+        }
+    }
+}
+
+#[derive(Copy, Clone, PartialEq)]
+pub struct EdgeStyle {
+    pub thickness: f32,
+    pub pattern: EdgePattern,
+    pub opacity: f32,
+    pub route: EdgeRoute,
+}
+
+impl Default for EdgeStyle {
+    fn default() -> Self {
+        EdgeStyle {
+            thickness: 2.0,
+            pattern: EdgePattern::Solid,
+            opacity: 1.0,
+            route: EdgeRoute::Curved,
+        }
+    }
 }
 
 #[derive(Clone, Component)]
@@ -31,7 +120,16 @@ pub struct GEdge {
     start: NodeE,
     end: NodeE,
     offset: Option<usize>,
+    /// Whether an arrowhead follows the curve in the edge mesh
+    arrow: bool,
+    /// The [`EdgeBend`]s a routed edge passes through, in order from its
+    /// start node
+    bends: Vec<Entity>,
+    /// The corners of the edge when the edge mesh draws it as straight
+    /// segments rather than a curve
+    path: Option<Vec<Vec2>>,
     pub weight: i32,
+    pub style: EdgeStyle,
 }
 
 impl GEdge {
@@ -40,16 +138,31 @@ impl GEdge {
     }
 
     pub fn size_in_mesh(&self) -> usize {
+        let arrow = if self.arrow { 3 } else { 0 };
         if self.is_loop() {
-            4
+            4 + arrow
+        } else if let Some(path) = &self.path {
+            // A quad for each segment
+            4 * (path.len() - 1) + arrow
         } else {
-            3
+            3 + arrow
         }
     }
 
     pub fn endpoints(&self) -> (NodeE, NodeE) {
         (self.start, self.end)
     }
+
+    pub fn bend_count(&self) -> usize {
+        self.bends.len()
+    }
+}
+
+/// A point a polyline or orthogonal edge is routed through, dragged around
+/// like a handle
+#[derive(Component)]
+pub struct EdgeBend {
+    pub edge: EdgeE,
 }
 
 #[derive(Default, Bundle)]
@@ -98,16 +211,23 @@ pub struct Graph {
     pub edge_nodes: HashMap<EdgeE, (NodeE, NodeE)>, // <Edge, (Start, End)>
     pub degree: usize,
     pub edge_mesh_handle: Mesh2dHandle,
-    pub edge_mesh: Entity,
 
     pub directed: bool,
     pub components: u32,
     pub last_node_num: u32,
     pub last_edge_num: u32,
-    pub last_component_num: u32,
 
-    pub show_labels: bool,
+    pub show_node_labels: bool,
+    pub show_edge_labels: bool,
+    /// Show each edge's weight next to it
+    pub show_weights: bool,
     pub do_physics: bool,
+    /// Length of the arrowheads on directed edges
+    pub arrow_size: f32,
+    /// Scale each edge's thickness with its weight
+    pub thickness_from_weight: bool,
+    /// Draw curved edges as straight lines, with their handles halfway along
+    pub straight_only: bool,
 }
 
 pub enum OppositeNode {
@@ -134,20 +254,23 @@ impl PathPart {
 }
 
 impl Graph {
-    pub fn new(edge_mesh_handle: Mesh2dHandle, edge_mesh: Entity) -> Self {
+    pub fn new(edge_mesh_handle: Mesh2dHandle) -> Self {
         Graph {
             node_edges: HashMap::new(),
             edge_nodes: HashMap::new(),
             degree: 0,
             edge_mesh_handle,
-            edge_mesh,
             directed: false,
             components: 0,
             last_node_num: 0,
             last_edge_num: 0,
-            last_component_num: 0,
-            show_labels: false,
+            show_node_labels: false,
+            show_edge_labels: false,
+            show_weights: false,
             do_physics: false,
+            arrow_size: 12.0,
+            thickness_from_weight: false,
+            straight_only: false,
         }
     }
 
@@ -173,7 +296,7 @@ impl Graph {
         if self.node_edges.get(node).unwrap().is_empty() {
             self.components -= 1;
         }
-        self.node_edges.remove(&node);
+        self.node_edges.remove(node);
     }
 
     fn add_edge(&mut self, edge: EdgeE, start: &NodeE, end: &NodeE) {
@@ -207,6 +330,21 @@ impl Graph {
         }
     }
 
+    /// Every edge between two nodes in either direction, or every loop on a
+    /// node if they're the same, in the order they were added
+    pub fn parallel_edges(&self, a: &NodeE, b: &NodeE) -> Vec<EdgeE> {
+        let mut edges: Vec<EdgeE> = self.node_edges[a]
+            .iter()
+            .filter(|edge| {
+                let (start, end) = self.edge_nodes[edge];
+                (start == *a && end == *b) || (start == *b && end == *a)
+            })
+            .copied()
+            .collect();
+        edges.sort_by_key(|edge| edge.0);
+        edges
+    }
+
     pub fn is_adjacent(&self, a: &NodeE, b: &NodeE) -> bool {
         self.node_edges.get(a).unwrap().iter().any(|&x| {
             let (start, end) = self.edge_nodes.get(&x).unwrap();
@@ -263,43 +401,92 @@ impl Graph {
         }
     }
 
-    pub fn bipartite_walk<F>(&self, start: &NodeE, mut visit: F) -> bool
+    /// Split the graph into two sets so that every edge goes between the sets,
+    /// calling the predicate on each node and edge visited along with the set
+    /// it was placed in. Every component is covered, starting with the one
+    /// containing the given node.
+    ///
+    /// If the graph isn't bipartite, an odd cycle is returned as proof, as a
+    /// closed path starting and ending on the same node.
+    pub fn bipartite_walk<F>(&self, start: &NodeE, mut visit: F) -> Result<(), Vec<PathPart>>
     where
         F: FnMut(&NodeE, Option<&EdgeE>, usize),
     {
-        let mut sets = Vec::new();
-        sets.push(HashSet::new());
-        sets.push(HashSet::new());
+        let mut sets: HashMap<NodeE, usize> = HashMap::new();
+        let mut parents: HashMap<NodeE, PathPart> = HashMap::new();
+
+        let mut roots = vec![*start];
+        roots.extend(self.node_edges.keys().filter(|&node| node != start));
+        for root in roots {
+            if sets.contains_key(&root) {
+                continue;
+            }
 
-        // Visit the start node and add it to set 0
-        sets[0].insert(*start);
-        visit(start, None, 0);
-        let mut stack = Vec::new();
-        stack.push((0, *start));
+            // Visit the root node and add it to set 0
+            sets.insert(root, 0);
+            visit(&root, None, 0);
+            let mut queue = VecDeque::from([root]);
+
+            while let Some(node) = queue.pop_front() {
+                let next_set = 1 - sets[&node];
+                for edge in self.node_edges.get(&node).unwrap() {
+                    let adj = match self.opposite(&node, edge) {
+                        OppositeNode::Adjacent(adj) => adj,
+                        OppositeNode::CounterAdjacent(adj) => adj,
+                        // A loop is a cycle of length one
+                        OppositeNode::Loop => {
+                            return Err(vec![
+                                PathPart::without_edge(node),
+                                PathPart::with_edge(node, *edge),
+                            ])
+                        }
+                        OppositeNode::None => continue,
+                    };
 
-        while let Some((current_set, node)) = stack.pop() {
-            let next_set = (current_set + 1) % sets.len();
-            for edge in self.node_edges.get(&node).unwrap() {
-                let adj = match self.opposite(&node, edge) {
-                    OppositeNode::Adjacent(adj) => adj,
-                    OppositeNode::CounterAdjacent(adj) => adj,
-                    OppositeNode::Loop => return false, // No loops in bipartite graphs
-                    OppositeNode::None => continue
-                };
-                if sets[current_set].contains(&adj) {
-                    return false;
-                }
-                else {
-                    visit(&adj, Some(edge), next_set);
-                    if !sets[next_set].contains(&adj) {
-                        sets[next_set].insert(adj);
-                        stack.push((next_set, adj));
+                    match sets.get(&adj) {
+                        Some(&set) if set != next_set => {
+                            return Err(Self::odd_cycle(&parents, node, adj, *edge));
+                        }
+                        Some(_) => visit(&adj, Some(edge), next_set),
+                        None => {
+                            sets.insert(adj, next_set);
+                            parents.insert(adj, PathPart::with_edge(node, *edge));
+                            visit(&adj, Some(edge), next_set);
+                            queue.push_back(adj);
+                        }
                     }
                 }
             }
         }
 
-        return true;
+        Ok(())
+    }
+
+    /// Close the cycle formed by adding the edge a-b to a breadth first search
+    /// tree, where `parents` maps each node to its parent and the edge between them
+    fn odd_cycle(parents: &HashMap<NodeE, PathPart>, a: NodeE, b: NodeE, edge: EdgeE) -> Vec<PathPart> {
+        let to_root = |mut node: NodeE| {
+            let mut path = vec![node];
+            while let Some(parent) = parents.get(&node) {
+                node = parent.node;
+                path.push(node);
+            }
+            path
+        };
+        let (a_path, b_path) = (to_root(a), to_root(b));
+        let lca = *a_path.iter().find(|node| b_path.contains(node)).unwrap();
+
+        // Walk down from the common ancestor to a, across to b, then back up
+        let mut cycle = vec![PathPart::without_edge(lca)];
+        for node in a_path.iter().take_while(|&&node| node != lca).collect::<Vec<_>>().into_iter().rev() {
+            cycle.push(PathPart::with_edge(*node, parents[node].edge.unwrap()));
+        }
+        cycle.push(PathPart::with_edge(b, edge));
+        for node in b_path.iter().take_while(|&&node| node != lca) {
+            let parent = parents[node];
+            cycle.push(PathPart::with_edge(parent.node, parent.edge.unwrap()));
+        }
+        cycle
     }
 
     /// Determine whether two nodes are connected in the graph
@@ -310,51 +497,18 @@ impl Graph {
                 connected = true;
                 return true;
             }
-            return false;
+            false
         });
         connected
     }
 
-    /// Determine whether a given edge is a bridge in the graph
-    pub fn is_bridge(&self, target_edge: &EdgeE) -> bool {
-        let (start, end) = self.edge_nodes.get(target_edge).unwrap();
-
-        let mut visited = HashSet::new();
-        let mut stack = Vec::new();
-        stack.push(*start);
-        visited.insert(*start);
-
-        while let Some(node) = stack.pop() {
-            for edge in self.node_edges.get(&node).unwrap() {
-                let adj = match self.opposite(&node, edge) {
-                    OppositeNode::Adjacent(adj) => adj,
-                    OppositeNode::CounterAdjacent(adj) => adj,
-                    _ => continue, // No loops in bipartite graphs
-                };
-                if edge == target_edge {
-                    continue; // Skip the target edge, we want to see if there are any connections other than through it
-                }
-                if adj == *end {
-                    return false; // Found an alternate path, this is a link, not a bridge
-                }
-                if !visited.contains(&adj) {
-                    visited.insert(adj);
-                    stack.push(adj);
-                }
-            }
-        }
-
-        // No alternate path found, this is a bridge
-        return true;
-    }
-
     pub fn dijkstra_path(&self, start: &NodeE, end: &NodeE, q_edge: Option<&Query<&GEdge>>) -> Option<Vec<PathPart>> {
         let mut unvisited = HashSet::new();
         let mut distances = HashMap::new();
         let mut previous: HashMap<NodeE, PathPart> = HashMap::new();
 
         for node in self.node_edges.keys() {
-            distances.insert(*node, std::f32::INFINITY);
+            distances.insert(*node, f32::INFINITY);
             unvisited.insert(*node);
         }
         distances.insert(*start, 0.0);
@@ -376,7 +530,7 @@ impl Graph {
 
             // For each edge adjacent to n that is still unvisited
             for edge_e in self.node_edges.get(&n).unwrap().iter() {
-                let adj = match self.opposite(&n, &edge_e) {
+                let adj = match self.opposite(&n, edge_e) {
                     OppositeNode::Adjacent(adj) => {
                         if unvisited.contains(&adj) {
                             adj
@@ -401,11 +555,11 @@ impl Graph {
             }
         }
 
-        if previous.get(end).is_some() || start == end {
+        if previous.contains_key(end) || start == end {
             let mut path = Vec::new();
             let mut cursor = Some(PathPart::without_edge(*end));
             while let Some(part) = cursor {
-                cursor = previous.get(&part.node).map(|part| *part);
+                cursor = previous.get(&part.node).copied();
                 path.push(part);
             }
 
@@ -417,16 +571,18 @@ impl Graph {
         }
     }
 
-    pub fn adjacency_matrix(&self, q_node: &Query<(Entity, &Children), With<GNode>>, q_text: &Query<&Text>) -> LabeledMatrix {
-        let mut matrix = LabeledMatrix::default();
-        matrix.data = vec![vec![0.0; self.node_edges.len()]; self.node_edges.len()];
+    pub fn adjacency_matrix(&self, q_node: &Query<(Entity, &Children), With<GNode>>, q_text: &Query<&Text, Without<Annotation>>) -> LabeledMatrix {
+        let mut matrix = LabeledMatrix {
+            data: vec![vec![0.0; self.node_edges.len()]; self.node_edges.len()],
+            ..Default::default()
+        };
 
         let mut labels = Vec::new();
         q_node.iter().for_each(|(node_e, children)| {
             for child in children.iter() {
-                q_text.get(*child).ok().map(|text| {
+                if let Ok(text) = q_text.get(*child) {
                     labels.push((node_e, text.sections[0].value.clone()));
-                });
+                }
             }
         });
         labels.sort_by(|(_, a), (_, b)| a.cmp(b));
@@ -454,6 +610,24 @@ impl Graph {
     }
 }
 
+#[cfg(test)]
+impl Graph {
+    /// A graph over nodes `0..n` with an edge for each pair, for testing the
+    /// algorithms without the app running
+    pub(crate) fn from_pairs(n: usize, pairs: &[(usize, usize)], directed: bool) -> (Graph, Vec<NodeE>) {
+        let mut graph = Graph::new(Mesh2dHandle::default());
+        graph.directed = directed;
+        let nodes: Vec<NodeE> = (0..n).map(|i| NodeE(Entity::from_raw(i as u32))).collect();
+        for node in nodes.iter() {
+            graph.add_node(*node);
+        }
+        for (i, &(a, b)) in pairs.iter().enumerate() {
+            graph.add_edge(EdgeE(Entity::from_raw((n + i) as u32)), &nodes[a], &nodes[b]);
+        }
+        (graph, nodes)
+    }
+}
+
 #[derive(Default)]
 pub struct LabeledMatrix {
     pub data: Vec<Vec<f32>>,
@@ -462,4 +636,47 @@ pub struct LabeledMatrix {
 }
 
 impl LabeledMatrix {
-}
\ No newline at end of file
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn cycle(n: usize) -> (Graph, Vec<NodeE>) {
+        let pairs: Vec<(usize, usize)> = (0..n).map(|i| (i, (i + 1) % n)).collect();
+        Graph::from_pairs(n, &pairs, false)
+    }
+
+    #[test]
+    fn even_cycle_alternates_sets() {
+        let (graph, nodes) = cycle(6);
+        let mut sets = HashMap::new();
+        assert!(graph.bipartite_walk(&nodes[0], |node, _, set| {
+            sets.insert(*node, set);
+        })
+        .is_ok());
+        for i in 0..6 {
+            assert_ne!(sets[&nodes[i]], sets[&nodes[(i + 1) % 6]]);
+        }
+    }
+
+    #[test]
+    fn odd_cycle_is_reported_closed() {
+        let (graph, nodes) = cycle(5);
+        let Err(path) = graph.bipartite_walk(&nodes[0], |_, _, _| ()) else {
+            panic!("a 5-cycle was taken as bipartite");
+        };
+        assert_eq!(path.len(), 6);
+        assert!(path.first().unwrap().node == path.last().unwrap().node);
+        assert!(path[1..].iter().all(|part| part.edge.is_some()));
+    }
+
+    #[test]
+    fn loop_is_an_odd_cycle() {
+        let (graph, nodes) = Graph::from_pairs(2, &[(0, 1), (1, 1)], false);
+        let Err(path) = graph.bipartite_walk(&nodes[0], |_, _, _| ()) else {
+            panic!("a loop was taken as bipartite");
+        };
+        assert_eq!(path.len(), 2);
+        assert!(path.iter().all(|part| part.node == nodes[1]));
+    }
+}
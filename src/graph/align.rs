@@ -0,0 +1,88 @@
+use bevy::prelude::*;
+
+#[derive(Copy, Clone, PartialEq, Eq)]
+pub enum Alignment {
+    Left,
+    Center,
+    Right,
+    Top,
+    Middle,
+    Bottom,
+    /// Space the nodes evenly between the leftmost and rightmost one
+    DistributeHorizontally,
+    /// Space the nodes evenly between the lowest and highest one
+    DistributeVertically,
+}
+
+impl core::fmt::Display for Alignment {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Alignment::Left => write!(f, "Left"),
+            Alignment::Center => write!(f, "Center"),
+            Alignment::Right => write!(f, "Right"),
+            Alignment::Top => write!(f, "Top"),
+            Alignment::Middle => write!(f, "Middle"),
+            Alignment::Bottom => write!(f, "Bottom"),
+            Alignment::DistributeHorizontally => write!(f, "Distribute Horizontally"),
+            Alignment::DistributeVertically => write!(f, "Distribute Vertically"),
+        }
+    }
+}
+
+impl Alignment {
+    pub const ALIGN: [Alignment; 6] = [
+        Alignment::Left,
+        Alignment::Center,
+        Alignment::Right,
+        Alignment::Top,
+        Alignment::Middle,
+        Alignment::Bottom,
+    ];
+    pub const DISTRIBUTE: [Alignment; 2] = [
+        Alignment::DistributeHorizontally,
+        Alignment::DistributeVertically,
+    ];
+}
+
+/// Where each of the node centres in `points` goes to line them up. Nodes
+/// only move along one axis, so their order along the other one is kept.
+pub fn arrange(points: &[Vec2], alignment: Alignment) -> Vec<Vec2> {
+    if points.is_empty() {
+        return Vec::new();
+    }
+
+    let (min, max) = points
+        .iter()
+        .fold((points[0], points[0]), |(min, max), p| (min.min(*p), max.max(*p)));
+    let set_x = |x: f32| points.iter().map(|p| Vec2::new(x, p.y)).collect();
+    let set_y = |y: f32| points.iter().map(|p| Vec2::new(p.x, y)).collect();
+
+    match alignment {
+        Alignment::Left => set_x(min.x),
+        Alignment::Center => set_x((min.x + max.x) / 2.0),
+        Alignment::Right => set_x(max.x),
+        Alignment::Top => set_y(max.y),
+        Alignment::Middle => set_y((min.y + max.y) / 2.0),
+        Alignment::Bottom => set_y(min.y),
+        Alignment::DistributeHorizontally => distribute(points, Vec2::X),
+        Alignment::DistributeVertically => distribute(points, Vec2::Y),
+    }
+}
+
+/// Spread the points evenly along `axis`, keeping the two outermost where
+/// they are
+fn distribute(points: &[Vec2], axis: Vec2) -> Vec<Vec2> {
+    let mut order: Vec<usize> = (0..points.len()).collect();
+    order.sort_by(|&a, &b| points[a].dot(axis).total_cmp(&points[b].dot(axis)));
+
+    let first = points[order[0]].dot(axis);
+    let last = points[*order.last().unwrap()].dot(axis);
+    let step = if points.len() > 1 { (last - first) / (points.len() - 1) as f32 } else { 0.0 };
+
+    let mut result = points.to_vec();
+    for (rank, &i) in order.iter().enumerate() {
+        let along = first + step * rank as f32;
+        result[i] = points[i] - axis * points[i].dot(axis) + axis * along;
+    }
+    result
+}
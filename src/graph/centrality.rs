@@ -0,0 +1,220 @@
+use std::collections::{HashMap, VecDeque};
+
+use super::{Graph, NodeE};
+
+/// Damping factor for PageRank, the chance of following an edge rather than
+/// jumping to a random node
+static PAGERANK_DAMPING: f64 = 0.85;
+/// Katz centrality uses this fraction of the largest attenuation factor that
+/// still converges
+static KATZ_ATTENUATION: f64 = 0.9;
+static ITERATIONS: usize = 100;
+
+#[derive(Copy, Clone, Default, PartialEq, Eq)]
+pub enum Centrality {
+    #[default]
+    Degree,
+    Closeness,
+    Betweenness,
+    Eigenvector,
+    PageRank,
+    Katz,
+}
+
+impl Centrality {
+    pub const ALL: [Centrality; 6] = [
+        Centrality::Degree,
+        Centrality::Closeness,
+        Centrality::Betweenness,
+        Centrality::Eigenvector,
+        Centrality::PageRank,
+        Centrality::Katz,
+    ];
+}
+
+impl core::fmt::Display for Centrality {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Centrality::Degree => write!(f, "Degree"),
+            Centrality::Closeness => write!(f, "Closeness"),
+            Centrality::Betweenness => write!(f, "Betweenness"),
+            Centrality::Eigenvector => write!(f, "Eigenvector"),
+            Centrality::PageRank => write!(f, "PageRank"),
+            Centrality::Katz => write!(f, "Katz"),
+        }
+    }
+}
+
+/// Every centrality measure for every node, rows follow `nodes` and columns
+/// follow [`Centrality::ALL`]
+#[derive(Default)]
+pub struct CentralityTable {
+    pub nodes: Vec<NodeE>,
+    pub values: Vec<[f64; 6]>,
+}
+
+impl CentralityTable {
+    pub fn column(&self, measure: Centrality) -> impl Iterator<Item = f64> + '_ {
+        let i = Centrality::ALL.iter().position(|m| *m == measure).unwrap();
+        self.values.iter().map(move |row| row[i])
+    }
+}
+
+/// Node indices reachable along each node's edges, following direction if
+/// the graph is directed. Parallel edges appear once per edge.
+struct Adjacency {
+    out: Vec<Vec<usize>>,
+    into: Vec<Vec<usize>>,
+}
+
+impl Graph {
+    fn index_adjacency(&self, nodes: &[NodeE]) -> Adjacency {
+        let index: HashMap<NodeE, usize> = nodes.iter().enumerate().map(|(i, node)| (*node, i)).collect();
+        let mut adj = Adjacency {
+            out: vec![Vec::new(); nodes.len()],
+            into: vec![Vec::new(); nodes.len()],
+        };
+        for (start, end) in self.edge_nodes.values() {
+            let (s, e) = (index[start], index[end]);
+            adj.out[s].push(e);
+            adj.into[e].push(s);
+            if !self.directed && s != e {
+                adj.out[e].push(s);
+                adj.into[s].push(e);
+            }
+        }
+        adj
+    }
+
+    pub fn centrality(&self) -> CentralityTable {
+        let mut nodes: Vec<NodeE> = self.node_edges.keys().copied().collect();
+        nodes.sort_by_key(|node| node.0);
+        let n = nodes.len();
+        if n == 0 {
+            return CentralityTable::default();
+        }
+        let adj = self.index_adjacency(&nodes);
+
+        let degree: Vec<f64> = nodes.iter().map(|node| self.node_edges[node].len() as f64).collect();
+        let (closeness, betweenness) = shortest_path_centrality(&adj, self.directed);
+
+        // Directed edges are counted both ways so the symmetric eigen solver
+        // still applies. Its largest eigenvalue bounds the directed one, which
+        // keeps the Katz iteration convergent.
+        let mut matrix = nalgebra::DMatrix::<f64>::zeros(n, n);
+        for (i, out) in adj.out.iter().enumerate() {
+            for &j in out.iter() {
+                matrix[(i, j)] += 1.0;
+                if self.directed {
+                    matrix[(j, i)] += 1.0;
+                }
+            }
+        }
+        let eigen = nalgebra::linalg::SymmetricEigen::new(matrix);
+        let largest = eigen.eigenvalues.imax();
+        let lambda = eigen.eigenvalues[largest];
+        let mut eigenvector: Vec<f64> = eigen.eigenvectors.column(largest).iter().map(|x| x.abs()).collect();
+        normalize_max(&mut eigenvector);
+
+        let pagerank = pagerank(&adj);
+        let katz = katz(&adj, if lambda > 0.0 { KATZ_ATTENUATION / lambda } else { 0.0 });
+
+        let values = (0..n)
+            .map(|i| [degree[i], closeness[i], betweenness[i], eigenvector[i], pagerank[i], katz[i]])
+            .collect();
+        CentralityTable { nodes, values }
+    }
+}
+
+fn normalize_max(values: &mut [f64]) {
+    let max = values.iter().copied().fold(0.0, f64::max);
+    if max > 0.0 {
+        values.iter_mut().for_each(|x| *x /= max);
+    }
+}
+
+/// Closeness with the Wasserman–Faust correction for disconnected graphs, and
+/// betweenness with Brandes' algorithm, both from one BFS per node
+fn shortest_path_centrality(adj: &Adjacency, directed: bool) -> (Vec<f64>, Vec<f64>) {
+    let n = adj.out.len();
+    let mut closeness = vec![0.0; n];
+    let mut betweenness = vec![0.0; n];
+
+    for s in 0..n {
+        let mut dist: Vec<Option<usize>> = vec![None; n];
+        let mut paths = vec![0.0; n];
+        let mut preds: Vec<Vec<usize>> = vec![Vec::new(); n];
+        let mut order = Vec::new();
+        dist[s] = Some(0);
+        paths[s] = 1.0;
+
+        let mut queue = VecDeque::from([s]);
+        while let Some(v) = queue.pop_front() {
+            order.push(v);
+            let d = dist[v].unwrap();
+            for &w in adj.out[v].iter() {
+                if dist[w].is_none() {
+                    dist[w] = Some(d + 1);
+                    queue.push_back(w);
+                }
+                if dist[w] == Some(d + 1) {
+                    paths[w] += paths[v];
+                    preds[w].push(v);
+                }
+            }
+        }
+
+        let reached = order.len() - 1;
+        let total: usize = dist.iter().flatten().sum();
+        if total > 0 && n > 1 {
+            closeness[s] = (reached as f64 / total as f64) * (reached as f64 / (n - 1) as f64);
+        }
+
+        // Accumulate dependencies from the furthest nodes back
+        let mut dependency = vec![0.0; n];
+        for &w in order.iter().rev() {
+            for &v in preds[w].iter() {
+                dependency[v] += paths[v] / paths[w] * (1.0 + dependency[w]);
+            }
+            if w != s {
+                betweenness[w] += dependency[w];
+            }
+        }
+    }
+
+    // Each path was counted from both ends
+    if !directed {
+        betweenness.iter_mut().for_each(|x| *x /= 2.0);
+    }
+    (closeness, betweenness)
+}
+
+fn pagerank(adj: &Adjacency) -> Vec<f64> {
+    let n = adj.out.len();
+    let mut rank = vec![1.0 / n as f64; n];
+    for _ in 0..ITERATIONS {
+        // Nodes without outgoing edges spread their rank over every node
+        let dangling: f64 = (0..n).filter(|&i| adj.out[i].is_empty()).map(|i| rank[i]).sum();
+        let mut next = vec![(1.0 - PAGERANK_DAMPING + PAGERANK_DAMPING * dangling) / n as f64; n];
+        for (i, out) in adj.out.iter().enumerate() {
+            for &j in out.iter() {
+                next[j] += PAGERANK_DAMPING * rank[i] / out.len() as f64;
+            }
+        }
+        rank = next;
+    }
+    rank
+}
+
+/// Katz centrality by iterating x = αAᵀx + 1, scaled so the largest is 1
+fn katz(adj: &Adjacency, alpha: f64) -> Vec<f64> {
+    let n = adj.out.len();
+    let mut x = vec![1.0; n];
+    for _ in 0..ITERATIONS {
+        x = (0..n)
+            .map(|i| 1.0 + alpha * adj.into[i].iter().map(|&j| x[j]).sum::<f64>())
+            .collect();
+    }
+    normalize_max(&mut x);
+    x
+}
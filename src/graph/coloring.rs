@@ -0,0 +1,533 @@
+use std::{
+    collections::{HashMap, HashSet},
+    sync::atomic::{AtomicBool, Ordering},
+};
+
+use super::{hamilton::Cancelled, EdgeE, Graph, NodeE, OppositeNode};
+
+/// The largest graph the exact coloring will attempt
+pub static EXACT_COLORING_LIMIT: usize = 30;
+
+#[derive(Copy, Clone, Default, PartialEq, Eq)]
+pub enum ColoringMethod {
+    /// Greedy coloring in order of decreasing degree
+    #[default]
+    WelshPowell,
+    /// Greedy coloring, always picking the node with the most distinct colors
+    /// among its neighbours next
+    DSatur,
+    /// Backtracking search for an optimal coloring
+    Exact,
+}
+
+impl core::fmt::Display for ColoringMethod {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ColoringMethod::WelshPowell => write!(f, "Welsh–Powell"),
+            ColoringMethod::DSatur => write!(f, "DSatur"),
+            ColoringMethod::Exact => write!(f, "Exact"),
+        }
+    }
+}
+
+/// An assignment of color classes to nodes, classes are numbered from 0
+pub type Coloring = HashMap<NodeE, usize>;
+
+impl Graph {
+    /// The distinct nodes adjacent to a node, ignoring edge direction and loops
+    pub fn neighbours(&self, node: &NodeE) -> HashSet<NodeE> {
+        self.node_edges
+            .get(node)
+            .unwrap()
+            .iter()
+            .filter_map(|edge| match self.opposite(node, edge) {
+                OppositeNode::Adjacent(adj) | OppositeNode::CounterAdjacent(adj) => Some(adj),
+                _ => None,
+            })
+            .collect()
+    }
+
+    pub fn has_loop(&self) -> bool {
+        self.edge_nodes.values().any(|(start, end)| start == end)
+    }
+
+    /// Color the nodes one at a time in the given order, giving each the
+    /// smallest color not used by its neighbours
+    fn greedy_coloring(&self, order: &[NodeE]) -> Coloring {
+        let mut coloring = Coloring::new();
+        for node in order {
+            let used: HashSet<usize> = self
+                .neighbours(node)
+                .iter()
+                .filter_map(|adj| coloring.get(adj).copied())
+                .collect();
+            let color = (0..).find(|c| !used.contains(c)).unwrap();
+            coloring.insert(*node, color);
+        }
+        coloring
+    }
+
+    pub fn welsh_powell_coloring(&self) -> Coloring {
+        let mut order: Vec<NodeE> = self.node_edges.keys().copied().collect();
+        order.sort_by_key(|node| (std::cmp::Reverse(self.neighbours(node).len()), node.0));
+        self.greedy_coloring(&order)
+    }
+
+    pub fn dsatur_coloring(&self) -> Coloring {
+        let neighbours: HashMap<NodeE, HashSet<NodeE>> = self
+            .node_edges
+            .keys()
+            .map(|node| (*node, self.neighbours(node)))
+            .collect();
+        let mut coloring = Coloring::new();
+
+        while coloring.len() < neighbours.len() {
+            // Highest saturation first, ties broken by degree
+            let (node, _) = neighbours
+                .iter()
+                .filter(|(node, _)| !coloring.contains_key(node))
+                .map(|(node, adj)| {
+                    let saturation = adj
+                        .iter()
+                        .filter_map(|a| coloring.get(a))
+                        .collect::<HashSet<_>>()
+                        .len();
+                    (*node, (saturation, adj.len(), std::cmp::Reverse(node.0)))
+                })
+                .max_by_key(|(_, key)| *key)
+                .unwrap();
+
+            let used: HashSet<usize> = neighbours[&node]
+                .iter()
+                .filter_map(|adj| coloring.get(adj).copied())
+                .collect();
+            let color = (0..).find(|c| !used.contains(c)).unwrap();
+            coloring.insert(node, color);
+        }
+        coloring
+    }
+
+    /// The nodes in the order the exact coloring goes through them, ready to
+    /// be searched on a background thread
+    pub fn coloring_problem(&self) -> ColoringProblem {
+        let mut order: Vec<NodeE> = self.node_edges.keys().copied().collect();
+        order.sort_by_key(|node| (std::cmp::Reverse(self.neighbours(node).len()), node.0));
+        let index: HashMap<NodeE, usize> =
+            order.iter().enumerate().map(|(i, node)| (*node, i)).collect();
+        let earlier = order
+            .iter()
+            .enumerate()
+            .map(|(i, node)| {
+                self.neighbours(node)
+                    .iter()
+                    .map(|adj| index[adj])
+                    .filter(|&j| j < i)
+                    .collect()
+            })
+            .collect();
+
+        ColoringProblem {
+            order,
+            earlier,
+            start: self.dsatur_coloring(),
+        }
+    }
+
+    /// The size of a clique found greedily, a lower bound on the chromatic number
+    pub fn clique_lower_bound(&self) -> usize {
+        let mut best = 0;
+        for start in self.node_edges.keys() {
+            let mut clique = vec![*start];
+            let mut candidates: Vec<NodeE> = self.neighbours(start).into_iter().collect();
+            candidates.sort_by_key(|node| (std::cmp::Reverse(self.neighbours(node).len()), node.0));
+            for node in candidates {
+                let adj = self.neighbours(&node);
+                if clique.iter().all(|c| adj.contains(c)) {
+                    clique.push(node);
+                }
+            }
+            best = best.max(clique.len());
+        }
+        best
+    }
+}
+
+/// A graph's nodes in the order the exact coloring goes through them, with a
+/// coloring to improve on, that can be handed off to a background thread
+pub struct ColoringProblem {
+    order: Vec<NodeE>,
+    /// The neighbours of each node that come before it in `order`, the only
+    /// ones that matter when backtracking
+    earlier: Vec<Vec<usize>>,
+    start: Coloring,
+}
+
+impl ColoringProblem {
+    /// Find a coloring with the fewest possible colors, searching downwards
+    /// from the DSatur coloring
+    pub fn solve(&self, cancel: &AtomicBool) -> Result<Coloring, Cancelled> {
+        fn search(
+            i: usize,
+            k: usize,
+            colors: &mut Vec<usize>,
+            earlier: &[Vec<usize>],
+            cancel: &AtomicBool,
+        ) -> Result<bool, Cancelled> {
+            if cancel.load(Ordering::Relaxed) {
+                return Err(Cancelled);
+            }
+            if i == earlier.len() {
+                return Ok(true);
+            }
+            // Only try one new color class at a time to skip symmetric colorings
+            let max_used = colors.iter().copied().max().map_or(0, |c| c + 1);
+            for c in 0..k.min(max_used + 1) {
+                if earlier[i].iter().all(|&j| colors[j] != c) {
+                    colors.push(c);
+                    if search(i + 1, k, colors, earlier, cancel)? {
+                        return Ok(true);
+                    }
+                    colors.pop();
+                }
+            }
+            Ok(false)
+        }
+
+        let mut best = self.start.clone();
+        let mut k = best.values().copied().max().map_or(0, |c| c + 1);
+        while k > 1 {
+            let mut colors = Vec::new();
+            if !search(0, k - 1, &mut colors, &self.earlier, cancel)? {
+                break;
+            }
+            best = self.order.iter().copied().zip(colors).collect();
+            k -= 1;
+        }
+        Ok(best)
+    }
+}
+
+/// The most edges the exact edge coloring will attempt
+pub static EXACT_EDGE_COLORING_LIMIT: usize = 40;
+
+#[derive(Copy, Clone, Default, PartialEq, Eq)]
+pub enum EdgeColoringMethod {
+    /// Misra–Gries, which never uses more than Δ + 1 colors on a simple graph
+    #[default]
+    MisraGries,
+    /// Backtracking search for a coloring with only Δ colors
+    Exact,
+}
+
+impl core::fmt::Display for EdgeColoringMethod {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            EdgeColoringMethod::MisraGries => write!(f, "Misra–Gries"),
+            EdgeColoringMethod::Exact => write!(f, "Exact"),
+        }
+    }
+}
+
+pub struct EdgeColoring {
+    pub colors: HashMap<EdgeE, usize>,
+    pub max_degree: usize,
+    /// Whether the graph is known to need Δ (class 1) or Δ + 1 (class 2) colors
+    pub class: Option<u8>,
+    /// Parallel edges mean Vizing's bound doesn't hold, so a plain greedy
+    /// coloring is used instead
+    pub multigraph: bool,
+}
+
+impl EdgeColoring {
+    pub fn color_count(&self) -> usize {
+        self.colors.values().copied().max().map_or(0, |c| c + 1)
+    }
+}
+
+fn misra_gries(n: usize, edges: &[(usize, usize)], max_degree: usize) -> Vec<usize> {
+    let mut adj = vec![Vec::new(); n];
+    for &(u, v) in edges {
+        adj[u].push(v);
+        adj[v].push(u);
+    }
+    let mut color: Vec<Vec<Option<usize>>> = vec![vec![None; n]; n];
+    let free = |color: &Vec<Vec<Option<usize>>>, x: usize, c: usize| {
+        adj[x].iter().all(|&y| color[x][y] != Some(c))
+    };
+
+    for &(u, v) in edges {
+        // Build a maximal fan around u, each edge's color must be free on the
+        // previous fan vertex
+        let mut fan = vec![v];
+        let mut in_fan = vec![false; n];
+        in_fan[v] = true;
+        while let Some(w) = adj[u].iter().copied().find(|&w| {
+            let last = *fan.last().unwrap();
+            !in_fan[w] && color[u][w].is_some_and(|c| free(&color, last, c))
+        }) {
+            in_fan[w] = true;
+            fan.push(w);
+        }
+
+        let c = (0..=max_degree).find(|&c| free(&color, u, c)).unwrap();
+        let d = (0..=max_degree)
+            .find(|&d| free(&color, *fan.last().unwrap(), d))
+            .unwrap();
+
+        // Swap c and d along the path of alternating d/c edges from u
+        if c != d {
+            let mut path = Vec::new();
+            let (mut x, mut want) = (u, d);
+            while let Some(y) = adj[x].iter().copied().find(|&y| color[x][y] == Some(want)) {
+                path.push((x, y));
+                x = y;
+                want = if want == d { c } else { d };
+            }
+            for (x, y) in path {
+                let swapped = if color[x][y] == Some(c) { d } else { c };
+                color[x][y] = Some(swapped);
+                color[y][x] = Some(swapped);
+            }
+        }
+
+        // Find the first fan vertex with d free that still ends a valid fan
+        let end = (0..fan.len())
+            .find(|&i| {
+                free(&color, fan[i], d)
+                    && (0..i).all(|j| {
+                        color[u][fan[j + 1]].is_some_and(|cc| free(&color, fan[j], cc))
+                    })
+            })
+            .unwrap();
+
+        // Rotate the fan up to that vertex and color the freed edge with d
+        for j in 0..end {
+            let next = color[u][fan[j + 1]];
+            color[u][fan[j]] = next;
+            color[fan[j]][u] = next;
+        }
+        color[u][fan[end]] = Some(d);
+        color[fan[end]][u] = Some(d);
+    }
+
+    edges.iter().map(|&(u, v)| color[u][v].unwrap()).collect()
+}
+
+fn greedy_edge_coloring(n: usize, edges: &[(usize, usize)]) -> Vec<usize> {
+    let mut used = vec![HashSet::new(); n];
+    edges
+        .iter()
+        .map(|&(u, v)| {
+            let c = (0..).find(|c| !used[u].contains(c) && !used[v].contains(c)).unwrap();
+            used[u].insert(c);
+            used[v].insert(c);
+            c
+        })
+        .collect()
+}
+
+/// Search for an edge coloring that uses at most k colors
+fn exact_edge_coloring(
+    n: usize,
+    edges: &[(usize, usize)],
+    k: usize,
+    cancel: &AtomicBool,
+) -> Result<Option<Vec<usize>>, Cancelled> {
+    fn search(
+        i: usize,
+        k: usize,
+        edges: &[(usize, usize)],
+        colors: &mut Vec<usize>,
+        used: &mut Vec<Vec<bool>>,
+        cancel: &AtomicBool,
+    ) -> Result<bool, Cancelled> {
+        if cancel.load(Ordering::Relaxed) {
+            return Err(Cancelled);
+        }
+        if i == edges.len() {
+            return Ok(true);
+        }
+        let (u, v) = edges[i];
+        // Only try one new color class at a time to skip symmetric colorings
+        let max_used = colors.iter().copied().max().map_or(0, |c| c + 1);
+        for c in 0..k.min(max_used + 1) {
+            if !used[u][c] && !used[v][c] {
+                used[u][c] = true;
+                used[v][c] = true;
+                colors.push(c);
+                if search(i + 1, k, edges, colors, used, cancel)? {
+                    return Ok(true);
+                }
+                colors.pop();
+                used[u][c] = false;
+                used[v][c] = false;
+            }
+        }
+        Ok(false)
+    }
+
+    let mut colors = Vec::new();
+    let mut used = vec![vec![false; k]; n];
+    Ok(search(0, k, edges, &mut colors, &mut used, cancel)?.then_some(colors))
+}
+
+/// A graph's edges as pairs of node indices, in the order they're colored,
+/// that can be handed off to a background thread
+pub struct EdgeColoringProblem {
+    order: Vec<EdgeE>,
+    pairs: Vec<(usize, usize)>,
+    node_count: usize,
+    max_degree: usize,
+    multigraph: bool,
+}
+
+impl Graph {
+    pub fn edge_coloring_problem(&self) -> Result<EdgeColoringProblem, String> {
+        if self.has_loop() {
+            return Err("The graph has loops, so it has no proper edge coloring".into());
+        }
+
+        let mut nodes: Vec<NodeE> = self.node_edges.keys().copied().collect();
+        nodes.sort_by_key(|node| node.0);
+        let index: HashMap<NodeE, usize> =
+            nodes.iter().enumerate().map(|(i, node)| (*node, i)).collect();
+
+        // Go through the edges breadth first so neighbouring edges are colored
+        // close together, which keeps the backtracking search shallow
+        let mut order: Vec<EdgeE> = Vec::new();
+        let mut seen = HashSet::new();
+        for start in nodes.iter() {
+            let mut queue = std::collections::VecDeque::from([*start]);
+            while let Some(node) = queue.pop_front() {
+                for edge in self.node_edges[&node].iter() {
+                    if seen.insert(*edge) {
+                        order.push(*edge);
+                        let (a, b) = self.edge_nodes[edge];
+                        queue.push_back(if a == node { b } else { a });
+                    }
+                }
+            }
+        }
+        let pairs: Vec<(usize, usize)> = order
+            .iter()
+            .map(|edge| {
+                let (a, b) = self.edge_nodes[edge];
+                (index[&a], index[&b])
+            })
+            .collect();
+
+        let max_degree = self.node_edges.values().map(|e| e.len()).max().unwrap_or(0);
+        let multigraph = {
+            let mut distinct = HashSet::new();
+            !pairs.iter().all(|&(a, b)| distinct.insert((a.min(b), a.max(b))))
+        };
+
+        Ok(EdgeColoringProblem {
+            order,
+            pairs,
+            node_count: nodes.len(),
+            max_degree,
+            multigraph,
+        })
+    }
+}
+
+impl EdgeColoringProblem {
+    pub fn edge_count(&self) -> usize {
+        self.pairs.len()
+    }
+
+    /// Color the edges with Misra–Gries, then with the exact method search
+    /// for a coloring with only Δ colors if that used Δ + 1. Only the search
+    /// can be cancelled.
+    pub fn solve(&self, method: EdgeColoringMethod, cancel: &AtomicBool) -> Result<EdgeColoring, Cancelled> {
+        let (n, pairs, max_degree) = (self.node_count, &self.pairs, self.max_degree);
+        let (colors, class) = if self.multigraph {
+            (greedy_edge_coloring(n, pairs), None)
+        } else {
+            let colors = misra_gries(n, pairs, max_degree);
+            let used = colors.iter().copied().max().map_or(0, |c| c + 1);
+            if used <= max_degree {
+                (colors, Some(1))
+            } else if method == EdgeColoringMethod::Exact {
+                match exact_edge_coloring(n, pairs, max_degree, cancel)? {
+                    Some(colors) => (colors, Some(1)),
+                    None => (colors, Some(2)),
+                }
+            } else {
+                (colors, None)
+            }
+        };
+
+        Ok(EdgeColoring {
+            colors: self.order.iter().copied().zip(colors).collect(),
+            max_degree,
+            class,
+            multigraph: self.multigraph,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// The outer 5-cycle, the spokes, and the inner pentagram
+    fn petersen() -> Graph {
+        let mut pairs = Vec::new();
+        for i in 0..5 {
+            pairs.push((i, (i + 1) % 5));
+            pairs.push((i, i + 5));
+            pairs.push((i + 5, (i + 2) % 5 + 5));
+        }
+        Graph::from_pairs(10, &pairs, false).0
+    }
+
+    fn color_count(coloring: &Coloring) -> usize {
+        coloring.values().copied().max().map_or(0, |c| c + 1)
+    }
+
+    fn is_proper(graph: &Graph, coloring: &Coloring) -> bool {
+        graph
+            .edge_nodes
+            .values()
+            .all(|(a, b)| a == b || coloring[a] != coloring[b])
+    }
+
+    #[test]
+    fn petersen_chromatic_number() {
+        let graph = petersen();
+        let coloring = graph.coloring_problem().solve(&AtomicBool::new(false)).unwrap();
+        assert!(is_proper(&graph, &coloring));
+        assert_eq!(color_count(&coloring), 3);
+
+        let greedy = graph.dsatur_coloring();
+        assert!(is_proper(&graph, &greedy));
+        assert!(color_count(&greedy) >= 3);
+    }
+
+    #[test]
+    fn complete_graph_needs_every_color() {
+        let pairs: Vec<_> = (0..5).flat_map(|a| (a + 1..5).map(move |b| (a, b))).collect();
+        let graph = Graph::from_pairs(5, &pairs, false).0;
+        let coloring = graph.coloring_problem().solve(&AtomicBool::new(false)).unwrap();
+        assert_eq!(color_count(&coloring), 5);
+    }
+
+    #[test]
+    fn petersen_is_class_2() {
+        let problem = petersen().edge_coloring_problem().unwrap();
+        let coloring = problem.solve(EdgeColoringMethod::Exact, &AtomicBool::new(false)).unwrap();
+        assert_eq!(coloring.max_degree, 3);
+        assert_eq!(coloring.class, Some(2));
+        assert_eq!(coloring.color_count(), 4);
+    }
+
+    #[test]
+    fn cancelled_search_stops() {
+        let graph = petersen();
+        assert!(graph.coloring_problem().solve(&AtomicBool::new(true)).is_err());
+        let problem = graph.edge_coloring_problem().unwrap();
+        assert!(problem.solve(EdgeColoringMethod::Exact, &AtomicBool::new(true)).is_err());
+    }
+}
@@ -0,0 +1,287 @@
+use std::collections::{HashMap, HashSet};
+
+use bevy::prelude::*;
+
+use super::{generate::Rng, Graph, NodeE};
+
+static LABEL_PROPAGATION_SEED: u64 = 0x5EED;
+static LABEL_PROPAGATION_ROUNDS: usize = 100;
+
+#[derive(Copy, Clone, Default, PartialEq, Eq)]
+pub enum CommunityMethod {
+    /// Greedily move nodes between communities to raise the modularity, then
+    /// merge each community into a single node and repeat
+    #[default]
+    Louvain,
+    /// Every node repeatedly takes the most common label among its neighbours
+    LabelPropagation,
+}
+
+impl core::fmt::Display for CommunityMethod {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            CommunityMethod::Louvain => write!(f, "Louvain"),
+            CommunityMethod::LabelPropagation => write!(f, "Label Propagation"),
+        }
+    }
+}
+
+pub struct Communities {
+    /// The community of each node, numbered from 0 by decreasing size
+    pub of: HashMap<NodeE, usize>,
+    pub count: usize,
+    pub modularity: f64,
+}
+
+impl Communities {
+    /// The nodes in each community
+    pub fn members(&self) -> Vec<Vec<NodeE>> {
+        let mut members = vec![Vec::new(); self.count];
+        for (node, community) in self.of.iter() {
+            members[*community].push(*node);
+        }
+        members
+    }
+}
+
+/// The last communities found, along with whether the physics simulation
+/// should use them to pull communities together and push them apart
+#[derive(Resource, Default)]
+pub struct CommunityLayout {
+    pub communities: Option<Communities>,
+    pub forces: bool,
+}
+
+/// An undirected weighted graph over node indices. Loops count twice towards
+/// their node's row, so each row sums to the node's degree.
+#[derive(Clone)]
+struct WeightedGraph {
+    adj: Vec<HashMap<usize, f64>>,
+}
+
+impl WeightedGraph {
+    fn degree(&self, i: usize) -> f64 {
+        self.adj[i].values().sum()
+    }
+
+    /// Twice the total edge weight
+    fn total(&self) -> f64 {
+        (0..self.adj.len()).map(|i| self.degree(i)).sum()
+    }
+
+    fn modularity(&self, community: &[usize]) -> f64 {
+        let m2 = self.total();
+        if m2 == 0.0 {
+            return 0.0;
+        }
+
+        let count = community.iter().max().map_or(0, |c| c + 1);
+        let (mut inside, mut tot) = (vec![0.0; count], vec![0.0; count]);
+        for (i, row) in self.adj.iter().enumerate() {
+            tot[community[i]] += self.degree(i);
+            for (j, w) in row.iter() {
+                if community[i] == community[*j] {
+                    inside[community[i]] += w;
+                }
+            }
+        }
+        (0..count).map(|c| inside[c] / m2 - (tot[c] / m2).powi(2)).sum()
+    }
+
+    /// Move single nodes to whichever neighbouring community raises the
+    /// modularity most until nothing improves. Returns the community of each
+    /// node and whether anything moved.
+    fn local_moving(&self) -> (Vec<usize>, bool) {
+        let n = self.adj.len();
+        let m2 = self.total();
+        let degree: Vec<f64> = (0..n).map(|i| self.degree(i)).collect();
+        let mut community: Vec<usize> = (0..n).collect();
+        let mut tot = degree.clone();
+
+        let mut improved = false;
+        let mut moved = true;
+        while moved {
+            moved = false;
+            for i in 0..n {
+                let mut links: HashMap<usize, f64> = HashMap::new();
+                for (j, w) in self.adj[i].iter() {
+                    if *j != i {
+                        *links.entry(community[*j]).or_default() += w;
+                    }
+                }
+
+                let old = community[i];
+                tot[old] -= degree[i];
+                // The modularity gained by joining a community, up to a constant
+                let gain = |c: usize| links.get(&c).copied().unwrap_or_default() - tot[c] * degree[i] / m2;
+
+                let mut best = (old, gain(old));
+                let mut candidates: Vec<usize> = links.keys().copied().collect();
+                candidates.sort();
+                for c in candidates {
+                    let g = gain(c);
+                    if g > best.1 + 1e-12 {
+                        best = (c, g);
+                    }
+                }
+
+                tot[best.0] += degree[i];
+                if best.0 != old {
+                    community[i] = best.0;
+                    moved = true;
+                    improved = true;
+                }
+            }
+        }
+        (community, improved)
+    }
+
+    /// Merge each community into a single node
+    fn aggregate(&self, community: &[usize], count: usize) -> WeightedGraph {
+        let mut adj = vec![HashMap::new(); count];
+        for (i, row) in self.adj.iter().enumerate() {
+            for (j, w) in row.iter() {
+                *adj[community[i]].entry(community[*j]).or_default() += w;
+            }
+        }
+        WeightedGraph { adj }
+    }
+}
+
+/// Number communities from 0 in order of decreasing size, breaking ties by
+/// their first node
+fn renumber(community: &[usize]) -> (Vec<usize>, usize) {
+    let mut sizes: HashMap<usize, (usize, usize)> = HashMap::new();
+    for (i, c) in community.iter().enumerate() {
+        sizes.entry(*c).or_insert((0, i)).0 += 1;
+    }
+    let mut order: Vec<(usize, (usize, usize))> = sizes.into_iter().collect();
+    order.sort_by_key(|(_, (size, first))| (std::cmp::Reverse(*size), *first));
+    let index: HashMap<usize, usize> = order.iter().enumerate().map(|(i, (c, _))| (*c, i)).collect();
+    (community.iter().map(|c| index[c]).collect(), order.len())
+}
+
+fn louvain(graph: &WeightedGraph) -> Vec<usize> {
+    let mut membership: Vec<usize> = (0..graph.adj.len()).collect();
+    let mut current = graph.clone();
+    loop {
+        let (community, improved) = current.local_moving();
+        if !improved {
+            return membership;
+        }
+        let (community, count) = renumber(&community);
+        for c in membership.iter_mut() {
+            *c = community[*c];
+        }
+        current = current.aggregate(&community, count);
+    }
+}
+
+fn label_propagation(graph: &WeightedGraph) -> Vec<usize> {
+    let n = graph.adj.len();
+    let mut rng = Rng::new(LABEL_PROPAGATION_SEED);
+    let mut labels: Vec<usize> = (0..n).collect();
+    let mut order: Vec<usize> = (0..n).collect();
+
+    for _ in 0..LABEL_PROPAGATION_ROUNDS {
+        for i in (1..n).rev() {
+            order.swap(i, rng.below(i + 1));
+        }
+
+        let mut changed = false;
+        for &i in order.iter() {
+            let mut counts: HashMap<usize, f64> = HashMap::new();
+            for (j, w) in graph.adj[i].iter() {
+                if *j != i {
+                    *counts.entry(labels[*j]).or_default() += w;
+                }
+            }
+            let Some(max) = counts.values().copied().reduce(f64::max) else {
+                continue;
+            };
+            if counts.get(&labels[i]) == Some(&max) {
+                continue;
+            }
+
+            // Ties between the most common labels are broken at random
+            let mut best: Vec<usize> = counts.iter().filter(|(_, w)| **w == max).map(|(l, _)| *l).collect();
+            best.sort();
+            labels[i] = best[rng.below(best.len())];
+            changed = true;
+        }
+        if !changed {
+            break;
+        }
+    }
+    labels
+}
+
+impl Graph {
+    fn weighted_graph(&self, nodes: &[NodeE]) -> WeightedGraph {
+        let index: HashMap<NodeE, usize> = nodes.iter().enumerate().map(|(i, node)| (*node, i)).collect();
+        let mut adj = vec![HashMap::new(); nodes.len()];
+        for (start, end) in self.edge_nodes.values() {
+            let (s, e) = (index[start], index[end]);
+            *adj[s].entry(e).or_default() += 1.0;
+            *adj[e].entry(s).or_default() += 1.0;
+        }
+        WeightedGraph { adj }
+    }
+
+    /// Split the nodes into communities, ignoring edge direction
+    pub fn communities(&self, method: CommunityMethod) -> Communities {
+        let mut nodes: Vec<NodeE> = self.node_edges.keys().copied().collect();
+        nodes.sort_by_key(|node| node.0);
+        let graph = self.weighted_graph(&nodes);
+
+        let community = match method {
+            CommunityMethod::Louvain => louvain(&graph),
+            CommunityMethod::LabelPropagation => label_propagation(&graph),
+        };
+        let (community, count) = renumber(&community);
+
+        Communities {
+            modularity: graph.modularity(&community),
+            of: nodes.into_iter().zip(community).collect(),
+            count,
+        }
+    }
+
+    /// The number of edges between a node's neighbours, and the number there
+    /// could be
+    fn neighbour_links(&self, node: &NodeE) -> (usize, usize) {
+        let neighbours = self.neighbours(node);
+        let k = neighbours.len();
+        let mut links = HashSet::new();
+        for a in neighbours.iter() {
+            for b in self.neighbours(a).intersection(&neighbours) {
+                links.insert(if a.0 < b.0 { (*a, *b) } else { (*b, *a) });
+            }
+        }
+        (links.len(), k * k.saturating_sub(1) / 2)
+    }
+
+    /// The fraction of pairs of a node's neighbours that are adjacent
+    pub fn local_clustering(&self, node: &NodeE) -> f64 {
+        match self.neighbour_links(node) {
+            (_, 0) => 0.0,
+            (links, pairs) => links as f64 / pairs as f64,
+        }
+    }
+
+    /// The transitivity of the graph, the fraction of connected triples that
+    /// close into triangles, and the average local clustering coefficient
+    pub fn global_clustering(&self) -> (f64, f64) {
+        let (mut links, mut pairs, mut local) = (0, 0, 0.0);
+        for node in self.node_edges.keys() {
+            let (l, p) = self.neighbour_links(node);
+            links += l;
+            pairs += p;
+            local += if p > 0 { l as f64 / p as f64 } else { 0.0 };
+        }
+
+        let transitivity = if pairs > 0 { links as f64 / pairs as f64 } else { 0.0 };
+        let average = if self.node_count() > 0 { local / self.node_count() as f64 } else { 0.0 };
+        (transitivity, average)
+    }
+}
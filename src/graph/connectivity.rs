@@ -0,0 +1,210 @@
+use std::collections::{HashMap, HashSet};
+
+use super::{EdgeE, Graph, NodeE};
+
+/// Everything found by a single pass of Tarjan's algorithm, ignoring edge
+/// direction
+#[derive(Default)]
+pub struct Biconnectivity {
+    /// Edges whose removal disconnects their component
+    pub bridges: HashSet<EdgeE>,
+    /// Nodes whose removal disconnects their component
+    pub cut_vertices: HashSet<NodeE>,
+    /// The edges of each biconnected component (block). A bridge is a block
+    /// on its own, and so is every loop.
+    pub components: Vec<Vec<EdgeE>>,
+}
+
+/// The same as [`Biconnectivity`] for a graph given as edges between node
+/// indices, with edges referred to by their index. Loops are left out.
+#[derive(Default)]
+pub struct Blocks {
+    pub bridges: Vec<usize>,
+    pub cut_vertices: Vec<usize>,
+    pub components: Vec<Vec<usize>>,
+}
+
+struct Frame {
+    node: usize,
+    parent: Option<usize>,
+    next: usize,
+}
+
+/// Find every bridge, articulation point and biconnected component in
+/// linear time
+pub fn blocks(n: usize, edges: &[(usize, usize)]) -> Blocks {
+    let mut adj: Vec<Vec<(usize, usize)>> = vec![Vec::new(); n];
+    for (i, &(a, b)) in edges.iter().enumerate() {
+        if a != b {
+            adj[a].push((i, b));
+            adj[b].push((i, a));
+        }
+    }
+
+    let mut result = Blocks::default();
+    let mut disc: Vec<Option<usize>> = vec![None; n];
+    let mut low = vec![0; n];
+    let mut time = 0;
+    let mut edge_stack: Vec<usize> = Vec::new();
+    let mut is_cut = vec![false; n];
+
+    for root in 0..n {
+        if disc[root].is_some() {
+            continue;
+        }
+
+        disc[root] = Some(time);
+        low[root] = time;
+        time += 1;
+        let mut root_children = 0;
+        let mut stack = vec![Frame { node: root, parent: None, next: 0 }];
+
+        while let Some(top) = stack.last_mut() {
+            if let Some(&(edge, adj)) = adj[top.node].get(top.next) {
+                top.next += 1;
+                // Skip the edge we came in on, but not edges parallel to it
+                if Some(edge) == top.parent {
+                    continue;
+                }
+
+                let node = top.node;
+                match disc[adj] {
+                    None => {
+                        edge_stack.push(edge);
+                        disc[adj] = Some(time);
+                        low[adj] = time;
+                        time += 1;
+                        stack.push(Frame { node: adj, parent: Some(edge), next: 0 });
+                    }
+                    Some(d) if d < disc[node].unwrap() => {
+                        // A back edge to an ancestor
+                        edge_stack.push(edge);
+                        low[node] = low[node].min(d);
+                    }
+                    _ => (),
+                }
+                continue;
+            }
+
+            let child = stack.pop().unwrap();
+            let Some(parent) = stack.last() else {
+                break;
+            };
+            let (p, c) = (parent.node, child.node);
+            low[p] = low[p].min(low[c]);
+
+            let disc_p = disc[p].unwrap();
+            if low[c] >= disc_p {
+                // Nothing below the child reaches above the parent, so the
+                // parent separates this block from the rest
+                let tree_edge = child.parent.unwrap();
+                let mut block = Vec::new();
+                while let Some(edge) = edge_stack.pop() {
+                    block.push(edge);
+                    if edge == tree_edge {
+                        break;
+                    }
+                }
+                result.components.push(block);
+
+                if stack.len() > 1 {
+                    is_cut[p] = true;
+                } else {
+                    root_children += 1;
+                }
+            }
+            if low[c] > disc_p {
+                result.bridges.push(child.parent.unwrap());
+            }
+        }
+
+        if root_children > 1 {
+            is_cut[root] = true;
+        }
+    }
+    result.cut_vertices = (0..n).filter(|i| is_cut[*i]).collect();
+    result
+}
+
+impl Graph {
+    /// Find every bridge, articulation point and biconnected component in
+    /// linear time
+    pub fn biconnectivity(&self) -> Biconnectivity {
+        let mut nodes: Vec<NodeE> = self.node_edges.keys().copied().collect();
+        nodes.sort_by_key(|node| node.0);
+        let index: HashMap<NodeE, usize> = nodes.iter().enumerate().map(|(i, node)| (*node, i)).collect();
+
+        let mut edges: Vec<EdgeE> = self.edge_nodes.keys().copied().collect();
+        edges.sort_by_key(|edge| edge.0);
+        let pairs: Vec<(usize, usize)> = edges
+            .iter()
+            .map(|edge| {
+                let (start, end) = self.edge_nodes[edge];
+                (index[&start], index[&end])
+            })
+            .collect();
+
+        let blocks = blocks(nodes.len(), &pairs);
+        let mut components: Vec<Vec<EdgeE>> = blocks
+            .components
+            .iter()
+            .map(|block| block.iter().map(|i| edges[*i]).collect())
+            .collect();
+        components.extend(
+            pairs
+                .iter()
+                .zip(edges.iter())
+                .filter(|((a, b), _)| a == b)
+                .map(|(_, edge)| vec![*edge]),
+        );
+
+        Biconnectivity {
+            bridges: blocks.bridges.iter().map(|i| edges[*i]).collect(),
+            cut_vertices: blocks.cut_vertices.iter().map(|i| nodes[*i]).collect(),
+            components,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn path_is_all_bridges() {
+        let result = blocks(4, &[(0, 1), (1, 2), (2, 3)]);
+        assert_eq!(result.cut_vertices, vec![1, 2]);
+        let mut bridges = result.bridges.clone();
+        bridges.sort();
+        assert_eq!(bridges, vec![0, 1, 2]);
+        assert_eq!(result.components.len(), 3);
+    }
+
+    #[test]
+    fn cycle_is_one_block() {
+        let result = blocks(4, &[(0, 1), (1, 2), (2, 3), (3, 0)]);
+        assert!(result.cut_vertices.is_empty());
+        assert!(result.bridges.is_empty());
+        assert_eq!(result.components.len(), 1);
+        assert_eq!(result.components[0].len(), 4);
+    }
+
+    #[test]
+    fn parallel_edges_are_not_bridges() {
+        // Two triangles sharing node 2, and a doubled edge out to node 5
+        let pairs = [(0, 1), (1, 2), (2, 0), (2, 3), (3, 4), (4, 2), (4, 5), (5, 4)];
+        let result = blocks(6, &pairs);
+        assert_eq!(result.cut_vertices, vec![2, 4]);
+        assert!(result.bridges.is_empty());
+        assert_eq!(result.components.len(), 3);
+    }
+
+    #[test]
+    fn loops_are_blocks_of_their_own() {
+        let (graph, nodes) = Graph::from_pairs(3, &[(0, 1), (1, 2), (1, 1)], false);
+        let result = graph.biconnectivity();
+        assert!(result.cut_vertices == HashSet::from([nodes[1]]));
+        assert_eq!(result.bridges.len(), 2);
+        assert_eq!(result.components.len(), 3);
+    }
+}
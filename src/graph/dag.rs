@@ -0,0 +1,217 @@
+use std::collections::{HashMap, HashSet};
+
+use bevy::prelude::*;
+
+use super::{EdgeE, GEdge, Graph, NodeE, PathPart};
+
+impl Graph {
+    fn sorted_nodes(&self) -> Vec<NodeE> {
+        let mut nodes: Vec<NodeE> = self.node_edges.keys().copied().collect();
+        nodes.sort_by_key(|node| node.0);
+        nodes
+    }
+
+    /// Edges leaving a node, along with the node they lead to
+    fn out_edges(&self, node: &NodeE) -> impl Iterator<Item = (EdgeE, NodeE)> + '_ {
+        let node = *node;
+        self.node_edges[&node].iter().filter_map(move |edge| {
+            let (start, end) = self.edge_nodes[edge];
+            (start == node).then_some((*edge, end))
+        })
+    }
+
+    /// Order the nodes so every edge points forward with Kahn's algorithm. If
+    /// the graph has a directed cycle it is returned as a closed path instead.
+    pub fn topological_order(&self) -> Result<Vec<NodeE>, Vec<PathPart>> {
+        let nodes = self.sorted_nodes();
+        let mut in_degree: HashMap<NodeE, usize> = nodes.iter().map(|node| (*node, 0)).collect();
+        for (_, end) in self.edge_nodes.values() {
+            *in_degree.get_mut(end).unwrap() += 1;
+        }
+
+        let mut ready: Vec<NodeE> = nodes.iter().copied().filter(|node| in_degree[node] == 0).collect();
+        ready.reverse();
+        let mut order = Vec::new();
+        while let Some(node) = ready.pop() {
+            order.push(node);
+            for (_, next) in self.out_edges(&node) {
+                let degree = in_degree.get_mut(&next).unwrap();
+                *degree -= 1;
+                if *degree == 0 {
+                    ready.push(next);
+                }
+            }
+        }
+
+        if order.len() == nodes.len() {
+            return Ok(order);
+        }
+
+        // Every node left over has an incoming edge from another leftover node,
+        // so walking backwards along those edges has to repeat a node
+        let left: HashSet<NodeE> = nodes.into_iter().filter(|node| in_degree[node] > 0).collect();
+        let mut node = *left.iter().min_by_key(|node| node.0).unwrap();
+        // Each node visited along with the edge that leads into it
+        let mut walk: Vec<(NodeE, EdgeE)> = Vec::new();
+        let mut seen: HashMap<NodeE, usize> = HashMap::new();
+        loop {
+            if let Some(&i) = seen.get(&node) {
+                // The walk went against the edges, so reverse it to follow them
+                let cycle = &walk[i..];
+                let mut path = vec![PathPart::without_edge(node)];
+                for (next, edge) in cycle.iter().rev() {
+                    path.push(PathPart::with_edge(*next, *edge));
+                }
+                return Err(path);
+            }
+            seen.insert(node, walk.len());
+
+            let (edge, prev) = self.node_edges[&node]
+                .iter()
+                .filter_map(|edge| {
+                    let (start, end) = self.edge_nodes[edge];
+                    (end == node && left.contains(&start)).then_some((*edge, start))
+                })
+                .min_by_key(|(edge, _)| edge.0)
+                .unwrap();
+            walk.push((node, edge));
+            node = prev;
+        }
+    }
+
+    /// The heaviest path through a directed acyclic graph by edge weight, the
+    /// critical path when edges are tasks with durations. Returns `None` if the
+    /// graph has a cycle.
+    pub fn longest_path(&self, q_edge: &Query<&GEdge>) -> Option<(Vec<PathPart>, i64)> {
+        let order = self.topological_order().ok()?;
+
+        // The heaviest path ending at each node and the edge it arrives by, a
+        // path can also start fresh at any node
+        let mut best: HashMap<NodeE, (i64, Option<EdgeE>)> =
+            order.iter().map(|node| (*node, (0, None))).collect();
+        for node in order.iter() {
+            let length = best[node].0;
+            for (edge, next) in self.out_edges(node) {
+                let weight = q_edge.get(*edge).map(|e| e.weight).unwrap_or(1) as i64;
+                let entry = best.get_mut(&next).unwrap();
+                if length + weight > entry.0 {
+                    *entry = (length + weight, Some(edge));
+                }
+            }
+        }
+
+        let (mut node, (length, _)) = order
+            .iter()
+            .map(|node| (*node, best[node]))
+            .max_by_key(|(_, (length, _))| *length)?;
+
+        let mut path = Vec::new();
+        while let (_, Some(edge)) = best[&node] {
+            path.push(PathPart::with_edge(node, edge));
+            node = self.edge_nodes[&edge].0;
+        }
+        path.push(PathPart::without_edge(node));
+        path.reverse();
+        Some((path, length))
+    }
+
+    fn reachable(&self, from: &NodeE) -> HashSet<NodeE> {
+        let mut visited = HashSet::new();
+        let mut stack = vec![*from];
+        while let Some(node) = stack.pop() {
+            for (_, next) in self.out_edges(&node) {
+                if visited.insert(next) {
+                    stack.push(next);
+                }
+            }
+        }
+        visited
+    }
+
+    /// The edges missing from the transitive closure, every pair of nodes
+    /// where the second can be reached from the first but isn't adjacent
+    pub fn transitive_closure(&self) -> Vec<(NodeE, NodeE)> {
+        let mut missing = Vec::new();
+        for node in self.sorted_nodes() {
+            let adjacent: HashSet<NodeE> = self.out_edges(&node).map(|(_, next)| next).collect();
+            let mut reachable: Vec<NodeE> = self
+                .reachable(&node)
+                .into_iter()
+                .filter(|other| *other != node && !adjacent.contains(other))
+                .collect();
+            reachable.sort_by_key(|other| other.0);
+            missing.extend(reachable.into_iter().map(|other| (node, other)));
+        }
+        missing
+    }
+
+    /// The edges to remove for the transitive reduction of a directed acyclic
+    /// graph: those implied by a longer path, along with parallel copies.
+    /// Returns `None` if the graph has a cycle, where the reduction isn't unique.
+    pub fn transitive_reduction(&self) -> Option<Vec<EdgeE>> {
+        self.topological_order().ok()?;
+
+        let mut redundant = Vec::new();
+        for node in self.sorted_nodes() {
+            let mut out: Vec<(EdgeE, NodeE)> = self.out_edges(&node).collect();
+            out.sort_by_key(|(edge, _)| edge.0);
+
+            // Anything reachable through a successor doesn't need a direct edge
+            let mut implied = HashSet::new();
+            for (_, next) in out.iter() {
+                implied.extend(self.reachable(next));
+            }
+
+            let mut kept = HashSet::new();
+            for (edge, next) in out {
+                if implied.contains(&next) || !kept.insert(next) {
+                    redundant.push(edge);
+                }
+            }
+        }
+        Some(redundant)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn order_puts_every_edge_forward() {
+        let pairs = [(3, 1), (0, 1), (0, 2), (1, 4), (2, 4)];
+        let (graph, nodes) = Graph::from_pairs(5, &pairs, true);
+        let order = graph.topological_order().ok().unwrap();
+        let position = |i: usize| order.iter().position(|node| *node == nodes[i]).unwrap();
+        assert_eq!(order.len(), 5);
+        assert!(pairs.iter().all(|&(a, b)| position(a) < position(b)));
+    }
+
+    #[test]
+    fn cycle_is_returned_closed() {
+        // A cycle 1 -> 2 -> 3 -> 1, with 0 leading into it
+        let (graph, nodes) = Graph::from_pairs(4, &[(0, 1), (1, 2), (2, 3), (3, 1)], true);
+        let Err(cycle) = graph.topological_order() else {
+            panic!("a cyclic graph was sorted");
+        };
+        assert_eq!(cycle.len(), 4);
+        assert!(cycle[0].node == cycle[3].node);
+        assert!(cycle.iter().all(|part| part.node != nodes[0]));
+        for pair in cycle.windows(2) {
+            assert!(graph.edge_nodes[&pair[1].edge.unwrap()] == (pair[0].node, pair[1].node));
+        }
+        assert!(graph.transitive_reduction().is_none());
+    }
+
+    #[test]
+    fn shortcut_is_redundant() {
+        let (graph, nodes) = Graph::from_pairs(3, &[(0, 1), (1, 2), (0, 2), (0, 1)], true);
+        let mut redundant = graph.transitive_reduction().unwrap();
+        redundant.sort_by_key(|edge| edge.0);
+        // The shortcut and the second copy of 0 -> 1
+        assert!(redundant == vec![EdgeE(Entity::from_raw(5)), EdgeE(Entity::from_raw(6))]);
+
+        let (graph, _) = Graph::from_pairs(3, &[(0, 1), (1, 2)], true);
+        assert!(graph.transitive_closure() == vec![(nodes[0], nodes[2])]);
+    }
+}
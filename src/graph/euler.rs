@@ -0,0 +1,206 @@
+use std::collections::{HashMap, HashSet};
+
+use super::{EdgeE, Graph, NodeE, OppositeNode, PathPart};
+
+pub enum EulerTrail {
+    /// A closed trail using every edge exactly once
+    Circuit(Vec<PathPart>),
+    /// An open trail using every edge exactly once
+    Path(Vec<PathPart>),
+}
+
+/// The reason a graph has no Eulerian trail
+pub enum NotEulerian {
+    NoEdges,
+    /// The edges are spread over this many components
+    Disconnected(usize),
+    /// Undirected graphs can have at most two nodes of odd degree
+    OddDegree(Vec<NodeE>),
+    /// Directed graphs need every node to have as many edges in as out,
+    /// except for the start and end of a path. Holds each unbalanced node
+    /// with its out degree minus its in degree.
+    Imbalanced(Vec<(NodeE, i32)>),
+}
+
+impl Graph {
+    /// Count the components that contain at least one edge, ignoring direction
+    fn edge_components(&self) -> usize {
+        let mut visited = HashSet::new();
+        let mut components = 0;
+        for (node, edges) in self.node_edges.iter() {
+            if edges.is_empty() || visited.contains(node) {
+                continue;
+            }
+
+            components += 1;
+            visited.insert(*node);
+            let mut stack = vec![*node];
+            while let Some(node) = stack.pop() {
+                for adj in self.neighbours(&node) {
+                    if visited.insert(adj) {
+                        stack.push(adj);
+                    }
+                }
+            }
+        }
+        components
+    }
+
+    /// Find a trail that uses every edge exactly once with Hierholzer's
+    /// algorithm, following edge directions if the graph is directed
+    pub fn eulerian_trail(&self) -> Result<EulerTrail, NotEulerian> {
+        if self.edge_nodes.is_empty() {
+            return Err(NotEulerian::NoEdges);
+        }
+        let components = self.edge_components();
+        if components > 1 {
+            return Err(NotEulerian::Disconnected(components));
+        }
+
+        let mut nodes: Vec<NodeE> = self.node_edges.keys().copied().collect();
+        nodes.sort_by_key(|node| node.0);
+        let with_edges = nodes.iter().copied().find(|node| !self.node_edges[node].is_empty());
+
+        let (start, closed) = if self.directed {
+            let mut balance: HashMap<NodeE, i32> = HashMap::new();
+            for (start, end) in self.edge_nodes.values() {
+                *balance.entry(*start).or_default() += 1;
+                *balance.entry(*end).or_default() -= 1;
+            }
+            let mut unbalanced: Vec<(NodeE, i32)> = nodes
+                .iter()
+                .map(|node| (*node, balance.get(node).copied().unwrap_or_default()))
+                .filter(|(_, b)| *b != 0)
+                .collect();
+
+            match unbalanced.as_slice() {
+                [] => (with_edges, true),
+                [(a, 1), (_, -1)] | [(_, -1), (a, 1)] => (Some(*a), false),
+                _ => {
+                    unbalanced.sort_by_key(|(_, b)| -b.abs());
+                    return Err(NotEulerian::Imbalanced(unbalanced));
+                }
+            }
+        } else {
+            // Loops add two to the degree of their node, so are left out here
+            let odd: Vec<NodeE> = nodes
+                .iter()
+                .filter(|node| {
+                    self.node_edges[node]
+                        .iter()
+                        .filter(|edge| !matches!(self.opposite(node, edge), OppositeNode::Loop))
+                        .count()
+                        % 2
+                        == 1
+                })
+                .copied()
+                .collect();
+
+            match odd.as_slice() {
+                [] => (with_edges, true),
+                [a, _] => (Some(*a), false),
+                _ => return Err(NotEulerian::OddDegree(odd)),
+            }
+        };
+
+        let mut used: HashSet<EdgeE> = HashSet::new();
+        let mut stack = vec![PathPart::without_edge(start.unwrap())];
+        let mut trail = Vec::new();
+        while let Some(&part) = stack.last() {
+            let next = self.node_edges[&part.node].iter().find_map(|edge| {
+                if used.contains(edge) {
+                    return None;
+                }
+                match self.opposite(&part.node, edge) {
+                    OppositeNode::Adjacent(adj) => Some(PathPart::with_edge(adj, *edge)),
+                    OppositeNode::Loop => Some(PathPart::with_edge(part.node, *edge)),
+                    _ => None,
+                }
+            });
+
+            match next {
+                Some(next) => {
+                    used.insert(next.edge.unwrap());
+                    stack.push(next);
+                }
+                None => {
+                    trail.push(part);
+                    stack.pop();
+                }
+            }
+        }
+        trail.reverse();
+
+        Ok(if closed {
+            EulerTrail::Circuit(trail)
+        } else {
+            EulerTrail::Path(trail)
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Check the trail walks along every edge exactly once
+    fn uses_every_edge(graph: &Graph, trail: &[PathPart]) {
+        assert_eq!(trail.len(), graph.edge_nodes.len() + 1);
+        let edges: HashSet<EdgeE> = trail[1..].iter().map(|part| part.edge.unwrap()).collect();
+        assert_eq!(edges.len(), graph.edge_nodes.len());
+        for pair in trail.windows(2) {
+            let (start, end) = graph.edge_nodes[&pair[1].edge.unwrap()];
+            let (a, b) = (pair[0].node, pair[1].node);
+            assert!((start, end) == (a, b) || (!graph.directed && (start, end) == (b, a)));
+        }
+    }
+
+    #[test]
+    fn two_odd_nodes_give_a_path_between_them() {
+        // A triangle with a tail, nodes 2 and 3 have odd degree
+        let (graph, nodes) = Graph::from_pairs(4, &[(0, 1), (1, 2), (2, 0), (2, 3)], false);
+        let Ok(EulerTrail::Path(trail)) = graph.eulerian_trail() else {
+            panic!("no Eulerian path found");
+        };
+        uses_every_edge(&graph, &trail);
+        let ends = [trail.first().unwrap().node, trail.last().unwrap().node];
+        assert!(ends == [nodes[2], nodes[3]] || ends == [nodes[3], nodes[2]]);
+    }
+
+    #[test]
+    fn four_odd_nodes_have_no_trail() {
+        let pairs = [(0, 1), (0, 2), (0, 3), (1, 2), (1, 3), (2, 3)];
+        let (graph, _) = Graph::from_pairs(4, &pairs, false);
+        assert!(matches!(graph.eulerian_trail(), Err(NotEulerian::OddDegree(odd)) if odd.len() == 4));
+    }
+
+    #[test]
+    fn loops_keep_a_circuit() {
+        let (graph, _) = Graph::from_pairs(3, &[(0, 1), (1, 2), (2, 0), (1, 1)], false);
+        let Ok(EulerTrail::Circuit(trail)) = graph.eulerian_trail() else {
+            panic!("no Eulerian circuit found");
+        };
+        uses_every_edge(&graph, &trail);
+    }
+
+    #[test]
+    fn directed_edges_are_followed() {
+        let (graph, _) = Graph::from_pairs(3, &[(0, 1), (1, 2), (2, 0)], true);
+        let Ok(EulerTrail::Circuit(trail)) = graph.eulerian_trail() else {
+            panic!("no Eulerian circuit found");
+        };
+        uses_every_edge(&graph, &trail);
+
+        // Node 1 has two edges out and none in
+        let (graph, _) = Graph::from_pairs(3, &[(1, 0), (1, 2)], true);
+        assert!(matches!(graph.eulerian_trail(), Err(NotEulerian::Imbalanced(_))));
+    }
+
+    #[test]
+    fn edges_must_be_connected() {
+        let (graph, _) = Graph::from_pairs(5, &[(0, 1), (2, 3)], false);
+        assert!(matches!(graph.eulerian_trail(), Err(NotEulerian::Disconnected(2))));
+        let (graph, _) = Graph::from_pairs(2, &[], false);
+        assert!(matches!(graph.eulerian_trail(), Err(NotEulerian::NoEdges)));
+    }
+}
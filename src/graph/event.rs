@@ -1,17 +1,23 @@
 mod add;
 mod remove;
 mod mesh;
+mod route;
+pub(crate) mod animate;
 pub(crate) mod draw;
 pub(crate) mod phys;
+pub(crate) mod search;
 
-pub(crate) use {add::*, remove::*, mesh::*};
+pub(crate) use {add::*, remove::*, mesh::*, route::*};
 
 use bevy::prelude::*;
 
-use crate::{input::CursorInfo, ui::UiItemInfo};
+use std::path::PathBuf;
+
+use crate::{input::CursorInfo, theme::Theme, ui::{Alerts, UiItemInfo}};
 use crate::types::*;
 
-use super::{Graph, plugin::ImageCache, NodeE};
+use self::animate::Animation;
+use super::{align::{self, Alignment}, labels::{self, LabelBox}, Annotation, EdgeE, WeightLabel, GEdge, Graph, export::{self, Drawing}, plugin::DefaultTextStyle, coloring::{ColoringMethod, EdgeColoringMethod}, matching::MatchingMethod, community::CommunityMethod, plugin::ImageCache, shape::NodeStyle, template::GraphTemplate, NodeE};
 
 pub(crate) fn get_visibility(is_visible: bool) -> Visibility {
     if is_visible {
@@ -25,11 +31,31 @@ pub(crate) fn get_visibility(is_visible: bool) -> Visibility {
 pub enum GraphEvent {
     AddNode(Vec2),
     AddEdge(NodeE, NodeE),
+    Spawn(GraphTemplate),
     RemoveItem(Entity),
     ItemSelected(Entity),
+    /// Clears both the selected item and the group of nodes
     ItemDeselected,
+    /// Add a node to the group picked for the alignment commands, or take it
+    /// out if it's already there
+    GroupToggled(NodeE),
+    /// Line up or space out the group of nodes
+    Align(Alignment),
+    /// Route an edge through one more bend point
+    AddBend(EdgeE),
+    /// Reset the curve of one edge, or every edge if `None`, to a straight
+    /// line
+    Straighten(Option<EdgeE>),
+    /// Move the visible labels around their nodes and edges to keep them
+    /// from covering each other or the graph
+    PlaceLabels,
     ResetColors,
     PhysicsInit,
+    /// Show some text under a node or edge handle, replacing any existing
+    /// annotation on it
+    Annotate(Entity, String),
+    /// Draw the graph to an `.svg`, `.png` or `.tex` file
+    Export(PathBuf),
 }
 
 #[derive(Event)]
@@ -43,87 +69,315 @@ pub enum AnalyzeGraphEvent {
     SpanningTree(NodeE),
     Bipartite(NodeE),
     Dijkstra(NodeE, NodeE),
+    MaxFlow(NodeE, NodeE),
+    Matching(MatchingMethod),
+    Communities(CommunityMethod),
+    Euler,
+    Coloring(ColoringMethod),
+    CheckColoring(NodeE),
+    EdgeColoring(EdgeColoringMethod),
+    Hamiltonian { cycle: bool },
+    Tsp,
+    Biconnected,
+    TopologicalSort,
+    CriticalPath,
+    TransitiveClosure,
+    TransitiveReduction,
+    Planarity,
+    Crossings,
 }
 
 pub(crate) fn item_selected_event(
     mut events: EventReader<GraphEvent>,
     mut cursor: ResMut<CursorInfo>,
-    mut q_node: Query<&mut Handle<Image>, GNodeExclusive>,
+    mut q_node: Query<(&mut Handle<Image>, &NodeStyle), GNodeExclusive>,
     mut q_edge: Query<&mut Handle<Image>, GEdgeExclusive>,
     graph: Res<Graph>,
     cache: Res<ImageCache>,
     mut ui_info: ResMut<UiItemInfo>,
 ) {
     for event in events.read() {
+        // Items whose texture might need to change
+        let mut changed = Vec::new();
         match event {
             GraphEvent::ItemSelected(entity) => {
-                if let Some(entity) = cursor.selected {
-                    if let Ok(mut texture) = q_node.get_mut(entity) {
-                        *texture = cache.get("node").unwrap().clone();
-                    } else if let Ok(mut texture) = q_edge.get_mut(entity) {
-                        *texture = if graph.directed {
-                            cache.get("handle-dir")
-                        } else {
-                            cache.get("handle")
-                        }
-                        .unwrap()
-                        .clone();
-                    }
-                }
-                if let Ok(mut texture) = q_node.get_mut(*entity) {
-                    *texture = cache.get("node-sel").unwrap().clone();
-                } else if let Ok(mut texture) = q_edge.get_mut(*entity) {
-                    *texture = if graph.directed {
-                        cache.get("handle-dir-sel")
-                    } else {
-                        cache.get("handle-sel")
-                    }
-                    .unwrap()
-                    .clone();
-                }
+                changed.extend(cursor.selected);
+                changed.push(*entity);
                 cursor.selected = Some(*entity);
             }
             GraphEvent::ItemDeselected => {
-                if let Some(entity) = cursor.selected {
-                    if let Ok(mut texture) = q_node.get_mut(entity) {
-                        *texture = cache.get("node").unwrap().clone();
-                    } else if let Ok(mut texture) = q_edge.get_mut(entity) {
-                        *texture = if graph.directed {
-                            cache.get("handle-dir")
-                        } else {
-                            cache.get("handle")
-                        }
-                        .unwrap()
-                        .clone();
-                    }
+                changed.extend(cursor.selected.take());
+                changed.extend(cursor.group.drain(..).map(|node| *node));
+            }
+            GraphEvent::GroupToggled(node) => {
+                if let Some(i) = cursor.group.iter().position(|n| n == node) {
+                    cursor.group.remove(i);
+                } else {
+                    cursor.group.push(*node);
                 }
-                cursor.selected = None;
+                changed.push(**node);
             }
+            // Editing an edge's route leaves its info open
+            GraphEvent::AddBend(_) | GraphEvent::Straighten(_) => continue,
             _ => ()
         }
 
+        for entity in changed {
+            let selected = cursor.selected == Some(entity) || cursor.group.contains(&NodeE(entity));
+            if let Ok((mut texture, style)) = q_node.get_mut(entity) {
+                *texture = cache.texture(&style.shape, selected);
+            } else if let Ok(mut texture) = q_edge.get_mut(entity) {
+                *texture = match (graph.directed, selected) {
+                    (true, true) => cache.get("handle-dir-sel"),
+                    (true, false) => cache.get("handle-dir"),
+                    (false, true) => cache.get("handle-sel"),
+                    (false, false) => cache.get("handle"),
+                }
+                .unwrap()
+                .clone();
+            }
+        }
+
         *ui_info = UiItemInfo::None;
     }
 }
 
+pub(crate) fn align_event(
+    mut events: EventReader<GraphEvent>,
+    cursor: Res<CursorInfo>,
+    q_nodes: Query<&Transform, GNodeExclusive>,
+    mut ev_move: EventWriter<ItemMovedEvent>,
+) {
+    for event in events.read() {
+        if let GraphEvent::Align(alignment) = event {
+            // Skip any nodes removed since they were picked
+            let (nodes, points): (Vec<NodeE>, Vec<Vec2>) = cursor
+                .group
+                .iter()
+                .filter_map(|node| q_nodes.get(**node).ok().map(|t| (*node, t.translation.truncate())))
+                .unzip();
+
+            for ((node, old), new) in nodes.iter().zip(points.iter()).zip(align::arrange(&points, *alignment)) {
+                if new != *old {
+                    ev_move.send(ItemMovedEvent(**node, (new - *old).extend(0.0)));
+                }
+            }
+        }
+    }
+}
+
 pub(crate) fn reset_colors_event(
     mut events: EventReader<GraphEvent>,
-    mut q_node: Query<&mut Sprite, GNodeExclusive>,
+    mut q_node: Query<(&mut Sprite, &NodeStyle), GNodeExclusive>,
     mut q_edge: Query<&mut Sprite, GEdgeExclusive>,
+    q_annotations: Query<Entity, With<Annotation>>,
+    (mut animation, theme): (ResMut<Animation>, Res<Theme>),
+    mut commands: Commands,
     mut regen_edge_mesh: EventWriter<RegenEdgeMesh>,
 ) {
     for event in events.read() {
-        match event {
-            GraphEvent::ResetColors => {
-                for mut sprite in q_node.iter_mut() {
-                    sprite.color = Color::WHITE;
+        if let GraphEvent::ResetColors = event {
+            for (mut sprite, style) in q_node.iter_mut() {
+                sprite.color = theme.node;
+                sprite.custom_size = Some(Vec2::splat(style.size));
+            }
+            for mut sprite in q_edge.iter_mut() {
+                sprite.color = theme.edge;
+            }
+            for annotation in q_annotations.iter() {
+                commands.entity(annotation).despawn_recursive();
+            }
+            animation.stop();
+            regen_edge_mesh.send(RegenEdgeMesh());
+        }
+    }
+}
+
+pub(crate) fn annotate_event(
+    mut events: EventReader<GraphEvent>,
+    mut commands: Commands,
+    q_children: Query<&Children>,
+    mut q_annotations: Query<&mut Text, With<Annotation>>,
+    (text_style, theme): (Res<DefaultTextStyle>, Res<Theme>),
+) {
+    // Only keep the last annotation for each entity, so that an entity
+    // annotated twice in one frame doesn't get two text children
+    let mut annotations = std::collections::HashMap::new();
+    for event in events.read() {
+        if let GraphEvent::Annotate(entity, text) = event {
+            annotations.insert(*entity, text.clone());
+        }
+    }
+
+    for (entity, value) in annotations {
+        let existing = q_children
+            .get(entity)
+            .ok()
+            .and_then(|children| children.iter().find(|&&child| q_annotations.contains(child)));
+        if let Some(child) = existing {
+            q_annotations.get_mut(*child).unwrap().sections[0].value = value;
+        } else if let Some(mut parent) = commands.get_entity(entity) {
+            parent.with_children(|p| {
+                p.spawn((
+                    Text2dBundle {
+                        text: Text::from_section(
+                            value,
+                            TextStyle {
+                                font_size: 20.0,
+                                color: theme.annotation,
+                                ..text_style.clone()
+                            },
+                        ),
+                        transform: Transform::from_translation(Vec3::new(0.0, -30.0, 1.0)),
+                        ..Default::default()
+                    },
+                    Annotation,
+                ));
+            });
+        }
+    }
+}
+
+/// Show or hide labels and resize them to match the graph's settings, and
+/// keep weight labels showing each edge's weight
+pub(crate) fn update_labels(
+    graph: Res<Graph>,
+    text_style: Res<DefaultTextStyle>,
+    q_nodes: Query<&Children, GNodeExclusive>,
+    q_edges: Query<(Ref<GEdge>, &Children), GEdgeExclusive>,
+    mut q_text: Query<(&mut Text, &mut Visibility, Option<&WeightLabel>), Without<Annotation>>,
+) {
+    let restyle = graph.is_changed() || text_style.is_changed();
+    let size = text_style.font_size;
+
+    if restyle {
+        for children in q_nodes.iter() {
+            let mut iter = q_text.iter_many_mut(children);
+            while let Some((mut text, mut visibility, _)) = iter.fetch_next() {
+                visibility.set_if_neq(get_visibility(graph.show_node_labels));
+                if text.sections[0].style.font_size != size {
+                    text.sections[0].style.font_size = size;
                 }
-                for mut sprite in q_edge.iter_mut() {
-                    sprite.color = Color::WHITE;
+            }
+        }
+    }
+
+    for (edge, children) in q_edges.iter() {
+        if !restyle && !edge.is_changed() {
+            continue;
+        }
+        let mut iter = q_text.iter_many_mut(children);
+        while let Some((mut text, mut visibility, weight_label)) = iter.fetch_next() {
+            if weight_label.is_some() {
+                visibility.set_if_neq(get_visibility(graph.show_weights));
+                let weight = edge.weight.to_string();
+                if text.sections[0].value != weight {
+                    text.sections[0].value = weight;
                 }
-                regen_edge_mesh.send(RegenEdgeMesh());
+            } else {
+                visibility.set_if_neq(get_visibility(graph.show_edge_labels));
+            }
+            if text.sections[0].style.font_size != size {
+                text.sections[0].style.font_size = size;
+            }
+        }
+    }
+}
+
+pub(crate) fn place_labels_event(
+    mut events: EventReader<GraphEvent>,
+    q_nodes: Query<(&Transform, &GlobalTransform, &NodeStyle, &Children), GNodeExclusive>,
+    q_edges: Query<(&GEdge, &Transform, &GlobalTransform, &Children), GEdgeExclusive>,
+    mut q_text: Query<
+        (&mut Transform, &GlobalTransform, &bevy::text::TextLayoutInfo, &Visibility),
+        GLabelExclusive,
+    >,
+) {
+    for event in events.read() {
+        if !matches!(event, GraphEvent::PlaceLabels) {
+            continue;
+        }
+
+        let nodes: Vec<(Vec2, f32)> = q_nodes
+            .iter()
+            .map(|(transform, _, style, _)| (transform.translation.truncate(), style.size / 2.0))
+            .collect();
+
+        // Break every edge into the straight pieces it's drawn with, or
+        // close enough to them for curves
+        let mut segments = Vec::new();
+        for (edge, edge_t, _, _) in q_edges.iter() {
+            let (Ok((start_t, ..)), Ok((end_t, ..))) = (q_nodes.get(*edge.start), q_nodes.get(*edge.end)) else {
+                continue;
+            };
+            let (start, handle, end) = (start_t.translation, edge_t.translation, end_t.translation);
+            let curves: Vec<[Vec3; 3]> = if let Some(path) = &edge.path {
+                segments.extend(path.windows(2).map(|pair| (pair[0], pair[1])));
+                continue;
+            } else if edge.is_loop() {
+                let [node, left, handle, right] = loop_vertices(start, handle).map(Vec3::from_array);
+                vec![[node, left, handle], [node, right, handle]]
+            } else {
+                vec![[start, 2.0 * handle - start.lerp(end, 0.5), end]]
+            };
+            for [a, c, b] in curves {
+                let point = |t: f32| a.lerp(c, t).lerp(c.lerp(b, t), t).truncate();
+                segments.extend((0..12).map(|i| (point(i as f32 / 12.0), point((i + 1) as f32 / 12.0))));
+            }
+        }
+
+        // Labels with the transform of what they belong to, for putting
+        // their new spots back into its coordinates
+        let mut boxes = Vec::new();
+        let mut owners = Vec::new();
+        let items = q_nodes
+            .iter()
+            .map(|(transform, global, style, children)| (transform, global, style.size / 2.0, children))
+            .chain(q_edges.iter().map(|(_, transform, global, children)| (transform, global, 8.0, children)));
+        for (transform, global, clearance, children) in items {
+            for child in children.iter() {
+                let Ok((_, text_global, layout, visibility)) = q_text.get(*child) else {
+                    continue;
+                };
+                if *visibility == Visibility::Hidden {
+                    continue;
+                }
+                let (scale, _, _) = text_global.to_scale_rotation_translation();
+                boxes.push(LabelBox {
+                    anchor: transform.translation.truncate(),
+                    clearance,
+                    size: layout.logical_size * scale.truncate(),
+                });
+                owners.push((*child, global.affine().inverse()));
+            }
+        }
+
+        let spots = labels::place_labels(&boxes, &nodes, &segments);
+        for ((label, to_local), spot) in owners.into_iter().zip(spots) {
+            if let Ok((mut transform, ..)) = q_text.get_mut(label) {
+                let local = to_local.transform_point3(spot.extend(0.0));
+                transform.translation.x = local.x;
+                transform.translation.y = local.y;
+            }
+        }
+    }
+}
+
+pub(crate) fn export_event(
+    mut events: EventReader<GraphEvent>,
+    (graph, theme): (Res<Graph>, Res<Theme>),
+    mut alerts: ResMut<Alerts>,
+    q_nodes: Query<(&Transform, &Sprite, &NodeStyle, &Children), GNodeExclusive>,
+    q_edges: Query<(&GEdge, &Transform, &Sprite, &Children), GEdgeExclusive>,
+    q_text: Query<export::ExportedText>,
+) {
+    for event in events.read() {
+        if let GraphEvent::Export(path) = event {
+            let drawing = Drawing::from_world(&graph, &theme, &q_nodes, &q_edges, &q_text);
+            match drawing.save(path) {
+                Ok(()) => alerts.0.push(format!("Exported {}", path.display())),
+                Err(e) => alerts.0.push(e),
             }
-            _ => ()
         }
     }
 }
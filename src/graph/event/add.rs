@@ -1,47 +1,79 @@
 use bevy::prelude::*;
 
-use crate::graph::{
+use crate::{graph::{
     plugin::{DefaultTextStyle, ImageCache},
-    EdgeE, GEdge, GEdgeBundle, GEdgeHandle, GNode, GNodeBundle, Grabbable, Graph, NodeE,
-};
+    shape::{NodeShape, NodeStyle},
+    EdgeE, EdgeStyle, GEdge, GEdgeBundle, GEdgeHandle, GNode, GNodeBundle, Grabbable, Graph, NodeE, WeightLabel,
+}, theme::Theme, ui::Alerts};
 
 use super::{get_visibility, GraphEvent, RegenEdgeMesh};
 
+#[allow(clippy::too_many_arguments)]
+fn spawn_node(
+    commands: &mut Commands,
+    graph: &mut Graph,
+    img_cache: &ImageCache,
+    text_style: &TextStyle,
+    theme: &Theme,
+    pos: Vec2,
+    label: Option<String>,
+    style: NodeStyle,
+) -> NodeE {
+    let transform = Transform::default().with_translation(Vec3::new(pos.x, pos.y, 0.0));
+    let node = commands
+        .spawn(GNodeBundle {
+            node: GNode,
+            sprite: SpriteBundle {
+                sprite: Sprite {
+                    color: theme.node,
+                    custom_size: Some(Vec2::splat(style.size)),
+                    ..Default::default()
+                },
+                texture: img_cache.texture(&style.shape, false),
+                transform,
+                ..Default::default()
+            },
+            grab: Grabbable::Circle { radius: style.size / 2.0 },
+            style,
+        })
+        .with_children(|p| {
+            p.spawn(Text2dBundle {
+                text: Text::from_section(
+                    label.unwrap_or_else(|| format!("v{}", graph.last_node_num)),
+                    text_style.clone(),
+                )
+                .with_alignment(TextAlignment::Center),
+                transform: Transform::from_translation(Vec3::new(0.0, 30.0, 1.0)),
+                visibility: get_visibility(graph.show_node_labels),
+                ..Default::default()
+            });
+        })
+        .id();
+
+    graph.add_node(NodeE(node));
+    NodeE(node)
+}
+
 pub(crate) fn add_node_event(
     mut events: EventReader<GraphEvent>,
     mut regen_ev: EventWriter<RegenEdgeMesh>,
     mut graph: ResMut<Graph>,
     mut commands: Commands,
     img_cache: Res<ImageCache>,
-    text_style: Res<DefaultTextStyle>,
+    (text_style, theme): (Res<DefaultTextStyle>, Res<Theme>),
 ) {
     for event in events.read() {
         if let GraphEvent::AddNode(pos) = event {
-            let transform = Transform::default().with_translation(Vec3::new(pos.x, pos.y, 0.0));
-            let node = commands
-                .spawn(GNodeBundle {
-                    node: GNode::default(),
-                    sprite: SpriteBundle {
-                        texture: img_cache.get("node").unwrap().clone(),
-                        transform,
-                        ..Default::default()
-                    },
-                    grab: Grabbable::default(),
-                })
-                .with_children(|p| {
-                    p.spawn(Text2dBundle {
-                        text: Text::from_section(
-                            format!("v{}", graph.last_node_num),
-                            text_style.clone(),
-                        ),
-                        transform: Transform::from_translation(Vec3::new(0.0, 30.0, 1.0)),
-                        visibility: get_visibility(graph.show_labels),
-                        ..Default::default()
-                    });
-                })
-                .id();
-
-            graph.add_node(NodeE(node));
+            spawn_node(
+                &mut commands,
+                &mut graph,
+                &img_cache,
+                &text_style,
+                &theme,
+                *pos,
+                None,
+                NodeStyle::default(),
+            );
             regen_ev.send(RegenEdgeMesh());
         }
     }
@@ -54,6 +86,127 @@ pub(crate) fn offset_midpoint(start: Vec3, end: Vec3, offset_len: f32) -> Vec3 {
     midpoint + offset
 }
 
+/// Distance between the handles of neighbouring parallel edges
+static PARALLEL_EDGE_GAP: f32 = 40.0;
+/// Angle between neighbouring loops on the same node
+static LOOP_FAN_ANGLE: f32 = std::f32::consts::PI / 3.0;
+
+/// Where to put the handle of the `index`th edge between two nodes, fanning
+/// parallel edges out on alternating sides of the line between them. The
+/// nodes should be given in the same order for every edge between them so
+/// edges in opposite directions don't end up on the same side.
+pub(crate) fn fan_midpoint(start: Vec3, end: Vec3, index: usize) -> Vec3 {
+    let side = if index.is_multiple_of(2) { 1.0 } else { -1.0 };
+    offset_midpoint(start, end, side * (20.0 + PARALLEL_EDGE_GAP * (index / 2) as f32))
+}
+
+/// Where to put the handle of the `index`th loop on a node, fanning them out
+/// on alternating sides of the first one above the node
+pub(crate) fn fan_loop(node: Vec3, index: usize) -> Vec3 {
+    let side = if index.is_multiple_of(2) { 1.0 } else { -1.0 };
+    let angle = std::f32::consts::FRAC_PI_2 + side * LOOP_FAN_ANGLE * index.div_ceil(2) as f32;
+    node + Vec3::new(angle.cos(), angle.sin(), 0.0) * 50.0
+}
+
+#[allow(clippy::too_many_arguments)]
+fn spawn_edge(
+    commands: &mut Commands,
+    graph: &mut Graph,
+    cache: &ImageCache,
+    text_style: &TextStyle,
+    theme: &Theme,
+    (start, s): (NodeE, Vec3),
+    (end, e): (NodeE, Vec3),
+    weight: i32,
+) -> EdgeE {
+    // Fan the new edge out from any already between the same nodes
+    let index = graph.parallel_edges(&start, &end).len();
+    let transform = if start == end {
+        // This edge is a loop
+        Transform::default()
+            .with_translation(fan_loop(s, index))
+            .with_scale(Vec3::splat(0.5))
+    } else {
+        // Place the handle between the two nodes
+        let point = if start.0 < end.0 {
+            fan_midpoint(s, e, index)
+        } else {
+            fan_midpoint(e, s, index)
+        };
+        let sign = if s.y < e.y {
+            1.0
+        } else {
+            -1.0
+        };
+        Transform::default()
+            .with_translation(point)
+            .with_rotation(Quat::from_axis_angle(
+                Vec3::Z,
+                (e - s).angle_between(Vec3::X) * sign,
+            ))
+            .with_scale(Vec3::splat(0.75))
+    };
+
+    let texture = if graph.directed {
+        cache.get("handle-dir").unwrap().clone()
+    } else {
+        cache.get("handle").unwrap().clone()
+    };
+
+    let edge = commands
+        .spawn(GEdgeBundle {
+            edge: GEdge {
+                start,
+                end,
+                weight,
+                offset: None,
+                arrow: false,
+                bends: Vec::new(),
+                path: None,
+                style: EdgeStyle::default(),
+            },
+            handle: GEdgeHandle {
+                grab: Grabbable::default(),
+                sprite: SpriteBundle {
+                    sprite: Sprite {
+                        color: theme.edge,
+                        ..Default::default()
+                    },
+                    texture,
+                    transform,
+                    ..Default::default()
+                },
+            },
+        })
+        .with_children(|p| {
+            p.spawn(Text2dBundle {
+                text: Text::from_section(
+                    format!("e{}", graph.last_edge_num),
+                    text_style.clone(),
+                )
+                .with_alignment(TextAlignment::Center),
+                transform: Transform::from_translation(Vec3::new(0.0, 30.0, 1.0)),
+                visibility: get_visibility(graph.show_edge_labels),
+                ..Default::default()
+            });
+            // On the other side of the edge from the label
+            p.spawn((
+                Text2dBundle {
+                    text: Text::from_section(weight.to_string(), text_style.clone())
+                        .with_alignment(TextAlignment::Center),
+                    transform: Transform::from_translation(Vec3::new(0.0, -30.0, 1.0)),
+                    visibility: get_visibility(graph.show_weights),
+                    ..Default::default()
+                },
+                WeightLabel,
+            ));
+        })
+        .id();
+
+    graph.add_edge(EdgeE(edge), &start, &end);
+    EdgeE(edge)
+}
+
 pub(crate) fn add_edge_event(
     mut events: EventReader<GraphEvent>,
     mut regen_ev: EventWriter<RegenEdgeMesh>,
@@ -61,73 +214,80 @@ pub(crate) fn add_edge_event(
     mut commands: Commands,
     q_nodes: Query<(Entity, &Transform), With<GNode>>,
     cache: Res<ImageCache>,
-    text_style: Res<DefaultTextStyle>,
+    (text_style, theme): (Res<DefaultTextStyle>, Res<Theme>),
 ) {
     for event in events.read() {
         if let GraphEvent::AddEdge(a, b) = event {
             let (start, start_t) = q_nodes.get(**a).unwrap();
             let (end, end_t) = q_nodes.get(**b).unwrap();
 
-            let transform = if start == end {
-                // This edge is a loop
-                Transform::default()
-                    .with_translation(start_t.translation + Vec3::new(0.0, 50.0, 0.0))
-                    .with_scale(Vec3::splat(0.5))
-            } else {
-                // Place the handle between the two nodes
-                let (s, e) = (start_t.translation, end_t.translation);
-                let point = offset_midpoint(s, e, 20.0);
-                let sign = if s.y < e.y {
-                    1.0
-                } else {
-                    -1.0
-                };
-                Transform::default()
-                    .with_translation(point)
-                    .with_rotation(Quat::from_axis_angle(
-                        Vec3::Z,
-                        (end_t.translation - start_t.translation).angle_between(Vec3::X) * sign,
-                    ))
-                    .with_scale(Vec3::splat(0.75))
-            };
-
-            let texture = if graph.directed {
-                cache.get("handle-dir").unwrap().clone()
-            } else {
-                cache.get("handle").unwrap().clone()
-            };
-
-            let edge = commands
-                .spawn(GEdgeBundle {
-                    edge: GEdge {
-                        start: *a,
-                        end: *b,
-                        weight: 1,
-                        offset: None,
-                    },
-                    handle: GEdgeHandle {
-                        grab: Grabbable::default(),
-                        sprite: SpriteBundle {
-                            texture,
-                            transform,
-                            ..Default::default()
-                        },
-                    },
-                })
-                .with_children(|p| {
-                    p.spawn(Text2dBundle {
-                        text: Text::from_section(
-                            format!("e{}", graph.last_edge_num),
-                            text_style.clone(),
-                        ),
-                        transform: Transform::from_translation(Vec3::new(0.0, 30.0, 1.0)),
-                        visibility: get_visibility(graph.show_labels),
-                        ..Default::default()
-                    });
+            spawn_edge(
+                &mut commands,
+                &mut graph,
+                &cache,
+                &text_style,
+                &theme,
+                (NodeE(start), start_t.translation),
+                (NodeE(end), end_t.translation),
+                1,
+            );
+            regen_ev.send(RegenEdgeMesh());
+        }
+    }
+}
+
+/// Spawn every node and edge of a [`GraphTemplate`](crate::graph::template::GraphTemplate)
+/// in one go, since edges can't be added through `AddEdge` until the nodes
+/// they connect exist
+pub(crate) fn spawn_template_event(
+    mut events: EventReader<GraphEvent>,
+    mut regen_ev: EventWriter<RegenEdgeMesh>,
+    mut graph: ResMut<Graph>,
+    mut commands: Commands,
+    (mut cache, mut images, mut alerts): (ResMut<ImageCache>, ResMut<Assets<Image>>, ResMut<Alerts>),
+    (text_style, theme): (Res<DefaultTextStyle>, Res<Theme>),
+) {
+    for event in events.read() {
+        if let GraphEvent::Spawn(template) = event {
+            for node in template.nodes.iter() {
+                if let NodeShape::Image(path) = &node.style.shape {
+                    if let Err(e) = cache.insert_file(path, &mut images) {
+                        alerts.0.push(e);
+                    }
+                }
+            }
+
+            let nodes: Vec<(NodeE, Vec3)> = template
+                .nodes
+                .iter()
+                .map(|node| {
+                    let node_e = spawn_node(
+                        &mut commands,
+                        &mut graph,
+                        &cache,
+                        &text_style,
+                        &theme,
+                        node.pos,
+                        node.label.clone(),
+                        node.style.clone(),
+                    );
+                    (node_e, node.pos.extend(0.0))
                 })
-                .id();
+                .collect();
+
+            for edge in template.edges.iter() {
+                spawn_edge(
+                    &mut commands,
+                    &mut graph,
+                    &cache,
+                    &text_style,
+                    &theme,
+                    nodes[edge.start],
+                    nodes[edge.end],
+                    edge.weight,
+                );
+            }
 
-            graph.add_edge(EdgeE(edge), &NodeE(start), &NodeE(end));
             regen_ev.send(RegenEdgeMesh());
         }
     }
@@ -0,0 +1,75 @@
+use std::collections::VecDeque;
+
+use bevy::{prelude::*, window::RequestRedraw};
+
+use super::{GraphEvent, RegenEdgeMesh};
+
+static STEP_SECONDS: f32 = 0.4;
+
+/// Changes applied together as one frame of an [`Animation`]
+#[derive(Default)]
+pub(crate) struct AnimationStep {
+	pub colors: Vec<(Entity, Color)>,
+	pub annotations: Vec<(Entity, String)>,
+}
+
+/// Plays back the steps of an algorithm one at a time
+#[derive(Resource)]
+pub(crate) struct Animation {
+	steps: VecDeque<AnimationStep>,
+	timer: Timer,
+}
+
+impl Default for Animation {
+	fn default() -> Self {
+		Animation {
+			steps: VecDeque::new(),
+			timer: Timer::from_seconds(STEP_SECONDS, TimerMode::Repeating),
+		}
+	}
+}
+
+impl Animation {
+	pub fn play(&mut self, steps: impl IntoIterator<Item = AnimationStep>) {
+		self.steps = steps.into_iter().collect();
+		self.timer.reset();
+	}
+
+	pub fn stop(&mut self) {
+		self.steps.clear();
+	}
+}
+
+pub(crate) fn animation_system(
+	time: Res<Time>,
+	mut animation: ResMut<Animation>,
+	mut q_sprite: Query<&mut Sprite>,
+	mut graph_ev: EventWriter<GraphEvent>,
+	mut ev_regen: EventWriter<RegenEdgeMesh>,
+	mut ev_redraw: EventWriter<RequestRedraw>,
+) {
+	if animation.steps.is_empty() {
+		return;
+	}
+
+	// Keep the app updating while the animation plays, since it otherwise
+	// only wakes up on input
+	ev_redraw.send(RequestRedraw);
+
+	animation.timer.tick(time.delta());
+	for _ in 0..animation.timer.times_finished_this_tick() {
+		let Some(step) = animation.steps.pop_front() else {
+			break;
+		};
+
+		for (entity, color) in step.colors {
+			if let Ok(mut sprite) = q_sprite.get_mut(entity) {
+				sprite.color = color;
+			}
+		}
+		for (entity, text) in step.annotations {
+			graph_ev.send(GraphEvent::Annotate(entity, text));
+		}
+		ev_regen.send(RegenEdgeMesh());
+	}
+}
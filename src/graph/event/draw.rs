@@ -1,25 +1,22 @@
-use bevy::prelude::*;
+use std::{collections::HashMap, sync::atomic::AtomicBool};
 
-use crate::{graph::{Graph, GEdge}, types::{GEdgeExclusive, GNodeExclusive}, input::CursorInfo};
+use bevy::{ecs::system::SystemParam, prelude::*};
 
-use super::{AnalyzeGraphEvent, RegenEdgeMesh};
+use crate::{graph::{Annotation, EdgeE, Graph, GEdge, NodeE, PathPart, WeightLabel, flow::FlowSettings, planarity::{crossings, Curve, PLANARITY_LIMIT}, matching::MatchingMethod, community::CommunityLayout, euler::{EulerTrail, NotEulerian}, coloring::{Coloring, ColoringMethod, EdgeColoring, EdgeColoringMethod}}, types::{GEdgeExclusive, GNodeExclusive}, theme::{Colors, Theme}};
+
+use super::{animate::{Animation, AnimationStep}, carry_handle, fan_midpoint, AnalyzeGraphEvent, GraphEvent, ItemMovedEvent, RegenEdgeMesh};
 
 pub(crate) fn draw_spanning_tree(
 	mut events: EventReader<AnalyzeGraphEvent>,
 	graph: Res<Graph>,
-	cursor: Res<CursorInfo>,
+	colors: Colors,
 	mut q_node: Query<&mut Sprite, GNodeExclusive>,
 	mut q_edge: Query<&mut Sprite, GEdgeExclusive>,
 	mut ev_regen: EventWriter<RegenEdgeMesh>,
 ) {
 	for event in events.read() {
 		if let AnalyzeGraphEvent::SpanningTree(node_e) = event {
-			let color = if cursor.paint_color != Color::WHITE {
-				cursor.paint_color
-			}
-			else {
-				Color::GREEN
-			};
+			let color = colors.highlight();
 
 			graph.spanning_tree(node_e, |part| {
 				if let Ok(mut sprite) = q_node.get_mut(*part.node) {
@@ -40,34 +37,64 @@ pub(crate) fn draw_spanning_tree(
 pub(crate) fn draw_bipartite(
 	mut events: EventReader<AnalyzeGraphEvent>,
 	graph: Res<Graph>,
+	colors: Colors,
 	mut alerts: ResMut<crate::ui::Alerts>,
-	mut q_node: Query<&mut Sprite, GNodeExclusive>,
-	mut q_edge: Query<&mut Sprite, GEdgeExclusive>,
+	(mut q_node, mut q_edge): (
+		Query<&mut Sprite, GNodeExclusive>,
+		Query<&mut Sprite, GEdgeExclusive>,
+	),
+	labels: Labels,
 	mut ev_regen: EventWriter<RegenEdgeMesh>,
 ) {
 	for event in events.read() {
 		if let AnalyzeGraphEvent::Bipartite(node_e) = event {
+			// Only color the graph once it's known to be bipartite
+			let mut visited = Vec::new();
 			let result = graph.bipartite_walk(node_e, |node_e, edge_e, set| {
-				let color = if set == 0 {
-					Color::rgb(1.0, 0.0, 0.0)
-				} else {
-					Color::rgb(0.0, 0.0, 1.0)
-				};
+				visited.push((*node_e, edge_e.copied(), set));
+			});
 
-				if let Ok(mut sprite) = q_node.get_mut(**node_e) {
-					sprite.color = color;
-				}
-				if let Some(edge_e) = edge_e {
-					if let Ok(mut sprite) = q_edge.get_mut(**edge_e) {
-						sprite.color = color;
+			match result {
+				Ok(()) => {
+					for (node_e, edge_e, set) in visited {
+						let color = colors.theme.sides[set];
+
+						if let Ok(mut sprite) = q_node.get_mut(*node_e) {
+							sprite.color = color;
+						}
+						if let Some(edge_e) = edge_e {
+							if let Ok(mut sprite) = q_edge.get_mut(*edge_e) {
+								sprite.color = color;
+							}
+						}
 					}
 				}
-			});
-			ev_regen.send(RegenEdgeMesh());
+				Err(cycle) => {
+					let color = colors.highlight();
 
-			if !result {
-				alerts.0.push("Graph is not bipartite".to_string());
+					for part in cycle.iter() {
+						if let Ok(mut sprite) = q_node.get_mut(*part.node) {
+							sprite.color = color;
+						}
+						if let Some(edge_e) = part.edge {
+							if let Ok(mut sprite) = q_edge.get_mut(*edge_e) {
+								sprite.color = color;
+							}
+						}
+					}
+
+					let path: Vec<String> = cycle
+						.iter()
+						.map(|part| labels.get(*part.node))
+						.collect();
+					alerts.0.push(format!(
+						"Graph is not bipartite, it has an odd cycle of length {}:\n{}",
+						cycle.len() - 1,
+						path.join(" → ")
+					));
+				}
 			}
+			ev_regen.send(RegenEdgeMesh());
 		}
 	}
 }
@@ -75,21 +102,16 @@ pub(crate) fn draw_bipartite(
 pub(crate) fn draw_shortest_path(
 	mut events: EventReader<AnalyzeGraphEvent>,
 	graph: Res<Graph>,
-	cursor: Res<CursorInfo>,
+	colors: Colors,
 	mut q_node: Query<&mut Sprite, GNodeExclusive>,
 	mut p_edge: ParamSet<(Query<&mut Sprite, GEdgeExclusive>, Query<&GEdge>)>,
 	mut ev_regen: EventWriter<RegenEdgeMesh>,
 ) {
 	for event in events.read() {
 		if let AnalyzeGraphEvent::Dijkstra(a, b) = event {
-			let color = if cursor.paint_color != Color::WHITE {
-				cursor.paint_color
-			}
-			else {
-				Color::GREEN
-			};
+			let color = colors.highlight();
 
-			if let Some(path) = graph.dijkstra_path(&a, &b, Some(&p_edge.p1())) {
+			if let Some(path) = graph.dijkstra_path(a, b, Some(&p_edge.p1())) {
 				for part in path {
 					if let Ok(mut sprite) = q_node.get_mut(*part.node) {
 						sprite.color = color;
@@ -106,3 +128,732 @@ pub(crate) fn draw_shortest_path(
 		}
 	}
 }
+
+/// Look up the text of node and edge labels
+#[derive(SystemParam)]
+pub(crate) struct Labels<'w, 's> {
+	children: Query<'w, 's, &'static Children>,
+	text: Query<'w, 's, &'static Text, (Without<Annotation>, Without<WeightLabel>)>,
+}
+
+impl Labels<'_, '_> {
+	pub fn get(&self, entity: Entity) -> String {
+		self.children
+			.get(entity)
+			.ok()
+			.and_then(|children| children.iter().find_map(|child| self.text.get(*child).ok()))
+			.map(|text| text.sections[0].value.clone())
+			.unwrap_or_default()
+	}
+}
+
+/// Describe a vertex coloring, with the bounds on the chromatic number it
+/// gives
+pub(super) fn coloring_message(graph: &Graph, theme: &Theme, method: ColoringMethod, coloring: &Coloring) -> String {
+	let colors = coloring.values().copied().max().map_or(0, |c| c + 1);
+	let mut message = if method == ColoringMethod::Exact {
+		format!("Chromatic number χ = {colors}")
+	} else {
+		format!(
+			"{method} used {colors} colors\n{} ≤ χ ≤ {colors}",
+			graph.clique_lower_bound()
+		)
+	};
+	if colors > theme.palette.len() {
+		message += "\nThere are more color classes than palette colors, some colors are reused";
+	}
+	if graph.has_loop() {
+		message += "\nThe graph has loops, so it has no proper coloring";
+	}
+	message
+}
+
+pub(crate) fn draw_coloring(
+	mut events: EventReader<AnalyzeGraphEvent>,
+	graph: Res<Graph>,
+	theme: Res<Theme>,
+	mut alerts: ResMut<crate::ui::Alerts>,
+	mut q_node: Query<&mut Sprite, GNodeExclusive>,
+	mut ev_regen: EventWriter<RegenEdgeMesh>,
+) {
+	for event in events.read() {
+		if let AnalyzeGraphEvent::Coloring(method) = event {
+			let coloring = match method {
+				ColoringMethod::WelshPowell => graph.welsh_powell_coloring(),
+				ColoringMethod::DSatur => graph.dsatur_coloring(),
+				// Searched for in the background, see `search::start_search`
+				ColoringMethod::Exact => continue,
+			};
+
+			for (node_e, class) in coloring.iter() {
+				if let Ok(mut sprite) = q_node.get_mut(**node_e) {
+					sprite.color = theme.palette_color(*class);
+				}
+			}
+			ev_regen.send(RegenEdgeMesh());
+			alerts.0.push(coloring_message(&graph, &theme, *method, &coloring));
+		}
+	}
+}
+
+/// Warn when a node painted by hand shares a color with one of its neighbours
+pub(crate) fn check_coloring(
+	mut events: EventReader<AnalyzeGraphEvent>,
+	graph: Res<Graph>,
+	theme: Res<Theme>,
+	mut alerts: ResMut<crate::ui::Alerts>,
+	q_node: Query<&Sprite, GNodeExclusive>,
+	labels: Labels,
+) {
+	for event in events.read() {
+		if let AnalyzeGraphEvent::CheckColoring(node_e) = event {
+			let Ok(color) = q_node.get(**node_e).map(|sprite| sprite.color) else {
+				continue;
+			};
+			if color == theme.node {
+				continue;
+			}
+
+			let mut clashes: Vec<String> = graph
+				.neighbours(node_e)
+				.iter()
+				.filter(|adj| q_node.get(***adj).is_ok_and(|sprite| sprite.color == color))
+				.map(|adj| labels.get(**adj))
+				.collect();
+			if clashes.is_empty() {
+				continue;
+			}
+			clashes.sort();
+
+			alerts.0.push(format!(
+				"Improper coloring: {} shares its color with adjacent {}",
+				labels.get(**node_e),
+				clashes.join(", ")
+			));
+		}
+	}
+}
+
+/// Describe an edge coloring, with the class of the graph if it's known
+pub(super) fn edge_coloring_message(theme: &Theme, method: EdgeColoringMethod, coloring: &EdgeColoring) -> String {
+	let colors = coloring.color_count();
+	let delta = coloring.max_degree;
+	let mut message = format!("{method} used {colors} colors (Δ = {delta})");
+	message += &match coloring.class {
+		_ if coloring.multigraph => {
+			"\nThe graph has parallel edges, so Vizing's bound doesn't apply and a greedy coloring was used".to_string()
+		}
+		Some(1) => format!("\nClass 1: χ' = Δ = {delta}"),
+		Some(_) => format!("\nClass 2: χ' = Δ + 1 = {}", delta + 1),
+		None => format!("\n{delta} ≤ χ' ≤ {}, run the exact coloring to find the class", delta + 1),
+	};
+	if colors > theme.palette.len() {
+		message += "\nThere are more color classes than palette colors, some colors are reused";
+	}
+	message
+}
+
+pub(crate) fn draw_edge_coloring(
+	mut events: EventReader<AnalyzeGraphEvent>,
+	graph: Res<Graph>,
+	theme: Res<Theme>,
+	mut alerts: ResMut<crate::ui::Alerts>,
+	mut q_edge: Query<&mut Sprite, GEdgeExclusive>,
+	mut ev_regen: EventWriter<RegenEdgeMesh>,
+) {
+	for event in events.read() {
+		// The exact coloring is searched for in the background, see
+		// `search::start_search`
+		let AnalyzeGraphEvent::EdgeColoring(method @ EdgeColoringMethod::MisraGries) = event else {
+			continue;
+		};
+		let problem = match graph.edge_coloring_problem() {
+			Ok(problem) => problem,
+			Err(e) => {
+				alerts.0.push(e);
+				continue;
+			}
+		};
+		// Nothing to cancel without the exact search
+		let Ok(coloring) = problem.solve(*method, &AtomicBool::new(false)) else {
+			continue;
+		};
+
+		for (edge_e, class) in coloring.colors.iter() {
+			if let Ok(mut sprite) = q_edge.get_mut(**edge_e) {
+				sprite.color = theme.palette_color(*class);
+			}
+		}
+		ev_regen.send(RegenEdgeMesh());
+		alerts.0.push(edge_coloring_message(&theme, *method, &coloring));
+	}
+}
+
+pub(crate) fn draw_euler(
+	mut events: EventReader<AnalyzeGraphEvent>,
+	graph: Res<Graph>,
+	colors: Colors,
+	mut alerts: ResMut<crate::ui::Alerts>,
+	mut animation: ResMut<Animation>,
+	labels: Labels,
+) {
+	for event in events.read() {
+		if let AnalyzeGraphEvent::Euler = event {
+			let (trail, kind) = match graph.eulerian_trail() {
+				Ok(EulerTrail::Circuit(trail)) => (trail, "circuit"),
+				Ok(EulerTrail::Path(trail)) => (trail, "path"),
+				Err(reason) => {
+					let names = |nodes: &mut dyn Iterator<Item = String>| nodes.collect::<Vec<_>>().join(", ");
+					alerts.0.push(match reason {
+						NotEulerian::NoEdges => "The graph has no edges to traverse".to_string(),
+						NotEulerian::Disconnected(components) => format!(
+							"No Eulerian trail: the edges are split over {components} components"
+						),
+						NotEulerian::OddDegree(odd) => format!(
+							"No Eulerian trail: {} vertices have odd degree, at most 2 are allowed\n{}",
+							odd.len(),
+							names(&mut odd.iter().map(|node| labels.get(**node)))
+						),
+						NotEulerian::Imbalanced(unbalanced) => format!(
+							"No Eulerian trail: every vertex needs as many edges in as out, except \
+							one start vertex with one extra out and one end vertex with one extra in\n{}",
+							names(&mut unbalanced
+								.iter()
+								.map(|(node, b)| format!("{}: out - in = {b}", labels.get(**node))))
+						),
+					});
+					continue;
+				}
+			};
+
+			let color = colors.highlight();
+
+			// Walk the trail one edge at a time, numbering each edge as it's used
+			animation.play(trail.iter().enumerate().map(|(i, part)| {
+				let mut step = AnimationStep::default();
+				step.colors.push((*part.node, color));
+				if let Some(edge_e) = part.edge {
+					step.colors.push((*edge_e, color));
+					step.annotations.push((*edge_e, format!("{i}")));
+				}
+				step
+			}));
+
+			let path: Vec<String> = trail.iter().map(|part| labels.get(*part.node)).collect();
+			alerts.0.push(format!("Eulerian {kind}:\n{}", path.join(" → ")));
+		}
+	}
+}
+
+pub(crate) fn draw_biconnected(
+	mut events: EventReader<AnalyzeGraphEvent>,
+	graph: Res<Graph>,
+	colors: Colors,
+	mut alerts: ResMut<crate::ui::Alerts>,
+	(mut q_node, mut q_edge): (Query<&mut Sprite, GNodeExclusive>, Query<&mut Sprite, GEdgeExclusive>),
+	labels: Labels,
+	mut ev_regen: EventWriter<RegenEdgeMesh>,
+) {
+	for event in events.read() {
+		if let AnalyzeGraphEvent::Biconnected = event {
+			let analysis = graph.biconnectivity();
+
+			// Each block gets its own color, nodes inside a single block take its
+			// color and the cut vertices joining blocks are highlighted
+			for (i, block) in analysis.components.iter().enumerate() {
+				for edge_e in block.iter() {
+					if let Ok(mut sprite) = q_edge.get_mut(**edge_e) {
+						sprite.color = colors.theme.palette_color(i);
+					}
+					let (start, end) = graph.edge_nodes[edge_e];
+					for node_e in [start, end] {
+						if analysis.cut_vertices.contains(&node_e) {
+							continue;
+						}
+						if let Ok(mut sprite) = q_node.get_mut(*node_e) {
+							sprite.color = colors.theme.palette_color(i);
+						}
+					}
+				}
+			}
+
+			let color = colors.highlight();
+			for node_e in analysis.cut_vertices.iter() {
+				if let Ok(mut sprite) = q_node.get_mut(**node_e) {
+					sprite.color = color;
+				}
+			}
+			ev_regen.send(RegenEdgeMesh());
+
+			let mut cut: Vec<String> = analysis.cut_vertices.iter().map(|node_e| labels.get(**node_e)).collect();
+			cut.sort();
+			let mut message = format!(
+				"Biconnected components: {}\nBridges: {}\nArticulation points: {}",
+				analysis.components.len(),
+				analysis.bridges.len(),
+				cut.len()
+			);
+			if !cut.is_empty() {
+				message += &format!(" ({})", cut.join(", "));
+			}
+			if analysis.components.len() > colors.theme.palette.len() {
+				message += "\nThere are more components than palette colors, some colors are reused";
+			}
+			alerts.0.push(message);
+		}
+	}
+}
+
+fn paint_path(path: &[PathPart], color: Color, q_sprite: &mut Query<&mut Sprite>) {
+	for part in path.iter() {
+		if let Ok(mut sprite) = q_sprite.get_mut(*part.node) {
+			sprite.color = color;
+		}
+		if let Some(edge_e) = part.edge {
+			if let Ok(mut sprite) = q_sprite.get_mut(*edge_e) {
+				sprite.color = color;
+			}
+		}
+	}
+}
+
+pub(crate) fn draw_dag(
+	mut events: EventReader<AnalyzeGraphEvent>,
+	graph: Res<Graph>,
+	colors: Colors,
+	mut alerts: ResMut<crate::ui::Alerts>,
+	mut p_query: ParamSet<(Query<&mut Sprite>, Query<&GEdge>)>,
+	labels: Labels,
+	(mut graph_ev, mut ev_regen): (EventWriter<GraphEvent>, EventWriter<RegenEdgeMesh>),
+) {
+	for event in events.read() {
+		if !matches!(
+			event,
+			AnalyzeGraphEvent::TopologicalSort
+				| AnalyzeGraphEvent::CriticalPath
+				| AnalyzeGraphEvent::TransitiveClosure
+				| AnalyzeGraphEvent::TransitiveReduction
+		) {
+			continue;
+		}
+		if !graph.directed {
+			alerts.0.push("This only works on directed graphs".into());
+			continue;
+		}
+
+		let color = colors.highlight();
+		let names = |path: &[PathPart]| -> String {
+			path.iter().map(|part| labels.get(*part.node)).collect::<Vec<_>>().join(" → ")
+		};
+
+		// Everything except the closure needs the graph to be acyclic
+		let order = if matches!(event, AnalyzeGraphEvent::TransitiveClosure) {
+			Vec::new()
+		} else {
+			match graph.topological_order() {
+				Ok(order) => order,
+				Err(cycle) => {
+					paint_path(&cycle, color, &mut p_query.p0());
+					ev_regen.send(RegenEdgeMesh());
+					alerts.0.push(format!("The graph has a cycle:\n{}", names(&cycle)));
+					continue;
+				}
+			}
+		};
+
+		match event {
+			AnalyzeGraphEvent::TopologicalSort => {
+				for (i, node_e) in order.iter().enumerate() {
+					graph_ev.send(GraphEvent::Annotate(**node_e, format!("{}", i + 1)));
+				}
+				let order: Vec<String> = order.iter().map(|node_e| labels.get(**node_e)).collect();
+				alerts.0.push(format!("Topological order:\n{}", order.join(", ")));
+			}
+			AnalyzeGraphEvent::CriticalPath => {
+				let Some((path, length)) = graph.longest_path(&p_query.p1()) else {
+					continue;
+				};
+				paint_path(&path, color, &mut p_query.p0());
+				ev_regen.send(RegenEdgeMesh());
+				alerts.0.push(format!("Critical path, total weight {length}:\n{}", names(&path)));
+			}
+			AnalyzeGraphEvent::TransitiveClosure => {
+				let missing = graph.transitive_closure();
+				for (a, b) in missing.iter() {
+					graph_ev.send(GraphEvent::AddEdge(*a, *b));
+				}
+				alerts.0.push(format!("Added {} edges for the transitive closure", missing.len()));
+			}
+			AnalyzeGraphEvent::TransitiveReduction => {
+				let redundant = graph.transitive_reduction().unwrap_or_default();
+				for edge_e in redundant.iter() {
+					graph_ev.send(GraphEvent::RemoveItem(**edge_e));
+				}
+				alerts.0.push(format!("Removed {} edges for the transitive reduction", redundant.len()));
+			}
+			_ => (),
+		}
+	}
+}
+
+/// Spacing between grid lines of a planar layout
+static PLANAR_GRID_STEP: f32 = 60.0;
+
+pub(crate) fn draw_planarity(
+	mut events: EventReader<AnalyzeGraphEvent>,
+	graph: Res<Graph>,
+	colors: Colors,
+	mut alerts: ResMut<crate::ui::Alerts>,
+	(q_node, q_edge): (Query<&Transform, GNodeExclusive>, Query<&Transform, GEdgeExclusive>),
+	(mut q_sprite, labels): (Query<&mut Sprite>, Labels),
+	(mut move_ev, mut ev_regen): (EventWriter<ItemMovedEvent>, EventWriter<RegenEdgeMesh>),
+) {
+	for event in events.read() {
+		if let AnalyzeGraphEvent::Planarity = event {
+			if graph.node_count() > PLANARITY_LIMIT {
+				let planar = if graph.is_planar() { "planar" } else { "not planar" };
+				alerts.0.push(format!(
+					"The graph is {planar}\nGraphs with more than {PLANARITY_LIMIT} vertices aren't laid out or searched for a Kuratowski subgraph"
+				));
+				continue;
+			}
+
+			let Some(layout) = graph.planar_layout() else {
+				let Some(kuratowski) = graph.kuratowski() else {
+					continue;
+				};
+				let color = colors.highlight();
+
+				// The subdivision is highlighted, with the vertices of the
+				// original K₅ or K₃,₃ in red
+				for edge_e in kuratowski.edges.iter() {
+					let (start, end) = graph.edge_nodes[edge_e];
+					for entity in [**edge_e, *start, *end] {
+						if let Ok(mut sprite) = q_sprite.get_mut(entity) {
+							sprite.color = color;
+						}
+					}
+				}
+				for node_e in kuratowski.branch.iter() {
+					if let Ok(mut sprite) = q_sprite.get_mut(**node_e) {
+						sprite.color = colors.theme.warning;
+					}
+				}
+				ev_regen.send(RegenEdgeMesh());
+
+				let mut names: Vec<String> = kuratowski.branch.iter().map(|node_e| labels.get(**node_e)).collect();
+				names.sort();
+				alerts.0.push(format!(
+					"The graph is not planar, it contains a subdivision of {} on {}",
+					kuratowski.kind,
+					names.join(", ")
+				));
+				continue;
+			};
+
+			// Keep the drawing centred where the graph was
+			let n = graph.node_count();
+			let centre = q_node.iter().map(|t| t.translation.truncate()).sum::<Vec2>() / n.max(1) as f32;
+			let scale = PLANAR_GRID_STEP * (2 * n).saturating_sub(4).max(1) as f32 / 1.732;
+			let mut delta: HashMap<NodeE, Vec2> = HashMap::new();
+			for (node_e, position) in layout.iter() {
+				let Ok(node_t) = q_node.get(**node_e) else {
+					continue;
+				};
+				let d = centre + *position * scale - node_t.translation.truncate();
+				delta.insert(*node_e, d);
+				move_ev.send(ItemMovedEvent(**node_e, d.extend(0.0)));
+			}
+
+			// Moving a node carries the handles of its edges along, so correct
+			// them to lie on the straight line between the new positions.
+			// Parallel edges are fanned out on either side of it.
+			for (edge_e, (start, end)) in graph.edge_nodes.iter() {
+				if start == end {
+					continue;
+				}
+				let (Ok(edge_t), Ok(start_t), Ok(end_t)) = (q_edge.get(**edge_e), q_node.get(**start), q_node.get(**end)) else {
+					continue;
+				};
+
+				let (old_start, old_end) = (start_t.translation, end_t.translation);
+				let (new_start, new_end) = (old_start + delta[start].extend(0.0), old_end + delta[end].extend(0.0));
+				let parallel = graph.parallel_edges(start, end);
+				let target = match parallel.iter().position(|e| e == edge_e) {
+					Some(_) if parallel.len() == 1 => new_start.lerp(new_end, 0.5),
+					Some(index) if start.0 < end.0 => fan_midpoint(new_start, new_end, index),
+					Some(index) => fan_midpoint(new_end, new_start, index),
+					None => continue,
+				};
+				let carried = carry_handle((old_start, old_end), (new_start, new_end), edge_t.translation);
+				move_ev.send(ItemMovedEvent(**edge_e, (target - carried).truncate().extend(0.0)));
+			}
+
+			let mut message = "The graph is planar, and has been drawn without crossings".to_string();
+			if graph.do_physics {
+				message += "\nTurn off physics to keep the layout";
+			}
+			alerts.0.push(message);
+		}
+	}
+}
+
+pub(crate) fn draw_crossings(
+	mut events: EventReader<AnalyzeGraphEvent>,
+	graph: Res<Graph>,
+	colors: Colors,
+	(mut alerts, mut ev_regen): (ResMut<crate::ui::Alerts>, EventWriter<RegenEdgeMesh>),
+	q_node: Query<&Transform, GNodeExclusive>,
+	q_edge: Query<(&GEdge, &Transform), GEdgeExclusive>,
+	mut q_sprite: Query<&mut Sprite, GEdgeExclusive>,
+) {
+	for event in events.read() {
+		if let AnalyzeGraphEvent::Crossings = event {
+			// Each edge is drawn along its path of straight segments if it has
+			// one, otherwise as a quadratic curve through its handle
+			let mut edges = Vec::new();
+			let mut lines = Vec::new();
+			for (edge_e, (start, end)) in graph.edge_nodes.iter() {
+				if start == end {
+					continue;
+				}
+				let (Ok((edge, edge_t)), Ok(start_t), Ok(end_t)) = (q_edge.get(**edge_e), q_node.get(**start), q_node.get(**end)) else {
+					continue;
+				};
+				let (start, end) = (start_t.translation.truncate(), end_t.translation.truncate());
+				let line = match &edge.path {
+					Some(path) => path.clone(),
+					None => {
+						let control = 2.0 * edge_t.translation.truncate() - start.lerp(end, 0.5);
+						Curve { start, control, end }.polyline()
+					}
+				};
+				edges.push(*edge_e);
+				lines.push(line);
+			}
+
+			let found = crossings(&lines);
+			let color = colors.highlight();
+			for &(a, b) in found.iter() {
+				for i in [a, b] {
+					if let Ok(mut sprite) = q_sprite.get_mut(*edges[i]) {
+						sprite.color = color;
+					}
+				}
+			}
+			ev_regen.send(RegenEdgeMesh());
+
+			let pairs: std::collections::HashSet<&(usize, usize)> = found.iter().collect();
+			alerts.0.push(format!(
+				"Edge crossings: {} between {} pairs of edges (loops aren't counted)",
+				found.len(),
+				pairs.len()
+			));
+		}
+	}
+}
+
+pub(crate) fn draw_max_flow(
+	mut events: EventReader<AnalyzeGraphEvent>,
+	graph: Res<Graph>,
+	(colors, settings): (Colors, Res<FlowSettings>),
+	(mut alerts, mut animation): (ResMut<crate::ui::Alerts>, ResMut<Animation>),
+	mut p_query: ParamSet<(Query<&mut Sprite>, Query<&GEdge>)>,
+	labels: Labels,
+	(mut graph_ev, mut ev_regen): (EventWriter<GraphEvent>, EventWriter<RegenEdgeMesh>),
+) {
+	for event in events.read() {
+		if let AnalyzeGraphEvent::MaxFlow(source, sink) = event {
+			let result = match graph.max_flow(source, sink, settings.method, &p_query.p1()) {
+				Ok(result) => result,
+				Err(e) => {
+					alerts.0.push(e);
+					continue;
+				}
+			};
+
+			let color = colors.highlight();
+			let label = |flow: i64, capacity: i64| format!("{}/{capacity}", flow.abs());
+
+			// The final state: nodes split by the minimum cut, the cut edges
+			// highlighted and every edge labeled with its flow
+			let mut last = AnimationStep::default();
+			for node_e in graph.node_edges.keys() {
+				let side = if result.source_side.contains(node_e) { 0 } else { 1 };
+				last.colors.push((**node_e, colors.theme.sides[side]));
+			}
+			for (edge_e, flow) in result.flow.iter() {
+				let edge_color = if result.cut.contains(edge_e) { color } else { colors.theme.edge };
+				last.colors.push((**edge_e, edge_color));
+				last.annotations.push((**edge_e, label(*flow, result.capacity[edge_e])));
+			}
+
+			if settings.animate {
+				let mut flow: HashMap<EdgeE, i64> = result.flow.keys().map(|edge_e| (*edge_e, 0)).collect();
+				let mut steps = vec![AnimationStep {
+					colors: result.flow.keys().map(|edge_e| (**edge_e, colors.theme.edge)).collect(),
+					annotations: result
+						.capacity
+						.iter()
+						.map(|(edge_e, capacity)| (**edge_e, label(0, *capacity)))
+						.collect(),
+				}];
+				for (path, amount) in result.augmenting.iter() {
+					let mut step = AnimationStep::default();
+					for pair in path.windows(2) {
+						let (prev, part) = (pair[0].node, pair[1]);
+						let edge_e = part.edge.unwrap();
+						// Travelling against an edge cancels flow along it
+						let along = graph.edge_nodes[&edge_e].0 == prev;
+						let edge_flow = flow.get_mut(&edge_e).unwrap();
+						*edge_flow += if along { *amount } else { -*amount };
+
+						step.colors.push((*prev, color));
+						step.colors.push((*part.node, color));
+						step.colors.push((*edge_e, color));
+						step.annotations.push((*edge_e, label(*edge_flow, result.capacity[&edge_e])));
+					}
+					// Clear the path again before the next one is shown
+					let clear = AnimationStep {
+						colors: step
+							.colors
+							.iter()
+							.map(|(entity, _)| {
+								let is_node = graph.node_edges.contains_key(&NodeE(*entity));
+								(*entity, if is_node { colors.theme.node } else { colors.theme.edge })
+							})
+							.collect(),
+						annotations: Vec::new(),
+					};
+					steps.push(step);
+					steps.push(clear);
+				}
+				steps.push(last);
+				animation.play(steps);
+			} else {
+				let mut q_sprite = p_query.p0();
+				for (entity, color) in last.colors {
+					if let Ok(mut sprite) = q_sprite.get_mut(entity) {
+						sprite.color = color;
+					}
+				}
+				for (entity, text) in last.annotations {
+					graph_ev.send(GraphEvent::Annotate(entity, text));
+				}
+				ev_regen.send(RegenEdgeMesh());
+			}
+
+			let cut_capacity: i64 = result.cut.iter().map(|edge_e| result.capacity[edge_e]).sum();
+			alerts.0.push(format!(
+				"Maximum flow from {} to {} is {}, found with {} augmenting paths ({})\nMinimum cut: {} edges with total capacity {}",
+				labels.get(**source),
+				labels.get(**sink),
+				result.value,
+				result.augmenting.len(),
+				settings.method,
+				result.cut.len(),
+				cut_capacity
+			));
+		}
+	}
+}
+
+pub(crate) fn draw_matching(
+	mut events: EventReader<AnalyzeGraphEvent>,
+	graph: Res<Graph>,
+	colors: Colors,
+	mut alerts: ResMut<crate::ui::Alerts>,
+	mut p_query: ParamSet<(Query<&mut Sprite>, Query<&GEdge>)>,
+	labels: Labels,
+	(mut graph_ev, mut ev_regen): (EventWriter<GraphEvent>, EventWriter<RegenEdgeMesh>),
+) {
+	for event in events.read() {
+		if let AnalyzeGraphEvent::Matching(method) = event {
+			let matching = match graph.matching(*method, &p_query.p1()) {
+				Ok(matching) => matching,
+				Err(e) => {
+					alerts.0.push(e);
+					continue;
+				}
+			};
+
+			let color = colors.highlight();
+
+			// Matched edges and their ends are highlighted, nodes left
+			// without a partner are marked in red
+			let mut q_sprite = p_query.p0();
+			let mut matched = std::collections::HashSet::new();
+			for edge_e in matching.edges.iter() {
+				let (start, end) = graph.edge_nodes[edge_e];
+				matched.extend([start, end]);
+				for entity in [**edge_e, *start, *end] {
+					if let Ok(mut sprite) = q_sprite.get_mut(entity) {
+						sprite.color = color;
+					}
+				}
+			}
+			let unmatched = graph.node_count() - matched.len();
+			for node_e in graph.node_edges.keys().filter(|node_e| !matched.contains(*node_e)) {
+				if let Ok(mut sprite) = q_sprite.get_mut(**node_e) {
+					sprite.color = colors.theme.warning;
+				}
+			}
+			ev_regen.send(RegenEdgeMesh());
+
+			let mut message = format!(
+				"{method} with {}: {} edges, {unmatched} unmatched vertices",
+				matching.algorithm,
+				matching.edges.len()
+			);
+			if *method == MatchingMethod::Assignment {
+				message += &format!("\nTotal weight {}", matching.weight);
+			}
+			if let Some(cover) = matching.cover {
+				// König's theorem: a cover as small as the matching proves it's maximum
+				for node_e in cover.iter() {
+					graph_ev.send(GraphEvent::Annotate(**node_e, "cover".into()));
+				}
+				let mut names: Vec<String> = cover.iter().map(|node_e| labels.get(**node_e)).collect();
+				names.sort();
+				message += &format!(
+					"\nKönig vertex cover of size {}: {}",
+					cover.len(),
+					names.join(", ")
+				);
+			}
+			alerts.0.push(message);
+		}
+	}
+}
+
+pub(crate) fn draw_communities(
+	mut events: EventReader<AnalyzeGraphEvent>,
+	graph: Res<Graph>,
+	theme: Res<Theme>,
+	mut layout: ResMut<CommunityLayout>,
+	mut alerts: ResMut<crate::ui::Alerts>,
+	mut q_node: Query<&mut Sprite, GNodeExclusive>,
+	mut ev_regen: EventWriter<RegenEdgeMesh>,
+) {
+	for event in events.read() {
+		if let AnalyzeGraphEvent::Communities(method) = event {
+			let communities = graph.communities(*method);
+			for (node_e, community) in communities.of.iter() {
+				if let Ok(mut sprite) = q_node.get_mut(**node_e) {
+					sprite.color = theme.palette_color(*community);
+				}
+			}
+			ev_regen.send(RegenEdgeMesh());
+
+			let mut message = format!(
+				"{method} found {} communities, modularity Q = {:.4}",
+				communities.count, communities.modularity
+			);
+			if communities.count > theme.palette.len() {
+				message += "\nThere are more communities than palette colors, some colors are reused";
+			}
+			alerts.0.push(message);
+			layout.communities = Some(communities);
+		}
+	}
+}
@@ -1,10 +1,10 @@
 use bevy::{prelude::*, render::mesh::{VertexAttributeValues, Indices}};
 
-use crate::{types::{GNodeExclusive, GEdgeExclusive}, graph::{GEdge, Graph, NodeE, GNode}};
+use crate::{types::{GNodeExclusive, GEdgeExclusive, GBendExclusive}, graph::{EdgePattern, EdgeRoute, GEdge, Graph, NodeE, GNode}, input::MAX_ZOOM, materials::ATTRIBUTE_EDGE_STYLE};
 
-use super::{ItemMovedEvent, RegenEdgeMesh};
+use super::{edge_path, is_straight, path_midpoint, ItemMovedEvent, RegenEdgeMesh};
 
-fn loop_vertices(node_pos: Vec3, handle_pos: Vec3) -> [[f32; 3]; 4] {
+pub(crate) fn loop_vertices(node_pos: Vec3, handle_pos: Vec3) -> [[f32; 3]; 4] {
     let midpoint = node_pos.lerp(handle_pos, 0.5);
     let start_handle = node_pos - handle_pos;
     let orthagonal = Vec3::new(start_handle.y, -start_handle.x, 0.0).normalize();
@@ -19,12 +19,76 @@ fn loop_vertices(node_pos: Vec3, handle_pos: Vec3) -> [[f32; 3]; 4] {
     ]
 }
 
+/// The arrowhead of a directed loop, where its second half comes back into
+/// the node
+pub(crate) fn loop_arrow_vertices(node_pos: Vec3, handle_pos: Vec3, radius: f32, size: f32) -> [[f32; 3]; 3] {
+    let [_, _, _, rh] = loop_vertices(node_pos, handle_pos);
+    arrow_vertices(handle_pos, Vec3::from_array(rh), node_pos, radius, size)
+}
+
+/// Node sprites are drawn at this size unless resized
+static NODE_RADIUS: f32 = 15.0;
+/// Texture coordinates marking a triangle to be filled in solid rather than
+/// drawn as a curve
+static SOLID_UV: [f32; 2] = [0.0, -1.0];
+/// The v texture coordinate down the middle of a straight segment's quad,
+/// running from one less to one more across it
+static LINE_V: f32 = 3.0;
+
+fn node_radius(sprite: &Sprite) -> f32 {
+    sprite.custom_size.map_or(NODE_RADIUS, |size| size.x / 2.0)
+}
+
+/// The arrowhead of a directed edge, with its tip where the curve enters the
+/// end node and pointing along the curve there
+pub(crate) fn arrow_vertices(start: Vec3, control: Vec3, end: Vec3, radius: f32, size: f32) -> [[f32; 3]; 3] {
+    let point = |t: f32| start.lerp(control, t).lerp(control.lerp(end, t), t);
+
+    // Bisect for the point where the curve crosses the node's boundary
+    let (mut outside, mut inside) = (0.0, 1.0);
+    if start.distance(end) > radius {
+        for _ in 0..16 {
+            let t = (outside + inside) / 2.0;
+            if point(t).distance(end) < radius {
+                inside = t;
+            } else {
+                outside = t;
+            }
+        }
+    }
+    let t = (outside + inside) / 2.0;
+    let tip = point(t);
+    let direction = (2.0 * (1.0 - t) * (control - start) + 2.0 * t * (end - control)).normalize_or_zero();
+    let normal = Vec3::new(-direction.y, direction.x, 0.0) * size / 2.0;
+    let base = tip - direction * size;
+
+    [
+        [tip.x, tip.y, 0.0],
+        [base.x + normal.x, base.y + normal.y, 0.0],
+        [base.x - normal.x, base.y - normal.y, 0.0],
+    ]
+}
+
+/// Where an edge's handle goes when its nodes move from `old` to `new`. Its
+/// offset from the midpoint turns with the edge, so curved and fanned out
+/// parallel edges keep their shape.
+pub(crate) fn carry_handle(old: (Vec3, Vec3), new: (Vec3, Vec3), handle: Vec3) -> Vec3 {
+    let (old_dir, new_dir) = ((old.1 - old.0).truncate(), (new.1 - new.0).truncate());
+    let mut offset = (handle - old.0.lerp(old.1, 0.5)).truncate();
+    if old_dir.length_squared() > 1e-6 && new_dir.length_squared() > 1e-6 {
+        offset = Vec2::from_angle(old_dir.angle_between(new_dir)).rotate(offset);
+    }
+    (new.0.lerp(new.1, 0.5).truncate() + offset).extend(handle.z)
+}
+
 pub(crate) fn move_item_event(
     mut events: EventReader<ItemMovedEvent>,
     mut meshes: ResMut<Assets<Mesh>>,
     graph: Res<Graph>,
-    mut q_nodes: Query<(Entity, &mut Transform), GNodeExclusive>,
+    mut q_nodes: Query<(Entity, &mut Transform, &Sprite), GNodeExclusive>,
     mut q_edges: Query<(&GEdge, &mut Transform), GEdgeExclusive>,
+    mut q_bends: Query<&mut Transform, GBendExclusive>,
+    mut regen_ev: EventWriter<RegenEdgeMesh>,
 ) {
     let mesh: &mut Mesh = meshes.get_mut(&graph.edge_mesh_handle.0).unwrap();
     let positions = match mesh.attribute_mut(Mesh::ATTRIBUTE_POSITION).unwrap() {
@@ -34,11 +98,11 @@ pub(crate) fn move_item_event(
 
     for ItemMovedEvent(entity, delta) in events.read() {
         // Do the transformation separately to get around borrow restrictions
-        if let Ok((_, mut node_t)) = q_nodes.get_mut(*entity) {
+        if let Ok((_, mut node_t, _)) = q_nodes.get_mut(*entity) {
             node_t.translation += *delta;
         }
 
-        if let Ok((node_e, node_t)) = q_nodes.get(*entity) {
+        if let Ok((node_e, node_t, node_sprite)) = q_nodes.get(*entity) {
             for edge_e in graph.node_edges.get(&NodeE(node_e)).unwrap() {
                 let (edge, mut edge_t) = match q_edges.get_mut(**edge_e) {
                     Ok(e) => e,
@@ -53,28 +117,59 @@ pub(crate) fn move_item_event(
                             node_t.translation,
                             edge_t.translation,
                         ));
+                        if edge.arrow {
+                            positions[(offset + 4)..(offset + 7)].clone_from_slice(&loop_arrow_vertices(
+                                node_t.translation,
+                                edge_t.translation,
+                                node_radius(node_sprite),
+                                graph.arrow_size,
+                            ));
+                        }
 
                         continue;
                     } else {
-                        edge_t.translation += *delta / 2.0;
+                        let other = if *edge.start == node_e { edge.end } else { edge.start };
+                        let (_, other_t, _) = q_nodes.get(*other).unwrap();
+                        edge_t.translation = carry_handle(
+                            (node_t.translation - *delta, other_t.translation),
+                            (node_t.translation, other_t.translation),
+                            edge_t.translation,
+                        );
 
-                        if *edge.start == node_e {
-                            positions[offset] = node_t.translation.to_array();
+                        // Edges drawn as straight segments don't take the
+                        // same vertices as a curve, so draw them again
+                        if edge.path.is_some() {
+                            regen_ev.send(RegenEdgeMesh());
                         } else {
-                            positions[offset + 2] = node_t.translation.to_array();
+                            if *edge.start == node_e {
+                                positions[offset] = node_t.translation.to_array();
+                            } else {
+                                positions[offset + 2] = node_t.translation.to_array();
+                            }
+                            let start = Vec3::from_array(positions[offset]);
+                            let end = Vec3::from_array(positions[offset + 2]);
+                            let start_end_mid = start.lerp(end, 0.5);
+                            let pos = 2.0 * edge_t.translation - start_end_mid;
+                            positions[offset + 1] = pos.to_array();
+
+                            if edge.arrow {
+                                let (_, _, end_sprite) = q_nodes.get(*edge.end).unwrap();
+                                positions[(offset + 3)..(offset + 6)].clone_from_slice(&arrow_vertices(
+                                    start,
+                                    pos,
+                                    end,
+                                    node_radius(end_sprite),
+                                    graph.arrow_size,
+                                ));
+                            }
                         }
-                        let start = Vec3::from_array(positions[offset]);
-                        let end = Vec3::from_array(positions[offset + 2]);
-                        let start_end_mid = start.lerp(end, 0.5);
-                        let pos = 2.0 * edge_t.translation - start_end_mid;
-                        positions[offset + 1] = pos.to_array();
                     }
                 }
 
                 // Rotate the edge to point towards the other node
                 if node_e == *edge.start {
                     // The node being moved is the start node for this edge
-                    let (_, end_node_t) = q_nodes.get(*edge.end).unwrap();
+                    let (_, end_node_t, _) = q_nodes.get(*edge.end).unwrap();
                     let sign = if node_t.translation.y < end_node_t.translation.y {
                         1.0
                     } else {
@@ -85,7 +180,7 @@ pub(crate) fn move_item_event(
                     );
                 } else {
                     // The node being moved is the end node for this edge
-                    let (_, start_node_t) = q_nodes.get(*edge.start).unwrap();
+                    let (_, start_node_t, _) = q_nodes.get(*edge.start).unwrap();
                     let sign = if node_t.translation.y < start_node_t.translation.y {
                         -1.0
                     } else {
@@ -107,18 +202,66 @@ pub(crate) fn move_item_event(
                     let start = Vec3::from_array(positions[offset]);
                     positions[(offset)..(offset + 4)]
                         .clone_from_slice(&loop_vertices(start, edge_t.translation));
+                    if edge.arrow {
+                        let (_, _, node_sprite) = q_nodes.get(*edge.start).unwrap();
+                        positions[(offset + 4)..(offset + 7)].clone_from_slice(&loop_arrow_vertices(
+                            start,
+                            edge_t.translation,
+                            node_radius(node_sprite),
+                            graph.arrow_size,
+                        ));
+                    }
                 } else {
+                    let (_, start_t, _) = q_nodes.get(*edge.start).unwrap();
+                    let (_, end_t, _) = q_nodes.get(*edge.end).unwrap();
+                    if edge.path.is_some() || is_straight(start_t.translation, edge_t.translation, end_t.translation) {
+                        // Routed edges take their bends along with the handle
+                        for bend in edge.bends.iter() {
+                            if let Ok(mut bend_t) = q_bends.get_mut(*bend) {
+                                bend_t.translation += *delta;
+                            }
+                        }
+                        regen_ev.send(RegenEdgeMesh());
+                        continue;
+                    }
+
                     let start = Vec3::from_array(positions[offset]);
                     let end = Vec3::from_array(positions[offset + 2]);
                     let start_end_mid = start.lerp(end, 0.5);
                     let pos = 2.0 * edge_t.translation - start_end_mid;
                     positions[offset + 1] = pos.to_array();
+
+                    if edge.arrow {
+                        let (_, _, end_sprite) = q_nodes.get(*edge.end).unwrap();
+                        positions[(offset + 3)..(offset + 6)].clone_from_slice(&arrow_vertices(
+                            start,
+                            pos,
+                            end,
+                            node_radius(end_sprite),
+                            graph.arrow_size,
+                        ));
+                    }
                 }
             }
+        } else if let Ok(mut bend_t) = q_bends.get_mut(*entity) {
+            bend_t.translation += *delta;
+            regen_ev.send(RegenEdgeMesh());
         }
     }
 }
 
+/// An edge's thickness as drawn. Weights can scale it between half and two and
+/// a half times the edge's own, given the lightest and heaviest weights in the
+/// graph.
+pub(crate) fn edge_thickness(graph: &Graph, edge: &GEdge, (min_weight, max_weight): (i32, i32)) -> f32 {
+    let mut thickness = edge.style.thickness;
+    if graph.thickness_from_weight && max_weight > min_weight {
+        let t = (edge.weight - min_weight) as f32 / (max_weight - min_weight) as f32;
+        thickness *= 0.5 + 2.0 * t;
+    }
+    thickness
+}
+
 fn color_lerp(a: Color, b: Color, t: f32) -> Color {
     let a: Vec4 = a.into();
     let b: Vec4 = b.into();
@@ -131,9 +274,10 @@ pub(crate) fn regen_edge_mesh(
     graph: Res<Graph>,
     mut meshes: ResMut<Assets<Mesh>>,
     q_node: Query<(Entity, &mut GNode, &Transform, &Sprite), With<GNode>>,
-    mut q_edge: Query<(&mut GEdge, &Transform, &Sprite)>,
+    mut q_edge: Query<(&mut GEdge, &mut Transform, &Sprite), GEdgeExclusive>,
+    q_bends: Query<&Transform, GBendExclusive>,
 ) {
-    if let Some(_) = events.read().last() {
+    if events.read().last().is_some() {
         let mesh: &mut Mesh = meshes.get_mut(&graph.edge_mesh_handle.0).unwrap();
 
         // There needs to be some initial values here or the mesh gets optimized
@@ -145,22 +289,47 @@ pub(crate) fn regen_edge_mesh(
             [0.0, 0.0, 0.0, 1.0],
             [0.0, 0.0, 0.0, 1.0],
         ];
+        let mut styles: Vec<[f32; 4]> = vec![[0.0, 0.0, 0.0, 0.0]; 3];
         let mut indices: Vec<u32> = vec![0, 1, 2];
 
-        for (mut edge, edge_t, handle_sprite) in q_edge.iter_mut() {
+        let (min_weight, max_weight) = q_edge
+            .iter()
+            .fold((i32::MAX, i32::MIN), |(min, max), (edge, _, _)| (min.min(edge.weight), max.max(edge.weight)));
+
+        for (mut edge, mut edge_t, handle_sprite) in q_edge.iter_mut() {
+            let thickness = edge_thickness(&graph, &edge, (min_weight, max_weight));
+            let pattern = match edge.style.pattern {
+                EdgePattern::Solid => 0.0,
+                EdgePattern::Dashed => 1.0,
+                EdgePattern::Dotted => 2.0,
+            };
+            let opacity = edge.style.opacity;
+            let style = |length: f32| [thickness, pattern, opacity, length];
+
             let (_, _, start_t, start_sprite) = q_node.get(*edge.start).unwrap();
             let (_, _, end_t, end_sprite) = q_node.get(*edge.end).unwrap();
             let start = start_t.translation;
             let handle = edge_t.translation;
             let end = end_t.translation;
+            let bends: Vec<Vec2> = edge
+                .bends
+                .iter()
+                .filter_map(|bend| q_bends.get(*bend).ok())
+                .map(|t| t.translation.truncate())
+                .collect();
+            edge.path = if edge.is_loop() {
+                None
+            } else {
+                edge_path(edge.style.route, graph.straight_only, (start, handle, end), &bends)
+            };
 
             if edge.is_loop() {
                 edge.offset = Some(positions.len());
                 positions.extend_from_slice(&loop_vertices(start, handle));
                 tex_coords.extend_from_slice(&[[0.0, 0.0], [0.5, 0.0], [1.0, 1.0], [0.5, 0.0]]);
 
-                let start_color = start_sprite.color.into();
-                let handle_color = handle_sprite.color.into();
+                let start_color = start_sprite.color;
+                let handle_color = handle_sprite.color;
                 let midpoint_color = color_lerp(start_color, handle_color, 0.5);
 
                 colors.extend_from_slice(&[
@@ -170,8 +339,91 @@ pub(crate) fn regen_edge_mesh(
                     midpoint_color.into(),
                 ]);
 
+                // Each half of the loop is its own curve
+                let length = start.distance(handle) * 1.2;
+                styles.extend_from_slice(&[style(length); 4]);
+
                 let i = edge.offset.unwrap() as u32;
                 indices.extend_from_slice(&[i, i + 1, i + 2, i + 2, i + 3, i]);
+
+                // Directed loops get an arrowhead where they come back in
+                edge.arrow = graph.directed;
+                if edge.arrow {
+                    positions.extend_from_slice(&loop_arrow_vertices(
+                        start,
+                        handle,
+                        node_radius(start_sprite),
+                        graph.arrow_size,
+                    ));
+                    tex_coords.extend_from_slice(&[SOLID_UV; 3]);
+                    colors.extend_from_slice(&[handle_color.into(); 3]);
+                    styles.extend_from_slice(&[style(0.0); 3]);
+                    indices.extend_from_slice(&[i + 4, i + 5, i + 6]);
+                }
+            }
+            // Straight segments, each a quad filled in across its width
+            else if let Some(path) = edge.path.clone() {
+                edge.offset = Some(positions.len());
+                // A curved edge that's only drawn straight for being nearly so
+                // keeps its handle where it was put, so it can still be dragged
+                // out into a curve bit by bit
+                if graph.straight_only || edge.style.route != EdgeRoute::Curved {
+                    edge_t.translation = path_midpoint(&path).extend(handle.z);
+                }
+
+                let start_color = color_lerp(start_sprite.color, handle_sprite.color, 0.5);
+                let handle_color = handle_sprite.color;
+                let end_color = color_lerp(end_sprite.color, handle_sprite.color, 0.5);
+                let length: f32 = path.windows(2).map(|pair| pair[0].distance(pair[1])).sum();
+                // Blend from the start color to the handle's and on to the end
+                // color along the path
+                let color_at = |along: f32| -> [f32; 4] {
+                    let t = if length > 0.0 { along / length } else { 0.5 };
+                    if t < 0.5 {
+                        color_lerp(start_color, handle_color, t * 2.0).into()
+                    } else {
+                        color_lerp(handle_color, end_color, t * 2.0 - 1.0).into()
+                    }
+                };
+
+                // Wide enough to fit the line however far the camera zooms out
+                let half_width = (thickness + 1.0) * MAX_ZOOM;
+                let mut along = 0.0;
+                for pair in path.windows(2) {
+                    let (a, b) = (pair[0], pair[1]);
+                    let across = (b - a).perp().normalize_or_zero() * half_width;
+                    let i = positions.len() as u32;
+                    positions.extend([a + across, a - across, b + across, b - across].map(|v| v.extend(0.0).to_array()));
+                    tex_coords.extend_from_slice(&[
+                        [0.0, LINE_V + 1.0],
+                        [0.0, LINE_V - 1.0],
+                        [1.0, LINE_V + 1.0],
+                        [1.0, LINE_V - 1.0],
+                    ]);
+                    let segment = a.distance(b);
+                    let (color_a, color_b) = (color_at(along), color_at(along + segment));
+                    colors.extend_from_slice(&[color_a, color_a, color_b, color_b]);
+                    styles.extend_from_slice(&[style(segment); 4]);
+                    indices.extend_from_slice(&[i, i + 1, i + 2, i + 2, i + 1, i + 3]);
+                    along += segment;
+                }
+
+                edge.arrow = graph.directed;
+                if edge.arrow {
+                    let (a, b) = (path[path.len() - 2].extend(0.0), path[path.len() - 1].extend(0.0));
+                    let i = positions.len() as u32;
+                    positions.extend_from_slice(&arrow_vertices(
+                        a,
+                        a.lerp(b, 0.5),
+                        b,
+                        node_radius(end_sprite),
+                        graph.arrow_size,
+                    ));
+                    tex_coords.extend_from_slice(&[SOLID_UV; 3]);
+                    colors.extend_from_slice(&[handle_color.into(); 3]);
+                    styles.extend_from_slice(&[style(0.0); 3]);
+                    indices.extend_from_slice(&[i, i + 1, i + 2]);
+                }
             }
             // Regular edge
             else {
@@ -199,14 +451,37 @@ pub(crate) fn regen_edge_mesh(
                     end_color.into(),
                 ]);
 
+                // The average of the chord and control polygon is close enough
+                // to the curve's length for spacing out dashes
+                let length = (start.distance(end) + start.distance(handle) + handle.distance(end)) / 2.0;
+                styles.extend_from_slice(&[style(length); 3]);
+
                 let i = edge.offset.unwrap() as u32;
                 indices.extend_from_slice(&[i, i + 1, i + 2]);
+
+                // Directed edges get an arrowhead at the end node, following
+                // the curve's direction as it enters
+                edge.arrow = graph.directed;
+                if edge.arrow {
+                    positions.extend_from_slice(&arrow_vertices(
+                        start,
+                        handle,
+                        end,
+                        node_radius(end_sprite),
+                        graph.arrow_size,
+                    ));
+                    tex_coords.extend_from_slice(&[SOLID_UV; 3]);
+                    colors.extend_from_slice(&[handle_color.into(); 3]);
+                    styles.extend_from_slice(&[style(0.0); 3]);
+                    indices.extend_from_slice(&[i + 3, i + 4, i + 5]);
+                }
             }
         }
 
         mesh.insert_attribute(Mesh::ATTRIBUTE_POSITION, positions);
         mesh.insert_attribute(Mesh::ATTRIBUTE_UV_0, tex_coords);
         mesh.insert_attribute(Mesh::ATTRIBUTE_COLOR, colors);
+        mesh.insert_attribute(ATTRIBUTE_EDGE_STYLE, styles);
         mesh.set_indices(Some(Indices::U32(indices)));
     }
 }
\ No newline at end of file
@@ -1,34 +1,39 @@
 use bevy::prelude::*;
 
-use crate::{graph::{GEdge, GNode, Graph, NodeE}, types::{GNodeExclusive, GEdgeExclusive}, input::CursorInfo};
+use crate::{graph::{GEdge, GNode, Graph, NodeE, community::CommunityLayout}, types::{GNodeExclusive, GEdgeExclusive}, input::CursorInfo};
 
-use super::{GraphEvent, ItemMovedEvent, offset_midpoint};
+use super::{GraphEvent, ItemMovedEvent, fan_midpoint};
 
 static EDGE_SPRING_CONSTANT: f32 = 5.0;
 static EDGE_SPRING_LENGTH: f32 = 125.0;
 static NODE_GRAVITY: f32 = 10000.0;
 static NODE_REPEL_RADIUS: f32 = 350.0;
+static COMMUNITY_SPRING_CONSTANT: f32 = 0.5;
+static COMMUNITY_REPEL: f32 = 20000.0;
 
 pub(crate) fn physics_init_event(
 	mut events: EventReader<GraphEvent>,
+	graph: Res<Graph>,
 	mut move_ev: EventWriter<ItemMovedEvent>,
 	mut q_edges: Query<(Entity, &GEdge, &mut Transform), GEdgeExclusive>,
 	q_nodes: Query<&mut Transform, GNodeExclusive>
 ) {
-	match events.read().last() {
-		Some(GraphEvent::PhysicsInit) => {
-			for (edge_e, edge, edge_t) in q_edges.iter_mut() {
-				if edge.is_loop() {
-					continue;
-				}
-				let start_t = q_nodes.get(*edge.start).unwrap();
-				let end_t = q_nodes.get(*edge.end).unwrap();
-				let midpoint = offset_midpoint(start_t.translation, end_t.translation, 20.0);
-				let diff = midpoint - edge_t.translation;
-				move_ev.send(ItemMovedEvent(edge_e, diff));
+	if let Some(GraphEvent::PhysicsInit) = events.read().last() {
+		for (edge_e, edge, edge_t) in q_edges.iter_mut() {
+			if edge.is_loop() {
+				continue;
 			}
+			let start_t = q_nodes.get(*edge.start).unwrap();
+			let end_t = q_nodes.get(*edge.end).unwrap();
+			let index = graph.parallel_edges(&edge.start, &edge.end).iter().position(|e| **e == edge_e).unwrap_or(0);
+			let midpoint = if edge.start.0 < edge.end.0 {
+				fan_midpoint(start_t.translation, end_t.translation, index)
+			} else {
+				fan_midpoint(end_t.translation, start_t.translation, index)
+			};
+			let diff = midpoint - edge_t.translation;
+			move_ev.send(ItemMovedEvent(edge_e, diff));
 		}
-		_ => ()
 	}
 }
 
@@ -36,6 +41,7 @@ pub(crate) fn physics_sim_system(
 	time: Res<Time>,
 	graph: Res<Graph>,
 	cursor: Res<CursorInfo>,
+	layout: Res<CommunityLayout>,
 	q_nodes: Query<(Entity, &GNode, &Transform)>,
 	mut move_ev: EventWriter<ItemMovedEvent>,
 ) {
@@ -50,7 +56,7 @@ pub(crate) fn physics_sim_system(
 
 	// for current_node_e in graph.node_edges.keys().filter(|node_e| cursor.grabbed != Some(***node_e)) {
 	for (current_node_e, _, _) in q_nodes.iter().filter(|node| cursor.grabbed != Some(node.0)) {
-		let current_node_t = q_nodes.get(current_node_e).unwrap().2.clone();
+		let current_node_t = *q_nodes.get(current_node_e).unwrap().2;
 		let mut translation = current_node_t.translation;
 		for (node_e, _, node_t) in q_nodes.iter().filter(|(node_e, _, _)| *node_e != current_node_e) {
 			let diff = node_t.translation - translation;
@@ -62,6 +68,18 @@ pub(crate) fn physics_sim_system(
 			if graph.is_adjacent(&NodeE(current_node_e), &NodeE(node_e)) {
 				force += dir * (EDGE_SPRING_CONSTANT * (dist - EDGE_SPRING_LENGTH));
 			}
+			if let Some(of) = layout.communities.as_ref().filter(|_| layout.forces).map(|c| &c.of) {
+				// Pull nodes in the same community together and push the rest away
+				match (of.get(&NodeE(current_node_e)), of.get(&NodeE(node_e))) {
+					(Some(a), Some(b)) if a == b => {
+						force += dir * (COMMUNITY_SPRING_CONSTANT * (dist - EDGE_SPRING_LENGTH)).max(0.0);
+					}
+					(Some(_), Some(_)) => {
+						force -= dir * (COMMUNITY_REPEL / dist);
+					}
+					_ => (),
+				}
+			}
 
 			translation += force * dt;
 		}
@@ -1,22 +1,33 @@
 use bevy::prelude::*;
 
-use crate::graph::{Graph, GNode, GEdge, EdgeE, NodeE};
+use crate::{graph::{Graph, GNode, GEdge, EdgeBend, EdgeE, NodeE}, input::CursorInfo};
 
 use super::{GraphEvent, RegenEdgeMesh};
 
 pub(crate) fn remove_item_event(
     mut events: EventReader<GraphEvent>,
     mut regen_ev: EventWriter<RegenEdgeMesh>,
-    mut graph: ResMut<Graph>,
+    (mut graph, mut cursor): (ResMut<Graph>, ResMut<CursorInfo>),
     mut commands: Commands,
     q_nodes: Query<&mut GNode>,
     mut q_edges: Query<(Entity, &mut GEdge)>,
+    q_bends: Query<&EdgeBend>,
 ) {
     let mut removed_edges = Vec::new();
     for event in events.read() {
 		if let GraphEvent::RemoveItem(entity) = event {
+			// Bends come out of their edge, leaving the edge in place
+			if let Ok(bend) = q_bends.get(*entity) {
+				if let Ok((_, mut edge)) = q_edges.get_mut(*bend.edge) {
+					edge.bends.retain(|b| b != entity);
+				}
+				commands.entity(*entity).despawn();
+				regen_ev.send(RegenEdgeMesh());
+				continue;
+			}
+
 			// Collect any edges that will be destroyed by this removal
-			if let Ok(_) = q_nodes.get(*entity) {
+			if q_nodes.get(*entity).is_ok() {
 				for edge_e in graph.node_edges.get(&NodeE(*entity)).unwrap() {
 					removed_edges.push(*edge_e);
 				}
@@ -25,18 +36,22 @@ pub(crate) fn remove_item_event(
 			}
 
 			for edge_e in removed_edges.iter() {
-				let offset_removed: Option<usize> = if let Ok((_, edge)) = q_edges.get(**edge_e) {
+				let offset_removed: Option<(usize, usize)> = if let Ok((_, edge)) = q_edges.get(**edge_e) {
 					graph.remove_edge(*edge_e, &edge.start, &edge.end);
-					edge.offset
+					for bend in edge.bends.iter() {
+						commands.entity(*bend).despawn();
+					}
+					edge.offset.map(|offset| (offset, edge.size_in_mesh()))
 				} else {
 					println!("Tried to remove an edge that doesn't exist");
 					None
 				};
 
-				if let Some(offset_removed) = offset_removed {
+				// Edges no longer all take the same number of vertices, so shift
+				// by the size of the one removed
+				if let Some((offset_removed, removed_size)) = offset_removed {
 					for (_, mut edge) in q_edges.iter_mut() {
 						if edge.offset.is_some() && edge.offset.unwrap() > offset_removed {
-							let removed_size = edge.size_in_mesh();
 							edge.offset = edge.offset.map(|o| o - removed_size);
 						}
 					}
@@ -46,8 +61,9 @@ pub(crate) fn remove_item_event(
 			removed_edges.clear();
 
 			// Actually remove the node after its edges are removed
-			if let Ok(_) = q_nodes.get(*entity) {
+			if q_nodes.get(*entity).is_ok() {
 				graph.remove_node(&(*entity).into());
+				cursor.group.retain(|node| **node != *entity);
 				commands.entity(*entity).despawn_recursive();
 			}
 
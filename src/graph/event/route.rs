@@ -0,0 +1,174 @@
+use bevy::prelude::*;
+
+use crate::{
+    graph::{plugin::ImageCache, EdgeBend, EdgeE, EdgeRoute, GEdge, Grabbable, Graph},
+    theme::Theme,
+    types::{GBendExclusive, GEdgeExclusive, GNodeExclusive},
+};
+
+use super::{GraphEvent, RegenEdgeMesh};
+
+/// Handles closer than this to the middle of their edge are drawn straight,
+/// since the curve through them would be too flat to draw
+static STRAIGHT_TOLERANCE: f32 = 2.0;
+
+pub(crate) fn is_straight(start: Vec3, handle: Vec3, end: Vec3) -> bool {
+    handle.distance(start.lerp(end, 0.5)) < STRAIGHT_TOLERANCE
+}
+
+/// The corners of an edge drawn as straight segments, from its start node
+/// through any bend points to its end node, or `None` if it's drawn as a curve
+/// through its handle
+pub(crate) fn edge_path(
+    route: EdgeRoute,
+    straight_only: bool,
+    (start, handle, end): (Vec3, Vec3, Vec3),
+    bends: &[Vec2],
+) -> Option<Vec<Vec2>> {
+    let (start, end) = (start.truncate(), end.truncate());
+    let points = || std::iter::once(start).chain(bends.iter().copied()).chain(std::iter::once(end));
+
+    let mut path: Vec<Vec2> = match route {
+        EdgeRoute::Curved if straight_only || is_straight(start.extend(0.0), handle, end.extend(0.0)) => {
+            vec![start, end]
+        }
+        EdgeRoute::Curved => return None,
+        EdgeRoute::Polyline => points().collect(),
+        EdgeRoute::Orthogonal => {
+            let points: Vec<Vec2> = points().collect();
+            let mut path = vec![start];
+            for (i, pair) in points.windows(2).enumerate() {
+                // Alternate between going across and going up or down first,
+                // so the path steps through the bends like a staircase
+                let corner = if i % 2 == 0 {
+                    Vec2::new(pair[1].x, pair[0].y)
+                } else {
+                    Vec2::new(pair[0].x, pair[1].y)
+                };
+                path.extend([corner, pair[1]]);
+            }
+            path
+        }
+    };
+
+    path.dedup_by(|a, b| a.distance(*b) < 1e-3);
+    if path.len() < 2 {
+        path = vec![start, end];
+    }
+    Some(path)
+}
+
+/// The point halfway along a path, where the handle of an edge drawn as
+/// straight segments sits
+pub(crate) fn path_midpoint(path: &[Vec2]) -> Vec2 {
+    let length: f32 = path.windows(2).map(|pair| pair[0].distance(pair[1])).sum();
+    let mut remaining = length / 2.0;
+    for pair in path.windows(2) {
+        let segment = pair[0].distance(pair[1]);
+        if remaining <= segment && segment > 0.0 {
+            return pair[0].lerp(pair[1], remaining / segment);
+        }
+        remaining -= segment;
+    }
+    path[0]
+}
+
+/// Add a bend point to an edge. A curved edge turns into a polyline bent
+/// where its handle is, otherwise the bend goes halfway along the longest
+/// stretch between the nodes and the bends already there.
+pub(crate) fn add_bend_event(
+    mut events: EventReader<GraphEvent>,
+    mut commands: Commands,
+    mut q_edges: Query<(&mut GEdge, &Transform), GEdgeExclusive>,
+    q_nodes: Query<&Transform, GNodeExclusive>,
+    q_bends: Query<&Transform, GBendExclusive>,
+    (cache, theme): (Res<ImageCache>, Res<Theme>),
+    mut regen_ev: EventWriter<RegenEdgeMesh>,
+) {
+    for event in events.read() {
+        let GraphEvent::AddBend(edge_e) = event else {
+            continue;
+        };
+        let Ok((mut edge, edge_t)) = q_edges.get_mut(**edge_e) else {
+            continue;
+        };
+        if edge.is_loop() {
+            continue;
+        }
+
+        let (index, pos) = if edge.style.route == EdgeRoute::Curved {
+            edge.style.route = EdgeRoute::Polyline;
+            (0, edge_t.translation.truncate())
+        } else {
+            let points: Vec<Vec2> = std::iter::once(q_nodes.get(*edge.start).unwrap())
+                .chain(edge.bends.iter().filter_map(|bend| q_bends.get(*bend).ok()))
+                .chain(std::iter::once(q_nodes.get(*edge.end).unwrap()))
+                .map(|t| t.translation.truncate())
+                .collect();
+            let (i, pair) = points
+                .windows(2)
+                .enumerate()
+                .max_by(|(_, a), (_, b)| a[0].distance(a[1]).total_cmp(&b[0].distance(b[1])))
+                .unwrap();
+            (i, pair[0].lerp(pair[1], 0.5))
+        };
+
+        let bend = commands
+            .spawn((
+                EdgeBend { edge: *edge_e },
+                Grabbable::Circle { radius: 8.0 },
+                SpriteBundle {
+                    sprite: Sprite {
+                        color: theme.edge,
+                        custom_size: Some(Vec2::splat(12.0)),
+                        ..Default::default()
+                    },
+                    texture: cache.get("node").unwrap().clone(),
+                    transform: Transform::from_translation(pos.extend(0.0)),
+                    ..Default::default()
+                },
+            ))
+            .id();
+        edge.bends.insert(index, bend);
+        regen_ev.send(RegenEdgeMesh());
+    }
+}
+
+/// Take the bends out of an edge, or every edge, and put its handle back in
+/// the middle so it's drawn as a straight line
+pub(crate) fn straighten_event(
+    mut events: EventReader<GraphEvent>,
+    mut commands: Commands,
+    graph: Res<Graph>,
+    mut q_edges: Query<(&mut GEdge, &mut Transform), GEdgeExclusive>,
+    q_nodes: Query<&Transform, GNodeExclusive>,
+    mut regen_ev: EventWriter<RegenEdgeMesh>,
+) {
+    for event in events.read() {
+        let GraphEvent::Straighten(only) = event else {
+            continue;
+        };
+
+        let edges: Vec<EdgeE> = match only {
+            Some(edge_e) => vec![*edge_e],
+            None => graph.edge_nodes.keys().copied().collect(),
+        };
+        for edge_e in edges {
+            let Ok((mut edge, mut edge_t)) = q_edges.get_mut(*edge_e) else {
+                continue;
+            };
+            if edge.is_loop() {
+                continue;
+            }
+
+            for bend in edge.bends.drain(..) {
+                commands.entity(bend).despawn();
+            }
+            edge.style.route = EdgeRoute::Curved;
+            let (start_t, end_t) = (q_nodes.get(*edge.start).unwrap(), q_nodes.get(*edge.end).unwrap());
+            let midpoint = start_t.translation.lerp(end_t.translation, 0.5);
+            edge_t.translation = midpoint.truncate().extend(edge_t.translation.z);
+        }
+        regen_ev.send(RegenEdgeMesh());
+    }
+}
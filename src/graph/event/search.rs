@@ -0,0 +1,249 @@
+use std::sync::{
+	atomic::{AtomicBool, Ordering},
+	Arc, Mutex,
+};
+
+use bevy::{prelude::*, window::RequestRedraw};
+
+use crate::{
+	graph::{
+		coloring::{
+			Coloring, ColoringMethod, EdgeColoring, EdgeColoringMethod, EXACT_COLORING_LIMIT,
+			EXACT_EDGE_COLORING_LIMIT,
+		},
+		hamilton::{Cancelled, HAMILTONIAN_LIMIT, TSP_EXACT_LIMIT},
+		GEdge, Graph, PathPart,
+	},
+	theme::Colors,
+};
+
+use super::{
+	draw::{coloring_message, edge_coloring_message, Labels},
+	AnalyzeGraphEvent, RegenEdgeMesh,
+};
+
+enum SearchOutcome {
+	Hamiltonian { cycle: bool, path: Option<Vec<PathPart>> },
+	Tour { path: Option<Vec<PathPart>>, cost: i64, exact: bool },
+	Coloring(Coloring),
+	EdgeColoring(EdgeColoring),
+	Cancelled,
+}
+
+struct SearchTask {
+	cancel: Arc<AtomicBool>,
+	result: Arc<Mutex<Option<SearchOutcome>>>,
+}
+
+/// A search that might take a long time, run on its own thread so it can be
+/// cancelled from the UI
+#[derive(Resource, Default)]
+pub(crate) struct BackgroundSearch(Option<SearchTask>);
+
+impl BackgroundSearch {
+	pub fn is_running(&self) -> bool {
+		self.0.is_some()
+	}
+
+	pub fn cancel(&self) {
+		if let Some(task) = &self.0 {
+			task.cancel.store(true, Ordering::Relaxed);
+		}
+	}
+
+	fn start<F>(&mut self, search: F)
+	where
+		F: FnOnce(&AtomicBool) -> SearchOutcome + Send + 'static,
+	{
+		self.cancel();
+		let task = SearchTask {
+			cancel: Arc::new(AtomicBool::new(false)),
+			result: Arc::new(Mutex::new(None)),
+		};
+
+		let (cancel, result) = (task.cancel.clone(), task.result.clone());
+		std::thread::spawn(move || {
+			let outcome = search(&cancel);
+			*result.lock().unwrap() = Some(outcome);
+		});
+		self.0 = Some(task);
+	}
+}
+
+pub(crate) fn start_search(
+	mut events: EventReader<AnalyzeGraphEvent>,
+	graph: Res<Graph>,
+	q_edge: Query<&GEdge>,
+	mut search: ResMut<BackgroundSearch>,
+	mut alerts: ResMut<crate::ui::Alerts>,
+) {
+	for event in events.read() {
+		match event {
+			AnalyzeGraphEvent::Hamiltonian { cycle } => {
+				if graph.node_count() > HAMILTONIAN_LIMIT {
+					alerts.0.push(format!(
+						"Hamiltonian search is limited to graphs with at most {HAMILTONIAN_LIMIT} vertices"
+					));
+					continue;
+				}
+
+				let (adj, cycle) = (graph.weighted_adjacency(&q_edge), *cycle);
+				search.start(move |cancel| match adj.hamiltonian(cycle, cancel) {
+					Ok(order) => SearchOutcome::Hamiltonian {
+						cycle,
+						path: order.map(|order| adj.path(&order)),
+					},
+					Err(Cancelled) => SearchOutcome::Cancelled,
+				});
+			}
+			AnalyzeGraphEvent::Tsp => {
+				let adj = graph.weighted_adjacency(&q_edge);
+				if !adj.is_complete() {
+					alerts.0.push("The travelling salesman tour needs a complete graph".into());
+					continue;
+				}
+				if adj.nodes.len() < adj.min_cycle() {
+					alerts.0.push(format!(
+						"The travelling salesman tour needs at least {} vertices",
+						adj.min_cycle()
+					));
+					continue;
+				}
+
+				let exact = adj.nodes.len() <= TSP_EXACT_LIMIT;
+				search.start(move |cancel| {
+					let tour = if exact {
+						adj.tsp_exact(cancel)
+					} else {
+						adj.tsp_heuristic(cancel)
+					};
+					match tour {
+						Ok(order) => SearchOutcome::Tour {
+							cost: order.as_ref().and_then(|o| adj.cost(o)).unwrap_or_default(),
+							path: order.map(|order| adj.path(&order)),
+							exact,
+						},
+						Err(Cancelled) => SearchOutcome::Cancelled,
+					}
+				});
+			}
+			AnalyzeGraphEvent::Coloring(ColoringMethod::Exact) => {
+				if graph.node_count() > EXACT_COLORING_LIMIT {
+					alerts.0.push(format!(
+						"Exact coloring is limited to graphs with at most {EXACT_COLORING_LIMIT} vertices"
+					));
+					continue;
+				}
+
+				let problem = graph.coloring_problem();
+				search.start(move |cancel| match problem.solve(cancel) {
+					Ok(coloring) => SearchOutcome::Coloring(coloring),
+					Err(Cancelled) => SearchOutcome::Cancelled,
+				});
+			}
+			AnalyzeGraphEvent::EdgeColoring(method @ EdgeColoringMethod::Exact) => {
+				let problem = match graph.edge_coloring_problem() {
+					Ok(problem) => problem,
+					Err(e) => {
+						alerts.0.push(e);
+						continue;
+					}
+				};
+				if problem.edge_count() > EXACT_EDGE_COLORING_LIMIT {
+					alerts.0.push(format!(
+						"Exact edge coloring is limited to graphs with at most {EXACT_EDGE_COLORING_LIMIT} edges"
+					));
+					continue;
+				}
+
+				let method = *method;
+				search.start(move |cancel| match problem.solve(method, cancel) {
+					Ok(coloring) => SearchOutcome::EdgeColoring(coloring),
+					Err(Cancelled) => SearchOutcome::Cancelled,
+				});
+			}
+			_ => (),
+		}
+	}
+}
+
+pub(crate) fn finish_search(
+	mut search: ResMut<BackgroundSearch>,
+	(graph, colors): (Res<Graph>, Colors),
+	mut alerts: ResMut<crate::ui::Alerts>,
+	mut q_sprite: Query<&mut Sprite>,
+	labels: Labels,
+	mut ev_regen: EventWriter<RegenEdgeMesh>,
+	mut ev_redraw: EventWriter<RequestRedraw>,
+) {
+	let Some(task) = &search.0 else {
+		return;
+	};
+	let Some(outcome) = task.result.lock().unwrap().take() else {
+		// Keep polling until the search thread finishes
+		ev_redraw.send(RequestRedraw);
+		return;
+	};
+	search.0 = None;
+
+	let (path, message) = match outcome {
+		SearchOutcome::Cancelled => return,
+		SearchOutcome::Coloring(coloring) => {
+			for (node_e, class) in coloring.iter() {
+				if let Ok(mut sprite) = q_sprite.get_mut(**node_e) {
+					sprite.color = colors.theme.palette_color(*class);
+				}
+			}
+			ev_regen.send(RegenEdgeMesh());
+			alerts.0.push(coloring_message(&graph, &colors.theme, ColoringMethod::Exact, &coloring));
+			return;
+		}
+		SearchOutcome::EdgeColoring(coloring) => {
+			for (edge_e, class) in coloring.colors.iter() {
+				if let Ok(mut sprite) = q_sprite.get_mut(**edge_e) {
+					sprite.color = colors.theme.palette_color(*class);
+				}
+			}
+			ev_regen.send(RegenEdgeMesh());
+			alerts.0.push(edge_coloring_message(&colors.theme, EdgeColoringMethod::Exact, &coloring));
+			return;
+		}
+		SearchOutcome::Hamiltonian { cycle, path } => {
+			let kind = if cycle { "cycle" } else { "path" };
+			let message = match &path {
+				Some(_) => format!("Hamiltonian {kind}:"),
+				None => format!("The graph has no Hamiltonian {kind}"),
+			};
+			(path, message)
+		}
+		SearchOutcome::Tour { path, cost, exact } => {
+			let message = match (&path, exact) {
+				(None, _) => "No tour exists".to_string(),
+				(Some(_), true) => format!("Optimal tour, total weight {cost}:"),
+				(Some(_), false) => format!("Tour found with nearest neighbour + 2-opt, total weight {cost}:"),
+			};
+			(path, message)
+		}
+	};
+
+	let Some(path) = path else {
+		alerts.0.push(message);
+		return;
+	};
+
+	let color = colors.highlight();
+	for part in path.iter() {
+		if let Ok(mut sprite) = q_sprite.get_mut(*part.node) {
+			sprite.color = color;
+		}
+		if let Some(edge_e) = part.edge {
+			if let Ok(mut sprite) = q_sprite.get_mut(*edge_e) {
+				sprite.color = color;
+			}
+		}
+	}
+	ev_regen.send(RegenEdgeMesh());
+
+	let names: Vec<String> = path.iter().map(|part| labels.get(*part.node)).collect();
+	alerts.0.push(format!("{message}\n{}", names.join(" → ")));
+}
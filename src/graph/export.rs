@@ -0,0 +1,783 @@
+//! Drawing the graph to SVG and PNG images on the CPU, or to TikZ for LaTeX,
+//! so figures can be produced from saved graphs on machines without a GPU or
+//! a window.
+//!
+//! Edges are the same quadratic Bézier curves the edge mesh draws, with the
+//! control point at `2 * handle - midpoint` so the curve passes through the
+//! handle. Edges the mesh draws as straight segments are curves with their
+//! control points halfway along each segment.
+
+use std::{collections::HashMap, fmt::Write, path::Path};
+
+use ab_glyph::{Font, FontRef, OutlineCurve, ScaleFont};
+use bevy::prelude::*;
+use tiny_skia::{FillRule, FilterQuality, LineCap, Paint, PathBuilder, Pixmap, PixmapPaint, Stroke, StrokeDash};
+
+use crate::{
+    theme::Theme,
+    types::{GEdgeExclusive, GNodeExclusive},
+};
+
+use super::{
+    event::{arrow_vertices, edge_thickness, fan_loop, fan_midpoint, loop_arrow_vertices, loop_vertices},
+    io,
+    shape::{NodeShape, NodeStyle},
+    template::GraphTemplate,
+    Annotation, EdgeE, EdgePattern, EdgeStyle, GEdge, Graph, NodeE, WeightLabel,
+};
+
+static FONT: &[u8] = include_bytes!("../../assets/fonts/FiraSans-Regular.ttf");
+/// Empty space left around the graph
+static MARGIN: f32 = 20.0;
+/// Pixels per world unit in PNG images
+static PNG_SCALE: f32 = 2.0;
+/// Labels sit this far above their node, as they do on screen
+static LABEL_OFFSET: f32 = 30.0;
+static LABEL_SIZE: f32 = 25.0;
+/// Centimetres per world unit in TikZ pictures
+static TIKZ_UNIT: f32 = 0.02;
+
+pub struct DrawnText {
+    /// Where the middle of the text goes
+    pub pos: Vec2,
+    pub text: String,
+    pub size: f32,
+    pub color: Color,
+}
+
+pub struct DrawnNode {
+    pub pos: Vec2,
+    pub style: NodeStyle,
+    pub color: Color,
+    pub label: Option<DrawnText>,
+}
+
+/// An edge between two of the drawing's nodes, by index. An edge from a node
+/// to itself is a loop.
+pub struct DrawnEdge {
+    pub start: usize,
+    pub end: usize,
+    pub handle: Vec2,
+    pub weight: i32,
+    pub color: Color,
+    /// The edge's style, with its thickness already scaled by weight if the
+    /// graph does that
+    pub style: EdgeStyle,
+    /// The corners of an edge drawn as straight segments rather than a curve
+    pub path: Option<Vec<Vec2>>,
+    pub label: Option<DrawnText>,
+    pub weight_label: Option<DrawnText>,
+}
+
+/// The labels, weight labels and annotations drawn with the graph
+pub(crate) type ExportedText = (
+    &'static Text,
+    &'static GlobalTransform,
+    &'static Visibility,
+    Option<&'static Annotation>,
+    Has<WeightLabel>,
+);
+
+/// Everything needed to draw the graph as it appears on screen, without
+/// reference to the ECS
+pub struct Drawing {
+    pub nodes: Vec<DrawnNode>,
+    pub edges: Vec<DrawnEdge>,
+    /// Text shown by algorithms, as opposed to labels
+    pub annotations: Vec<DrawnText>,
+    pub directed: bool,
+    pub arrow_size: f32,
+    pub background: Color,
+}
+
+impl Drawing {
+    /// Lay out a saved graph the way spawning it would: handles fanned out
+    /// between their nodes and everything in the theme's default colors. Only
+    /// nodes are labeled, since edge labels aren't saved.
+    pub fn from_template(t: &GraphTemplate, directed: bool, theme: &Theme) -> Self {
+        let nodes = t
+            .nodes
+            .iter()
+            .enumerate()
+            .map(|(i, node)| DrawnNode {
+                pos: node.pos,
+                style: node.style.clone(),
+                color: theme.node,
+                label: Some(DrawnText {
+                    pos: node.pos + Vec2::Y * LABEL_OFFSET,
+                    text: node.label.clone().unwrap_or_else(|| format!("v{i}")),
+                    size: LABEL_SIZE,
+                    color: theme.label,
+                }),
+            })
+            .collect();
+
+        let mut edges: Vec<DrawnEdge> = Vec::new();
+        for edge in t.edges.iter() {
+            let (a, b) = (edge.start.min(edge.end), edge.start.max(edge.end));
+            let index = edges
+                .iter()
+                .filter(|e| (e.start.min(e.end), e.start.max(e.end)) == (a, b))
+                .count();
+            let (s, e) = (t.nodes[a].pos.extend(0.0), t.nodes[b].pos.extend(0.0));
+            let handle = if a == b {
+                fan_loop(s, index)
+            } else {
+                fan_midpoint(s, e, index)
+            };
+            edges.push(DrawnEdge {
+                start: edge.start,
+                end: edge.end,
+                handle: handle.truncate(),
+                weight: edge.weight,
+                color: theme.edge,
+                style: EdgeStyle::default(),
+                path: None,
+                label: None,
+                weight_label: None,
+            });
+        }
+
+        Drawing {
+            nodes,
+            edges,
+            annotations: Vec::new(),
+            directed,
+            arrow_size: 12.0,
+            background: theme.background,
+        }
+    }
+
+    /// Capture the graph as it's currently drawn, including colors and any
+    /// visible labels and annotations
+    pub fn from_world(
+        graph: &Graph,
+        theme: &Theme,
+        q_nodes: &Query<(&Transform, &Sprite, &NodeStyle, &Children), GNodeExclusive>,
+        q_edges: &Query<(&GEdge, &Transform, &Sprite, &Children), GEdgeExclusive>,
+        q_text: &Query<ExportedText>,
+    ) -> Self {
+        let mut annotations = Vec::new();
+        // The label and weight label of an item, collecting its annotations
+        // on the way
+        let mut label = |children: &Children| {
+            let (mut label, mut weight) = (None, None);
+            for (text, transform, visibility, annotation, weight_label) in children.iter().filter_map(|c| q_text.get(*c).ok()) {
+                if *visibility == Visibility::Hidden {
+                    continue;
+                }
+                let (scale, _, translation) = transform.to_scale_rotation_translation();
+                let drawn = DrawnText {
+                    pos: translation.truncate(),
+                    text: text.sections.iter().map(|s| s.value.as_str()).collect(),
+                    size: text.sections[0].style.font_size * scale.y,
+                    color: text.sections[0].style.color,
+                };
+                if annotation.is_some() {
+                    annotations.push(drawn);
+                } else if weight_label {
+                    weight = Some(drawn);
+                } else {
+                    label = Some(drawn);
+                }
+            }
+            (label, weight)
+        };
+
+        let mut entities: Vec<NodeE> = graph.node_edges.keys().copied().collect();
+        entities.sort_by_key(|node| node.0);
+        let mut index = HashMap::new();
+        let mut nodes = Vec::new();
+        for node_e in entities {
+            let Ok((transform, sprite, style, children)) = q_nodes.get(*node_e) else {
+                continue;
+            };
+            index.insert(node_e, nodes.len());
+            nodes.push(DrawnNode {
+                pos: transform.translation.truncate(),
+                style: style.clone(),
+                color: sprite.color,
+                label: label(children).0,
+            });
+        }
+
+        let weights = q_edges
+            .iter()
+            .fold((i32::MAX, i32::MIN), |(min, max), (edge, ..)| (min.min(edge.weight), max.max(edge.weight)));
+        let mut entities: Vec<EdgeE> = graph.edge_nodes.keys().copied().collect();
+        entities.sort_by_key(|edge| edge.0);
+        let mut edges = Vec::new();
+        for edge_e in entities {
+            let Ok((edge, transform, sprite, children)) = q_edges.get(*edge_e) else {
+                continue;
+            };
+            let (label, weight_label) = label(children);
+            edges.push(DrawnEdge {
+                start: index[&edge.start],
+                end: index[&edge.end],
+                handle: transform.translation.truncate(),
+                weight: edge.weight,
+                color: sprite.color,
+                style: EdgeStyle {
+                    thickness: edge_thickness(graph, edge, weights),
+                    ..edge.style
+                },
+                path: edge.path.clone(),
+                label,
+                weight_label,
+            });
+        }
+
+        Drawing {
+            nodes,
+            edges,
+            annotations,
+            directed: graph.directed,
+            arrow_size: graph.arrow_size,
+            background: theme.background,
+        }
+    }
+
+    /// The curves making up an edge as `[start, control, end]`. Loops are two
+    /// curves from the node out to the handle, and edges drawn as straight
+    /// segments a flat curve for each segment.
+    pub fn curves(&self, edge: &DrawnEdge) -> Vec<[Vec2; 3]> {
+        let (start, end) = (self.nodes[edge.start].pos, self.nodes[edge.end].pos);
+        if let Some(path) = &edge.path {
+            path.windows(2).map(|pair| [pair[0], pair[0].lerp(pair[1], 0.5), pair[1]]).collect()
+        } else if edge.start == edge.end {
+            let [node, left, handle, right] =
+                loop_vertices(start.extend(0.0), edge.handle.extend(0.0)).map(|v| Vec2::new(v[0], v[1]));
+            vec![[node, left, handle], [node, right, handle]]
+        } else {
+            vec![[start, 2.0 * edge.handle - start.lerp(end, 0.5), end]]
+        }
+    }
+
+    /// The arrowhead at the end of a directed edge
+    pub fn arrow(&self, edge: &DrawnEdge) -> Option<[Vec2; 3]> {
+        if !self.directed {
+            return None;
+        }
+        let end_node = &self.nodes[edge.end];
+        let vertices = if edge.start == edge.end {
+            loop_arrow_vertices(
+                end_node.pos.extend(0.0),
+                edge.handle.extend(0.0),
+                end_node.style.size / 2.0,
+                self.arrow_size,
+            )
+        } else {
+            let [start, control, end] = *self.curves(edge).last()?;
+            arrow_vertices(
+                start.extend(0.0),
+                control.extend(0.0),
+                end.extend(0.0),
+                end_node.style.size / 2.0,
+                self.arrow_size,
+            )
+        };
+        Some(vertices.map(|v| Vec2::new(v[0], v[1])))
+    }
+
+    fn texts(&self) -> impl Iterator<Item = &DrawnText> {
+        self.nodes
+            .iter()
+            .filter_map(|node| node.label.as_ref())
+            .chain(self.edges.iter().flat_map(|edge| edge.label.iter().chain(edge.weight_label.iter())))
+            .chain(self.annotations.iter())
+    }
+
+    /// The corners of the area everything is drawn in, in world coordinates
+    fn bounds(&self) -> (Vec2, Vec2) {
+        let mut min = Vec2::splat(f32::INFINITY);
+        let mut max = Vec2::splat(f32::NEG_INFINITY);
+        let mut add = |p: Vec2, extent: Vec2| {
+            min = min.min(p - extent);
+            max = max.max(p + extent);
+        };
+        for node in self.nodes.iter() {
+            add(node.pos, Vec2::splat(node.style.size / 2.0));
+        }
+        for edge in self.edges.iter() {
+            // The control points bound the curve
+            for curve in self.curves(edge) {
+                for p in curve {
+                    add(p, Vec2::splat(edge.style.thickness));
+                }
+            }
+        }
+        for text in self.texts() {
+            let lines = text.text.lines().count().max(1) as f32;
+            let widest = text.text.lines().map(|l| l.chars().count()).max().unwrap_or(0) as f32;
+            add(text.pos, Vec2::new(widest * text.size * 0.3, lines * text.size * 0.6));
+        }
+
+        if min.x > max.x {
+            (Vec2::ZERO, Vec2::ZERO)
+        } else {
+            (min - MARGIN, max + MARGIN)
+        }
+    }
+
+    pub fn to_svg(&self) -> String {
+        let (min, max) = self.bounds();
+        let size = max - min;
+        // SVG's y axis points down
+        let p = |v: Vec2| format!("{:.2} {:.2}", v.x - min.x, max.y - v.y);
+
+        let mut out = String::new();
+        let _ = writeln!(
+            out,
+            r#"<svg xmlns="http://www.w3.org/2000/svg" width="{:.0}" height="{:.0}" viewBox="0 0 {:.2} {:.2}">"#,
+            size.x.ceil(),
+            size.y.ceil(),
+            size.x,
+            size.y
+        );
+        let _ = writeln!(out, r#"<rect width="100%" height="100%" fill="{}"/>"#, svg_color(self.background));
+
+        for edge in self.edges.iter() {
+            let style = &edge.style;
+            let dash = match style.pattern {
+                EdgePattern::Solid => String::new(),
+                EdgePattern::Dashed => r#" stroke-dasharray="10 6""#.into(),
+                EdgePattern::Dotted => format!(r#" stroke-dasharray="{0:.2} {0:.2}""#, style.thickness * 2.0),
+            };
+            // Curves carrying on from the last one's end continue the same
+            // path, so the corners of routed edges are joined
+            let mut last = None;
+            let d: Vec<String> = self
+                .curves(edge)
+                .iter()
+                .map(|[s, c, e]| {
+                    let part = match last {
+                        Some(end) if end == *s => format!("Q {} {}", p(*c), p(*e)),
+                        _ => format!("M {} Q {} {}", p(*s), p(*c), p(*e)),
+                    };
+                    last = Some(*e);
+                    part
+                })
+                .collect();
+            let _ = writeln!(
+                out,
+                r#"<path d="{}" fill="none" stroke="{}" stroke-opacity="{:.3}" stroke-width="{:.2}"{dash}/>"#,
+                d.join(" "),
+                svg_color(edge.color),
+                edge.color.a() * style.opacity,
+                style.thickness * 2.0,
+            );
+            if let Some([tip, left, right]) = self.arrow(edge) {
+                let _ = writeln!(
+                    out,
+                    r#"<polygon points="{} {} {}" fill="{}" fill-opacity="{:.3}"/>"#,
+                    p(tip),
+                    p(left),
+                    p(right),
+                    svg_color(edge.color),
+                    edge.color.a() * style.opacity,
+                );
+            }
+        }
+
+        for node in self.nodes.iter() {
+            let r = node.style.size / 2.0;
+            let fill = format!(r#"fill="{}" fill-opacity="{:.3}""#, svg_color(node.color), node.color.a());
+            let _ = match &node.style.shape {
+                NodeShape::Circle => writeln!(
+                    out,
+                    r#"<circle cx="{:.2}" cy="{:.2}" r="{r:.2}" {fill}/>"#,
+                    node.pos.x - min.x,
+                    max.y - node.pos.y
+                ),
+                NodeShape::Image(path) => writeln!(
+                    out,
+                    r#"<image href="{}" x="{:.2}" y="{:.2}" width="{:.2}" height="{:.2}" preserveAspectRatio="none"/>"#,
+                    escape(path),
+                    node.pos.x - r - min.x,
+                    max.y - node.pos.y - r,
+                    node.style.size,
+                    node.style.size
+                ),
+                shape => {
+                    let points: Vec<String> = polygon(shape).iter().map(|v| p(node.pos + *v * r)).collect();
+                    writeln!(out, r#"<polygon points="{}" {fill}/>"#, points.join(" "))
+                }
+            };
+        }
+
+        for text in self.texts() {
+            let lines: Vec<&str> = text.text.lines().collect();
+            for (i, line) in lines.iter().enumerate() {
+                let offset = (i as f32 - (lines.len() - 1) as f32 / 2.0) * text.size * 1.2;
+                let _ = writeln!(
+                    out,
+                    r#"<text x="{:.2}" y="{:.2}" font-family="Fira Sans, sans-serif" font-size="{:.2}" fill="{}" text-anchor="middle" dominant-baseline="central">{}</text>"#,
+                    text.pos.x - min.x,
+                    max.y - text.pos.y + offset,
+                    text.size,
+                    svg_color(text.color),
+                    escape(line)
+                );
+            }
+        }
+
+        out += "</svg>\n";
+        out
+    }
+
+    pub fn to_png(&self) -> Result<Vec<u8>, String> {
+        let (min, max) = self.bounds();
+        let size = ((max - min) * PNG_SCALE).ceil();
+        let mut pixmap = Pixmap::new(size.x.max(1.0) as u32, size.y.max(1.0) as u32)
+            .ok_or_else(|| "The image is too large".to_string())?;
+        pixmap.fill(skia_color(self.background, 1.0));
+
+        // Image y runs down
+        let transform = tiny_skia::Transform::from_row(PNG_SCALE, 0.0, 0.0, -PNG_SCALE, -min.x * PNG_SCALE, max.y * PNG_SCALE);
+        let paint = |color: Color, opacity: f32| {
+            let mut paint = Paint::default();
+            paint.set_color(skia_color(color, opacity));
+            paint.anti_alias = true;
+            paint
+        };
+
+        for edge in self.edges.iter() {
+            let style = &edge.style;
+            let mut builder = PathBuilder::new();
+            let mut last = None;
+            for [s, c, e] in self.curves(edge) {
+                if last != Some(s) {
+                    builder.move_to(s.x, s.y);
+                }
+                builder.quad_to(c.x, c.y, e.x, e.y);
+                last = Some(e);
+            }
+            let dash = match style.pattern {
+                EdgePattern::Solid => None,
+                EdgePattern::Dashed => StrokeDash::new(vec![10.0, 6.0], 0.0),
+                EdgePattern::Dotted => StrokeDash::new(vec![style.thickness * 2.0; 2], 0.0),
+            };
+            let stroke = Stroke {
+                width: style.thickness * 2.0,
+                line_cap: LineCap::Butt,
+                dash,
+                ..Default::default()
+            };
+            if let Some(path) = builder.finish() {
+                pixmap.stroke_path(&path, &paint(edge.color, style.opacity), &stroke, transform, None);
+            }
+            if let Some(path) = self.arrow(edge).and_then(|points| polygon_path(&points)) {
+                pixmap.fill_path(&path, &paint(edge.color, style.opacity), FillRule::Winding, transform, None);
+            }
+        }
+
+        for node in self.nodes.iter() {
+            let r = node.style.size / 2.0;
+            let path = match &node.style.shape {
+                NodeShape::Image(file) => {
+                    match std::fs::read(file).ok().and_then(|data| Pixmap::decode_png(&data).ok()) {
+                        Some(image) => {
+                            let (w, h) = (image.width() as f32, image.height() as f32);
+                            let placed = tiny_skia::Transform::from_row(
+                                node.style.size / w,
+                                0.0,
+                                0.0,
+                                node.style.size / h,
+                                node.pos.x - r,
+                                node.pos.y - r,
+                            );
+                            // Flip the image back upright inside the world's
+                            // y-up transform
+                            let upright = tiny_skia::Transform::from_row(1.0, 0.0, 0.0, -1.0, 0.0, h);
+                            pixmap.draw_pixmap(
+                                0,
+                                0,
+                                image.as_ref(),
+                                &PixmapPaint {
+                                    quality: FilterQuality::Bilinear,
+                                    ..Default::default()
+                                },
+                                transform.pre_concat(placed).pre_concat(upright),
+                                None,
+                            );
+                            continue;
+                        }
+                        // Images that can't be read are drawn as circles
+                        None => PathBuilder::from_circle(node.pos.x, node.pos.y, r),
+                    }
+                }
+                NodeShape::Circle => PathBuilder::from_circle(node.pos.x, node.pos.y, r),
+                shape => polygon_path(&polygon(shape).iter().map(|v| node.pos + *v * r).collect::<Vec<_>>()),
+            };
+            if let Some(path) = path {
+                pixmap.fill_path(&path, &paint(node.color, 1.0), FillRule::Winding, transform, None);
+            }
+        }
+
+        let font = FontRef::try_from_slice(FONT).map_err(|e| e.to_string())?;
+        for text in self.texts() {
+            let to_image = |v: Vec2| ((v.x - min.x) * PNG_SCALE, (max.y - v.y) * PNG_SCALE);
+            let (x, y) = to_image(text.pos);
+            if let Some(path) = text_path(&font, &text.text, text.size * PNG_SCALE) {
+                let placed = tiny_skia::Transform::from_translate(x, y);
+                pixmap.fill_path(&path, &paint(text.color, 1.0), FillRule::Winding, placed, None);
+            }
+        }
+
+        pixmap.encode_png().map_err(|e| e.to_string())
+    }
+
+    /// A `tikzpicture` in the same coordinates as the app, at 0.02cm per
+    /// unit. Shapes other than circles need the `shapes.geometric` library and
+    /// image nodes need `graphicx`.
+    pub fn to_tikz(&self) -> String {
+        let mut colors: Vec<[u8; 3]> = Vec::new();
+        let mut color = |c: Color| {
+            let [r, g, b, _] = c.as_rgba_u8();
+            let i = colors.iter().position(|x| *x == [r, g, b]).unwrap_or_else(|| {
+                colors.push([r, g, b]);
+                colors.len() - 1
+            });
+            format!("c{i}")
+        };
+        let p = |v: Vec2| format!("({:.2}, {:.2})", v.x, v.y);
+        let cm = |units: f32| format!("{:.3}cm", units * TIKZ_UNIT);
+
+        let mut body = String::new();
+        let (min, max) = self.bounds();
+        let _ = writeln!(body, "\\fill[{}] {} rectangle {};", color(self.background), p(min), p(max));
+
+        for (i, node) in self.nodes.iter().enumerate() {
+            let size = node.style.size;
+            let mut options = match &node.style.shape {
+                NodeShape::Circle => format!("circle, minimum size={}", cm(size)),
+                NodeShape::Square => format!("rectangle, minimum size={}", cm(size * 0.9)),
+                NodeShape::Diamond => format!("diamond, minimum size={}", cm(size)),
+                NodeShape::Triangle => format!("regular polygon, regular polygon sides=3, minimum size={}", cm(size)),
+                NodeShape::Image(_) => String::new(),
+            };
+            if !matches!(node.style.shape, NodeShape::Image(_)) {
+                options += &format!(", fill={}", color(node.color));
+            }
+            options += ", inner sep=0pt";
+            if let Some(label) = &node.label {
+                let _ = write!(options, ", label={{[text={}, align=center]above:{{{}}}}}", color(label.color), tex_escape(&label.text));
+            }
+            let content = match &node.style.shape {
+                NodeShape::Image(path) => format!("\\includegraphics[width={0}, height={0}]{{{path}}}", cm(size)),
+                _ => String::new(),
+            };
+            let _ = writeln!(body, "\\node[{options}] (v{i}) at {} {{{content}}};", p(node.pos));
+        }
+
+        // Weights are only worth showing if some edge has one, or they're
+        // shown on screen
+        let weighted = self.edges.iter().any(|edge| edge.weight != 1);
+        for edge in self.edges.iter() {
+            let style = &edge.style;
+            let mut options = vec![color(edge.color), format!("line width={}", cm(style.thickness * 2.0))];
+            if self.arrow(edge).is_some() {
+                options.insert(0, "->".into());
+            }
+            match style.pattern {
+                EdgePattern::Solid => (),
+                EdgePattern::Dashed => options.push("dashed".into()),
+                EdgePattern::Dotted => options.push("dotted".into()),
+            }
+            if edge.color.a() * style.opacity < 1.0 {
+                options.push(format!("opacity={:.3}", edge.color.a() * style.opacity));
+            }
+            let weight = if weighted || edge.weight_label.is_some() {
+                format!("node[auto, font=\\footnotesize] {{{}}} ", edge.weight)
+            } else {
+                String::new()
+            };
+
+            // Quadratic curves raised to the cubic ones TikZ draws
+            let controls = |[s, c, e]: [Vec2; 3]| {
+                format!("controls {} and {}", p(s + (c - s) * 2.0 / 3.0), p(e + (c - e) * 2.0 / 3.0))
+            };
+            let (start, end) = (format!("(v{})", edge.start), format!("(v{})", edge.end));
+            let path = match (&edge.path, self.curves(edge).as_slice()) {
+                (Some(path), _) => {
+                    let mut stops: Vec<String> = path.iter().map(|v| p(*v)).collect();
+                    stops[0] = start;
+                    *stops.last_mut().unwrap() = end;
+                    // The weight goes by the middle segment
+                    let middle = (stops.len() - 2) / 2;
+                    let mut out = stops[0].clone();
+                    for (i, stop) in stops.iter().enumerate().skip(1) {
+                        let weight = if i - 1 == middle { weight.as_str() } else { "" };
+                        let _ = write!(out, " -- {weight}{stop}");
+                    }
+                    out
+                }
+                (None, [[s, c, e], [_, back, _]]) => format!(
+                    "{start} .. {} .. {} {weight}.. {} .. {end}",
+                    controls([*s, *c, *e]),
+                    p(*e),
+                    controls([*e, *back, *s])
+                ),
+                (None, [curve]) => format!("{start} .. {} .. {weight}{end}", controls(*curve)),
+                _ => continue,
+            };
+            let _ = writeln!(body, "\\draw[{}] {path};", options.join(", "));
+        }
+
+        let mut out = format!("\\begin{{tikzpicture}}[x={0}cm, y={0}cm]\n", TIKZ_UNIT);
+        for (i, [r, g, b]) in colors.iter().enumerate() {
+            let _ = writeln!(out, "\\definecolor{{c{i}}}{{RGB}}{{{r},{g},{b}}}");
+        }
+        out += &body;
+        out += "\\end{tikzpicture}\n";
+        out
+    }
+
+    /// Write the drawing to an `.svg`, `.png` or `.tex` file, going by the
+    /// extension
+    pub fn save(&self, path: &Path) -> Result<(), String> {
+        let data = match path.extension().and_then(|ext| ext.to_str()) {
+            Some("svg") => self.to_svg().into_bytes(),
+            Some("png") => self.to_png()?,
+            Some("tex") => self.to_tikz().into_bytes(),
+            _ => return Err(format!("Can't export {}, use a .svg, .png or .tex file", path.display())),
+        };
+        std::fs::write(path, data).map_err(|e| format!("Failed to export {}: {e}", path.display()))
+    }
+}
+
+/// Draw a saved graph file straight to an image
+pub fn convert(input: &Path, output: &Path) -> Result<(), String> {
+    let (t, directed) = io::load(input)?;
+    Drawing::from_template(&t, directed, &Theme::default()).save(output)
+}
+
+/// The corners of a built in shape, scaled to fit in a unit circle with y up,
+/// matching the generated textures
+fn polygon(shape: &NodeShape) -> Vec<Vec2> {
+    match shape {
+        NodeShape::Square => vec![
+            Vec2::new(-0.9, -0.9),
+            Vec2::new(0.9, -0.9),
+            Vec2::new(0.9, 0.9),
+            Vec2::new(-0.9, 0.9),
+        ],
+        NodeShape::Diamond => vec![Vec2::new(0.0, -1.0), Vec2::X, Vec2::Y, Vec2::NEG_X],
+        NodeShape::Triangle => vec![
+            Vec2::new(-1.7 / 1.9, -0.75),
+            Vec2::new(1.7 / 1.9, -0.75),
+            Vec2::new(0.0, 0.95),
+        ],
+        NodeShape::Circle | NodeShape::Image(_) => Vec::new(),
+    }
+}
+
+fn polygon_path(points: &[Vec2]) -> Option<tiny_skia::Path> {
+    let (first, rest) = points.split_first()?;
+    let mut builder = PathBuilder::new();
+    builder.move_to(first.x, first.y);
+    for p in rest {
+        builder.line_to(p.x, p.y);
+    }
+    builder.close();
+    builder.finish()
+}
+
+/// The outlines of some text centred on the origin, in image coordinates
+fn text_path(font: &FontRef, text: &str, size: f32) -> Option<tiny_skia::Path> {
+    let scaled = font.as_scaled(size);
+    let (sx, sy) = (scaled.h_scale_factor(), scaled.v_scale_factor());
+    let lines: Vec<&str> = text.lines().collect();
+    let line_height = scaled.height() + scaled.line_gap();
+    let top = -line_height * lines.len() as f32 / 2.0;
+
+    let mut builder = PathBuilder::new();
+    for (row, line) in lines.iter().enumerate() {
+        let glyphs: Vec<_> = line.chars().map(|c| scaled.glyph_id(c)).collect();
+        let width: f32 = glyphs.iter().map(|id| scaled.h_advance(*id)).sum::<f32>()
+            + glyphs.windows(2).map(|w| scaled.kern(w[0], w[1])).sum::<f32>();
+        let baseline = top + row as f32 * line_height + scaled.ascent();
+
+        let mut x = -width / 2.0;
+        for (i, id) in glyphs.iter().enumerate() {
+            if i > 0 {
+                x += scaled.kern(glyphs[i - 1], *id);
+            }
+            if let Some(outline) = font.outline(*id) {
+                // Outlines are in unscaled font units with y up
+                let point = |p: ab_glyph::Point| (x + p.x * sx, baseline - p.y * sy);
+                let mut last = None;
+                for curve in outline.curves.iter() {
+                    let (from, to) = match curve {
+                        OutlineCurve::Line(a, b) | OutlineCurve::Quad(a, _, b) | OutlineCurve::Cubic(a, _, _, b) => (*a, *b),
+                    };
+                    if last != Some(from) {
+                        if last.is_some() {
+                            builder.close();
+                        }
+                        let (px, py) = point(from);
+                        builder.move_to(px, py);
+                    }
+                    match curve {
+                        OutlineCurve::Line(_, b) => {
+                            let (bx, by) = point(*b);
+                            builder.line_to(bx, by);
+                        }
+                        OutlineCurve::Quad(_, c, b) => {
+                            let ((cx, cy), (bx, by)) = (point(*c), point(*b));
+                            builder.quad_to(cx, cy, bx, by);
+                        }
+                        OutlineCurve::Cubic(_, c1, c2, b) => {
+                            let ((c1x, c1y), (c2x, c2y), (bx, by)) = (point(*c1), point(*c2), point(*b));
+                            builder.cubic_to(c1x, c1y, c2x, c2y, bx, by);
+                        }
+                    }
+                    last = Some(to);
+                }
+                if last.is_some() {
+                    builder.close();
+                }
+            }
+            x += scaled.h_advance(*id);
+        }
+    }
+    builder.finish()
+}
+
+fn svg_color(color: Color) -> String {
+    let [r, g, b, _] = color.as_rgba_u8();
+    format!("#{r:02x}{g:02x}{b:02x}")
+}
+
+fn skia_color(color: Color, opacity: f32) -> tiny_skia::Color {
+    let [r, g, b, a] = color.as_rgba_u8();
+    tiny_skia::Color::from_rgba8(r, g, b, (a as f32 * opacity).round() as u8)
+}
+
+fn tex_escape(text: &str) -> String {
+    let mut out = String::new();
+    for c in text.chars() {
+        match c {
+            '\\' => out += "\\textbackslash{}",
+            '~' => out += "\\textasciitilde{}",
+            '^' => out += "\\textasciicircum{}",
+            '<' => out += "\\textless{}",
+            '>' => out += "\\textgreater{}",
+            '&' | '%' | '$' | '#' | '_' | '{' | '}' => {
+                out.push('\\');
+                out.push(c);
+            }
+            '\n' => out += "\\\\",
+            c => out.push(c),
+        }
+    }
+    out
+}
+
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
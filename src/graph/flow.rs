@@ -0,0 +1,251 @@
+use std::collections::{HashMap, HashSet, VecDeque};
+
+use bevy::prelude::*;
+
+use super::{EdgeE, GEdge, Graph, NodeE, PathPart};
+
+#[derive(Copy, Clone, Default, PartialEq, Eq)]
+pub enum FlowMethod {
+    /// Augment along shortest paths found with a breadth first search
+    #[default]
+    EdmondsKarp,
+    /// Augment along every shortest path at once using a level graph
+    Dinic,
+}
+
+impl core::fmt::Display for FlowMethod {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            FlowMethod::EdmondsKarp => write!(f, "Edmonds–Karp"),
+            FlowMethod::Dinic => write!(f, "Dinic"),
+        }
+    }
+}
+
+/// Options for the maximum flow cursor mode, set from the UI
+#[derive(Resource, Default)]
+pub struct FlowSettings {
+    pub method: FlowMethod,
+    /// Step through the augmenting paths instead of only showing the result
+    pub animate: bool,
+}
+
+pub struct MaxFlow {
+    pub value: i64,
+    /// The flow along each edge in its direction, undirected edges carrying
+    /// flow from their end to their start have a negative flow
+    pub flow: HashMap<EdgeE, i64>,
+    pub capacity: HashMap<EdgeE, i64>,
+    /// The nodes still reachable from the source in the residual network,
+    /// the source side of a minimum cut
+    pub source_side: HashSet<NodeE>,
+    /// The edges crossing the minimum cut
+    pub cut: Vec<EdgeE>,
+    /// Each augmenting path in the order it was used, with the flow it added
+    pub augmenting: Vec<(Vec<PathPart>, i64)>,
+}
+
+/// Every edge becomes a pair of arcs in the residual network, arc `2i` runs
+/// along edge `i` and arc `2i + 1` against it
+struct Residual {
+    nodes: Vec<NodeE>,
+    edges: Vec<EdgeE>,
+    /// The arcs leaving each node
+    out: Vec<Vec<usize>>,
+    head: Vec<usize>,
+    residual: Vec<i64>,
+}
+
+impl Residual {
+    fn tail(&self, arc: usize) -> usize {
+        self.head[arc ^ 1]
+    }
+
+    fn augment(&mut self, arcs: &[usize], amount: i64) -> (Vec<PathPart>, i64) {
+        let mut path = vec![PathPart::without_edge(self.nodes[self.tail(arcs[0])])];
+        for &arc in arcs {
+            self.residual[arc] -= amount;
+            self.residual[arc ^ 1] += amount;
+            path.push(PathPart::with_edge(self.nodes[self.head[arc]], self.edges[arc / 2]));
+        }
+        (path, amount)
+    }
+
+    /// Distance from the source to every node along arcs with capacity left
+    fn levels(&self, source: usize) -> Vec<Option<usize>> {
+        let mut level = vec![None; self.nodes.len()];
+        level[source] = Some(0);
+        let mut queue = VecDeque::from([source]);
+        while let Some(node) = queue.pop_front() {
+            for &arc in self.out[node].iter() {
+                let next = self.head[arc];
+                if self.residual[arc] > 0 && level[next].is_none() {
+                    level[next] = Some(level[node].unwrap() + 1);
+                    queue.push_back(next);
+                }
+            }
+        }
+        level
+    }
+
+    /// The arcs of a shortest augmenting path, for Edmonds–Karp
+    fn shortest_path(&self, source: usize, sink: usize) -> Option<Vec<usize>> {
+        let mut parent: Vec<Option<usize>> = vec![None; self.nodes.len()];
+        let mut queue = VecDeque::from([source]);
+        while let Some(node) = queue.pop_front() {
+            for &arc in self.out[node].iter() {
+                let next = self.head[arc];
+                if self.residual[arc] > 0 && next != source && parent[next].is_none() {
+                    parent[next] = Some(arc);
+                    if next == sink {
+                        let mut arcs = vec![arc];
+                        while let Some(arc) = parent[self.tail(*arcs.last().unwrap())] {
+                            arcs.push(arc);
+                        }
+                        arcs.reverse();
+                        return Some(arcs);
+                    }
+                    queue.push_back(next);
+                }
+            }
+        }
+        None
+    }
+
+    /// Find a path along the level graph for Dinic's algorithm, skipping arcs
+    /// that already led to dead ends
+    fn level_path(&self, source: usize, sink: usize, level: &[Option<usize>], next_arc: &mut [usize]) -> Option<Vec<usize>> {
+        let mut arcs = Vec::new();
+        let mut node = source;
+        while node != sink {
+            let advance = loop {
+                let Some(&arc) = self.out[node].get(next_arc[node]) else {
+                    break None;
+                };
+                let next = self.head[arc];
+                if self.residual[arc] > 0 && level[next].is_some() && level[next] == level[node].map(|l| l + 1) {
+                    break Some(arc);
+                }
+                next_arc[node] += 1;
+            };
+
+            match advance {
+                Some(arc) => {
+                    arcs.push(arc);
+                    node = self.head[arc];
+                }
+                None => {
+                    // Retreat, the arc that led here is no use any more
+                    let arc = arcs.pop()?;
+                    node = self.tail(arc);
+                    next_arc[node] += 1;
+                }
+            }
+        }
+        Some(arcs)
+    }
+}
+
+impl Graph {
+    /// Find a maximum flow from `source` to `sink`, using each edge's weight as
+    /// its capacity. Undirected edges can carry flow either way.
+    pub fn max_flow(&self, source: &NodeE, sink: &NodeE, method: FlowMethod, q_edge: &Query<&GEdge>) -> Result<MaxFlow, String> {
+        if source == sink {
+            return Err("The source and sink must be different nodes".into());
+        }
+
+        let mut nodes: Vec<NodeE> = self.node_edges.keys().copied().collect();
+        nodes.sort_by_key(|node| node.0);
+        let index: HashMap<NodeE, usize> = nodes.iter().enumerate().map(|(i, node)| (*node, i)).collect();
+
+        // Loops can't carry flow anywhere, so they're left out
+        let mut edges: Vec<EdgeE> = self
+            .edge_nodes
+            .iter()
+            .filter(|(_, (start, end))| start != end)
+            .map(|(edge, _)| *edge)
+            .collect();
+        edges.sort_by_key(|edge| edge.0);
+
+        let capacity: HashMap<EdgeE, i64> = edges
+            .iter()
+            .map(|edge| (*edge, q_edge.get(**edge).map(|e| e.weight).unwrap_or(1).max(0) as i64))
+            .collect();
+
+        let mut net = Residual {
+            out: vec![Vec::new(); nodes.len()],
+            head: Vec::with_capacity(edges.len() * 2),
+            residual: Vec::with_capacity(edges.len() * 2),
+            nodes,
+            edges,
+        };
+        for (i, edge) in net.edges.iter().enumerate() {
+            let (start, end) = self.edge_nodes[edge];
+            let (s, e) = (index[&start], index[&end]);
+            let c = capacity[edge];
+            net.head.extend([e, s]);
+            net.residual.extend([c, if self.directed { 0 } else { c }]);
+            net.out[s].push(2 * i);
+            net.out[e].push(2 * i + 1);
+        }
+
+        let (s, t) = (index[source], index[sink]);
+        let bottleneck = |net: &Residual, arcs: &[usize]| arcs.iter().map(|&arc| net.residual[arc]).min().unwrap();
+
+        let mut augmenting = Vec::new();
+        match method {
+            FlowMethod::EdmondsKarp => {
+                while let Some(arcs) = net.shortest_path(s, t) {
+                    let amount = bottleneck(&net, &arcs);
+                    augmenting.push(net.augment(&arcs, amount));
+                }
+            }
+            FlowMethod::Dinic => loop {
+                let level = net.levels(s);
+                if level[t].is_none() {
+                    break;
+                }
+                let mut next_arc = vec![0; net.nodes.len()];
+                while let Some(arcs) = net.level_path(s, t, &level, &mut next_arc) {
+                    let amount = bottleneck(&net, &arcs);
+                    augmenting.push(net.augment(&arcs, amount));
+                }
+            },
+        }
+
+        let flow: HashMap<EdgeE, i64> = net
+            .edges
+            .iter()
+            .enumerate()
+            .map(|(i, edge)| (*edge, capacity[edge] - net.residual[2 * i]))
+            .collect();
+
+        let level = net.levels(s);
+        let source_side: HashSet<NodeE> = net
+            .nodes
+            .iter()
+            .enumerate()
+            .filter(|(i, _)| level[*i].is_some())
+            .map(|(_, node)| *node)
+            .collect();
+        let cut = net
+            .edges
+            .iter()
+            .filter(|edge| {
+                let (start, end) = self.edge_nodes[edge];
+                let (a, b) = (source_side.contains(&start), source_side.contains(&end));
+                (a && !b) || (!self.directed && b && !a)
+            })
+            .copied()
+            .collect();
+
+        Ok(MaxFlow {
+            value: augmenting.iter().map(|(_, amount)| amount).sum(),
+            flow,
+            capacity,
+            source_side,
+            cut,
+            augmenting,
+        })
+    }
+}
@@ -0,0 +1,389 @@
+use std::f32::consts::{PI, TAU};
+
+use bevy::prelude::*;
+
+use super::template::GraphTemplate;
+
+static NODE_SPACING: f32 = 100.0;
+
+#[derive(Copy, Clone, Default, PartialEq, Eq)]
+pub enum GraphFamily {
+    #[default]
+    Complete,
+    CompleteBipartite,
+    Cycle,
+    Path,
+    Star,
+    Wheel,
+    Grid,
+    Torus,
+    Hypercube,
+    Petersen,
+    BinaryTree,
+    ErdosRenyi,
+    BarabasiAlbert,
+    WattsStrogatz,
+}
+
+impl GraphFamily {
+    pub const ALL: [GraphFamily; 14] = [
+        GraphFamily::Complete,
+        GraphFamily::CompleteBipartite,
+        GraphFamily::Cycle,
+        GraphFamily::Path,
+        GraphFamily::Star,
+        GraphFamily::Wheel,
+        GraphFamily::Grid,
+        GraphFamily::Torus,
+        GraphFamily::Hypercube,
+        GraphFamily::Petersen,
+        GraphFamily::BinaryTree,
+        GraphFamily::ErdosRenyi,
+        GraphFamily::BarabasiAlbert,
+        GraphFamily::WattsStrogatz,
+    ];
+
+    pub fn is_random(&self) -> bool {
+        matches!(
+            self,
+            GraphFamily::ErdosRenyi | GraphFamily::BarabasiAlbert | GraphFamily::WattsStrogatz
+        )
+    }
+}
+
+impl core::fmt::Display for GraphFamily {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            GraphFamily::Complete => write!(f, "Complete Kₙ"),
+            GraphFamily::CompleteBipartite => write!(f, "Complete Bipartite Kₘ,ₙ"),
+            GraphFamily::Cycle => write!(f, "Cycle Cₙ"),
+            GraphFamily::Path => write!(f, "Path Pₙ"),
+            GraphFamily::Star => write!(f, "Star Sₙ"),
+            GraphFamily::Wheel => write!(f, "Wheel Wₙ"),
+            GraphFamily::Grid => write!(f, "Grid"),
+            GraphFamily::Torus => write!(f, "Torus"),
+            GraphFamily::Hypercube => write!(f, "Hypercube Qₙ"),
+            GraphFamily::Petersen => write!(f, "Petersen"),
+            GraphFamily::BinaryTree => write!(f, "Binary Tree"),
+            GraphFamily::ErdosRenyi => write!(f, "Erdős–Rényi G(n, p)"),
+            GraphFamily::BarabasiAlbert => write!(f, "Barabási–Albert"),
+            GraphFamily::WattsStrogatz => write!(f, "Watts–Strogatz"),
+        }
+    }
+}
+
+/// Parameters shared by all of the graph families, each family only reads the
+/// ones that are relevant to it
+#[derive(Clone)]
+pub struct GeneratorParams {
+    pub n: usize,
+    pub m: usize,
+    pub k: usize,
+    pub p: f32,
+    pub seed: u64,
+}
+
+impl Default for GeneratorParams {
+    fn default() -> Self {
+        GeneratorParams {
+            n: 5,
+            m: 3,
+            k: 2,
+            p: 0.3,
+            seed: 1,
+        }
+    }
+}
+
+/// Small deterministic PRNG (SplitMix64) so random graphs can be reproduced
+/// from their seed
+pub struct Rng(u64);
+
+impl Rng {
+    pub fn new(seed: u64) -> Self {
+        Rng(seed)
+    }
+
+    pub fn next_u64(&mut self) -> u64 {
+        self.0 = self.0.wrapping_add(0x9E3779B97F4A7C15);
+        let mut z = self.0;
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58476D1CE4E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D049BB133111EB);
+        z ^ (z >> 31)
+    }
+
+    /// A uniformly distributed float in [0, 1)
+    pub fn next_f32(&mut self) -> f32 {
+        (self.next_u64() >> 40) as f32 / (1u64 << 24) as f32
+    }
+
+    /// A uniformly distributed integer in [0, bound)
+    pub fn below(&mut self, bound: usize) -> usize {
+        (self.next_u64() % bound as u64) as usize
+    }
+}
+
+fn circle_radius(n: usize) -> f32 {
+    (n as f32 * NODE_SPACING / TAU).max(NODE_SPACING)
+}
+
+/// Add `n` nodes evenly spaced around a circle, starting from the top
+fn add_circle(template: &mut GraphTemplate, n: usize, radius: f32) -> Vec<usize> {
+    (0..n)
+        .map(|i| {
+            let angle = PI / 2.0 - TAU * i as f32 / n as f32;
+            template.add_node(Vec2::new(angle.cos(), angle.sin()) * radius)
+        })
+        .collect()
+}
+
+pub fn generate(family: GraphFamily, params: &GeneratorParams) -> GraphTemplate {
+    let mut t = GraphTemplate::default();
+    let n = params.n;
+
+    match family {
+        GraphFamily::Complete => {
+            let nodes = add_circle(&mut t, n, circle_radius(n));
+            for i in 0..n {
+                for j in (i + 1)..n {
+                    t.add_edge(nodes[i], nodes[j]);
+                }
+            }
+        }
+        GraphFamily::CompleteBipartite => {
+            let m = params.m;
+            let height = |count: usize, i: usize| {
+                (i as f32 - (count as f32 - 1.0) / 2.0) * -NODE_SPACING
+            };
+            let left: Vec<usize> = (0..m)
+                .map(|i| t.add_node(Vec2::new(-1.5 * NODE_SPACING, height(m, i))))
+                .collect();
+            let right: Vec<usize> = (0..n)
+                .map(|i| t.add_node(Vec2::new(1.5 * NODE_SPACING, height(n, i))))
+                .collect();
+            for &a in left.iter() {
+                for &b in right.iter() {
+                    t.add_edge(a, b);
+                }
+            }
+        }
+        GraphFamily::Cycle => {
+            let nodes = add_circle(&mut t, n, circle_radius(n));
+            for i in 0..n {
+                t.add_edge(nodes[i], nodes[(i + 1) % n]);
+            }
+        }
+        GraphFamily::Path => {
+            let nodes: Vec<usize> = (0..n)
+                .map(|i| t.add_node(Vec2::new(i as f32 * NODE_SPACING, 0.0)))
+                .collect();
+            for pair in nodes.windows(2) {
+                t.add_edge(pair[0], pair[1]);
+            }
+        }
+        GraphFamily::Star | GraphFamily::Wheel => {
+            let center = t.add_node(Vec2::ZERO);
+            let rim = add_circle(&mut t, n, circle_radius(n).max(1.5 * NODE_SPACING));
+            for i in 0..n {
+                t.add_edge(center, rim[i]);
+                if family == GraphFamily::Wheel {
+                    t.add_edge(rim[i], rim[(i + 1) % n]);
+                }
+            }
+        }
+        GraphFamily::Grid | GraphFamily::Torus => {
+            let (w, h) = (n, params.m);
+            let nodes: Vec<usize> = (0..h)
+                .flat_map(|y| (0..w).map(move |x| (x, y)))
+                .map(|(x, y)| {
+                    t.add_node(Vec2::new(x as f32, -(y as f32)) * NODE_SPACING)
+                })
+                .collect();
+            let torus = family == GraphFamily::Torus;
+            for y in 0..h {
+                for x in 0..w {
+                    let i = y * w + x;
+                    if x + 1 < w {
+                        t.add_edge(nodes[i], nodes[i + 1]);
+                    } else if torus && w > 2 {
+                        t.add_edge(nodes[i], nodes[y * w]);
+                    }
+                    if y + 1 < h {
+                        t.add_edge(nodes[i], nodes[i + w]);
+                    } else if torus && h > 2 {
+                        t.add_edge(nodes[i], nodes[x]);
+                    }
+                }
+            }
+        }
+        GraphFamily::Hypercube => {
+            // Project each dimension onto its own direction in the plane, the
+            // standard way of drawing Qₙ
+            let dims: Vec<Vec2> = (0..n)
+                .map(|d| {
+                    let angle = PI * d as f32 / n as f32;
+                    Vec2::new(angle.cos(), angle.sin()) * NODE_SPACING * (1.0 + d as f32 * 0.25)
+                })
+                .collect();
+            let count = 1usize << n;
+            let nodes: Vec<usize> = (0..count)
+                .map(|v| {
+                    let pos = (0..n)
+                        .filter(|d| v & (1 << d) != 0)
+                        .map(|d| dims[d])
+                        .sum();
+                    t.add_node(pos)
+                })
+                .collect();
+            for v in 0..count {
+                for d in 0..n {
+                    let u = v ^ (1 << d);
+                    if v < u {
+                        t.add_edge(nodes[v], nodes[u]);
+                    }
+                }
+            }
+        }
+        GraphFamily::Petersen => {
+            let outer = add_circle(&mut t, 5, 2.0 * NODE_SPACING);
+            let inner = add_circle(&mut t, 5, NODE_SPACING);
+            for i in 0..5 {
+                t.add_edge(outer[i], outer[(i + 1) % 5]);
+                t.add_edge(outer[i], inner[i]);
+                t.add_edge(inner[i], inner[(i + 2) % 5]);
+            }
+        }
+        GraphFamily::BinaryTree => {
+            // n is the depth of the tree, levels are spread out so the
+            // bottom level is evenly spaced
+            let width = (1usize << n.saturating_sub(1)) as f32 * NODE_SPACING;
+            let mut nodes = Vec::new();
+            for level in 0..n {
+                let count = 1usize << level;
+                for i in 0..count {
+                    let x = (i as f32 + 0.5) / count as f32 * width - width / 2.0;
+                    nodes.push(t.add_node(Vec2::new(x, -(level as f32) * NODE_SPACING)));
+                }
+            }
+            for i in 1..nodes.len() {
+                t.add_edge(nodes[(i - 1) / 2], nodes[i]);
+            }
+        }
+        GraphFamily::ErdosRenyi => {
+            let mut rng = Rng::new(params.seed);
+            let nodes = add_circle(&mut t, n, circle_radius(n));
+            for i in 0..n {
+                for j in (i + 1)..n {
+                    if rng.next_f32() < params.p {
+                        t.add_edge(nodes[i], nodes[j]);
+                    }
+                }
+            }
+        }
+        GraphFamily::BarabasiAlbert => {
+            // Start from a complete graph on m + 1 nodes, then attach every
+            // new node to m distinct existing nodes chosen proportionally to
+            // their degree
+            let mut rng = Rng::new(params.seed);
+            let m = params.m.max(1);
+            let nodes = add_circle(&mut t, n.max(m + 1), circle_radius(n));
+            let mut endpoints = Vec::new();
+            for i in 0..=m {
+                for j in (i + 1)..=m {
+                    t.add_edge(nodes[i], nodes[j]);
+                    endpoints.extend_from_slice(&[i, j]);
+                }
+            }
+            for v in (m + 1)..nodes.len() {
+                let mut targets = Vec::new();
+                while targets.len() < m {
+                    let target = endpoints[rng.below(endpoints.len())];
+                    if !targets.contains(&target) {
+                        targets.push(target);
+                    }
+                }
+                for target in targets {
+                    t.add_edge(nodes[v], nodes[target]);
+                    endpoints.extend_from_slice(&[v, target]);
+                }
+            }
+        }
+        GraphFamily::WattsStrogatz => {
+            // Ring lattice where each node is joined to its k nearest
+            // neighbours on each side, then every edge is rewired with
+            // probability p
+            let mut rng = Rng::new(params.seed);
+            let k = params.k.min(n.saturating_sub(1) / 2);
+            let nodes = add_circle(&mut t, n, circle_radius(n));
+            for i in 0..n {
+                for j in 1..=k {
+                    let mut target = (i + j) % n;
+                    if rng.next_f32() < params.p {
+                        // Give up on rewiring if the node is already joined
+                        // to every other node
+                        for _ in 0..n {
+                            let candidate = rng.below(n);
+                            if candidate != i && !t.has_edge(nodes[i], nodes[candidate]) {
+                                target = candidate;
+                                break;
+                            }
+                        }
+                    }
+                    if !t.has_edge(nodes[i], nodes[target]) {
+                        t.add_edge(nodes[i], nodes[target]);
+                    }
+                }
+            }
+        }
+    }
+
+    t
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn counts(family: GraphFamily, n: usize, m: usize) -> (usize, usize) {
+        let t = generate(family, &GeneratorParams { n, m, ..Default::default() });
+        (t.nodes.len(), t.edges.len())
+    }
+
+    #[test]
+    fn families_have_the_right_size() {
+        assert_eq!(counts(GraphFamily::Complete, 5, 0), (5, 10));
+        assert_eq!(counts(GraphFamily::CompleteBipartite, 5, 3), (8, 15));
+        assert_eq!(counts(GraphFamily::Cycle, 5, 0), (5, 5));
+        assert_eq!(counts(GraphFamily::Path, 5, 0), (5, 4));
+        assert_eq!(counts(GraphFamily::Star, 5, 0), (6, 5));
+        assert_eq!(counts(GraphFamily::Wheel, 5, 0), (6, 10));
+        assert_eq!(counts(GraphFamily::Grid, 5, 3), (15, 22));
+        assert_eq!(counts(GraphFamily::Torus, 5, 3), (15, 30));
+        assert_eq!(counts(GraphFamily::Hypercube, 3, 0), (8, 12));
+        assert_eq!(counts(GraphFamily::Petersen, 0, 0), (10, 15));
+        assert_eq!(counts(GraphFamily::BinaryTree, 3, 0), (7, 6));
+    }
+
+    #[test]
+    fn random_families_follow_their_parameters() {
+        let params = |p: f32, seed: u64| GeneratorParams { n: 12, m: 3, k: 2, p, seed };
+        let edges = |family, params: &GeneratorParams| -> Vec<(usize, usize)> {
+            generate(family, params).edges.iter().map(|e| (e.start, e.end)).collect()
+        };
+
+        assert!(edges(GraphFamily::ErdosRenyi, &params(0.0, 1)).is_empty());
+        assert_eq!(edges(GraphFamily::ErdosRenyi, &params(1.0, 1)).len(), 66);
+        assert_eq!(edges(GraphFamily::ErdosRenyi, &params(0.5, 7)), edges(GraphFamily::ErdosRenyi, &params(0.5, 7)));
+
+        // K₄ to start with, then three edges for each of the other 8 nodes
+        assert_eq!(edges(GraphFamily::BarabasiAlbert, &params(0.0, 3)).len(), 6 + 8 * 3);
+        // Each node joined to two neighbours on either side, and rewiring
+        // never makes a loop or a parallel edge
+        assert_eq!(edges(GraphFamily::WattsStrogatz, &params(0.0, 3)).len(), 24);
+        let rewired = edges(GraphFamily::WattsStrogatz, &params(0.5, 3));
+        let distinct: std::collections::HashSet<(usize, usize)> =
+            rewired.iter().map(|&(a, b)| (a.min(b), a.max(b))).collect();
+        assert!(rewired.iter().all(|(a, b)| a != b));
+        assert_eq!(distinct.len(), rewired.len());
+    }
+}
@@ -0,0 +1,121 @@
+use bevy::{
+    prelude::*,
+    render::{mesh::Indices, render_resource::PrimitiveTopology},
+    sprite::{MaterialMesh2dBundle, Mesh2dHandle},
+};
+
+use crate::{theme::Theme, MainCamera};
+
+/// Most lines drawn across the view, past this the grid is hidden rather
+/// than blending into a solid block when zoomed out with a small spacing
+static MAX_LINES: f32 = 400.0;
+
+#[derive(Resource)]
+pub struct Grid {
+    pub show: bool,
+    /// Drop dragged nodes onto the nearest grid point
+    pub snap: bool,
+    pub spacing: f32,
+}
+
+impl Default for Grid {
+    fn default() -> Self {
+        Grid {
+            show: false,
+            snap: false,
+            spacing: 50.0,
+        }
+    }
+}
+
+impl Grid {
+    /// The grid point closest to `pos`
+    pub fn snap_point(&self, pos: Vec2) -> Vec2 {
+        (pos / self.spacing).round() * self.spacing
+    }
+}
+
+#[derive(Component)]
+pub(crate) struct GridMesh;
+
+fn grid_mesh(lines: Vec<[f32; 3]>) -> Mesh {
+    let mut mesh = Mesh::new(PrimitiveTopology::LineList);
+    let count = lines.len() as u32;
+    mesh.insert_attribute(Mesh::ATTRIBUTE_UV_0, vec![[0.0, 0.0]; lines.len()]);
+    mesh.insert_attribute(Mesh::ATTRIBUTE_POSITION, lines);
+    mesh.set_indices(Some(Indices::U32((0..count).collect())));
+    mesh
+}
+
+pub(crate) fn spawn_grid(
+    mut commands: Commands,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<ColorMaterial>>,
+) {
+    // Behind the edge mesh
+    commands.spawn((
+        MaterialMesh2dBundle {
+            mesh: meshes.add(grid_mesh(vec![[0.0; 3]; 2])).into(),
+            material: materials.add(ColorMaterial::default()),
+            transform: Transform::from_translation(Vec3::new(0.0, 0.0, -2.0)),
+            visibility: Visibility::Hidden,
+            ..Default::default()
+        },
+        GridMesh,
+    ));
+}
+
+/// Cover the part of the world the camera sees with grid lines, redrawing
+/// them when the camera moves or the grid or theme change
+pub(crate) fn draw_grid(
+    grid: Res<Grid>,
+    theme: Res<Theme>,
+    q_camera: Query<(&Transform, &OrthographicProjection), With<MainCamera>>,
+    mut q_grid: Query<(&Mesh2dHandle, &Handle<ColorMaterial>, &mut Visibility), With<GridMesh>>,
+    (mut meshes, mut materials): (ResMut<Assets<Mesh>>, ResMut<Assets<ColorMaterial>>),
+    mut drawn: Local<Option<(Rect, f32, Color)>>,
+) {
+    let (Ok((camera_t, proj)), Ok((mesh, material, mut visibility))) = (q_camera.get_single(), q_grid.get_single_mut()) else {
+        return;
+    };
+
+    let color = theme.label.with_a(0.12);
+    let view = Rect::from_corners(
+        proj.area.min + camera_t.translation.truncate(),
+        proj.area.max + camera_t.translation.truncate(),
+    );
+    let too_dense = view.width() / grid.spacing > MAX_LINES || view.height() / grid.spacing > MAX_LINES;
+    if !grid.show || too_dense {
+        visibility.set_if_neq(Visibility::Hidden);
+        return;
+    }
+    visibility.set_if_neq(Visibility::Inherited);
+
+    if *drawn == Some((view, grid.spacing, color)) {
+        return;
+    }
+    *drawn = Some((view, grid.spacing, color));
+
+    if let Some(material) = materials.get_mut(material) {
+        material.color = color;
+    }
+
+    let start = (view.min / grid.spacing).floor() * grid.spacing;
+    let mut lines = Vec::new();
+    let mut x = start.x;
+    while x <= view.max.x {
+        lines.push([x, view.min.y, 0.0]);
+        lines.push([x, view.max.y, 0.0]);
+        x += grid.spacing;
+    }
+    let mut y = start.y;
+    while y <= view.max.y {
+        lines.push([view.min.x, y, 0.0]);
+        lines.push([view.max.x, y, 0.0]);
+        y += grid.spacing;
+    }
+
+    if let Some(mesh) = meshes.get_mut(&mesh.0) {
+        *mesh = grid_mesh(lines);
+    }
+}
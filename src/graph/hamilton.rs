@@ -0,0 +1,336 @@
+use std::sync::atomic::{AtomicBool, Ordering};
+
+use bevy::prelude::*;
+
+use super::{EdgeE, GEdge, Graph, NodeE, PathPart};
+
+/// The largest graph the Hamiltonian search will attempt
+pub static HAMILTONIAN_LIMIT: usize = 64;
+/// The largest graph the travelling salesman problem is solved exactly for,
+/// beyond this a heuristic is used
+pub static TSP_EXACT_LIMIT: usize = 15;
+/// The fewest nodes a cycle in an undirected graph can visit without using an
+/// edge twice. A directed graph only needs two, going there and back along
+/// opposite arcs.
+pub static MIN_CYCLE: usize = 3;
+
+/// A copy of the graph's edges with their weights, indexed by position in
+/// `nodes`, that can be handed off to a background thread
+pub struct WeightedAdjacency {
+    pub nodes: Vec<NodeE>,
+    /// The lightest edge from one node to another, following edge direction
+    pub matrix: Vec<Vec<Option<(EdgeE, i32)>>>,
+    pub directed: bool,
+}
+
+impl Graph {
+    pub fn weighted_adjacency(&self, q_edge: &Query<&GEdge>) -> WeightedAdjacency {
+        let mut nodes: Vec<NodeE> = self.node_edges.keys().copied().collect();
+        nodes.sort_by_key(|node| node.0);
+        let index = |node: &NodeE| nodes.iter().position(|n| n == node).unwrap();
+
+        let mut matrix = vec![vec![None; nodes.len()]; nodes.len()];
+        for (edge, (start, end)) in self.edge_nodes.iter() {
+            if start == end {
+                continue;
+            }
+            let weight = q_edge.get(**edge).map(|e| e.weight).unwrap_or(1);
+            let (s, e) = (index(start), index(end));
+            let mut set = |a: usize, b: usize| {
+                if matrix[a][b].is_none_or(|(_, w)| weight < w) {
+                    matrix[a][b] = Some((*edge, weight));
+                }
+            };
+            set(s, e);
+            if !self.directed {
+                set(e, s);
+            }
+        }
+
+        WeightedAdjacency { nodes, matrix, directed: self.directed }
+    }
+}
+
+/// The search was stopped before it finished
+#[derive(Debug)]
+pub struct Cancelled;
+
+impl WeightedAdjacency {
+    fn len(&self) -> usize {
+        self.nodes.len()
+    }
+
+    fn weight(&self, a: usize, b: usize) -> Option<i64> {
+        self.matrix[a][b].map(|(_, w)| w as i64)
+    }
+
+    /// Turn a sequence of node indices into a path along the graph's edges
+    pub fn path(&self, order: &[usize]) -> Vec<PathPart> {
+        let mut path = vec![PathPart::without_edge(self.nodes[order[0]])];
+        for pair in order.windows(2) {
+            let (edge, _) = self.matrix[pair[0]][pair[1]].unwrap();
+            path.push(PathPart::with_edge(self.nodes[pair[1]], edge));
+        }
+        path
+    }
+
+    pub fn cost(&self, order: &[usize]) -> Option<i64> {
+        order.windows(2).map(|pair| self.weight(pair[0], pair[1])).sum()
+    }
+
+    /// The fewest nodes a Hamiltonian cycle or tour can visit
+    pub fn min_cycle(&self) -> usize {
+        if self.directed {
+            2
+        } else {
+            MIN_CYCLE
+        }
+    }
+
+    pub fn is_complete(&self) -> bool {
+        (0..self.len()).all(|a| (0..self.len()).all(|b| a == b || self.matrix[a][b].is_some()))
+    }
+
+    /// Backtracking search for a path visiting every node once, returning to
+    /// the start if `cycle` is set. An undirected cycle needs at least three
+    /// nodes, with fewer it could only go back along the edge it came by.
+    pub fn hamiltonian(&self, cycle: bool, cancel: &AtomicBool) -> Result<Option<Vec<usize>>, Cancelled> {
+        let n = self.len();
+        if n == 0 || (cycle && n < self.min_cycle()) {
+            return Ok(None);
+        }
+
+        fn search(
+            adj: &WeightedAdjacency,
+            path: &mut Vec<usize>,
+            visited: u64,
+            cycle: bool,
+            cancel: &AtomicBool,
+        ) -> Result<bool, Cancelled> {
+            if cancel.load(Ordering::Relaxed) {
+                return Err(Cancelled);
+            }
+
+            let n = adj.len();
+            let last = *path.last().unwrap();
+            if path.len() == n {
+                return Ok(!cycle || adj.matrix[last][path[0]].is_some());
+            }
+
+            // Try the most constrained neighbours first (Warnsdorff's rule)
+            let mut next: Vec<(usize, usize)> = (0..n)
+                .filter(|&v| visited & (1 << v) == 0 && adj.matrix[last][v].is_some())
+                .map(|v| {
+                    let onward = (0..n)
+                        .filter(|&w| visited & (1 << w) == 0 && adj.matrix[v][w].is_some())
+                        .count();
+                    (onward, v)
+                })
+                .collect();
+            next.sort();
+
+            for (_, v) in next {
+                path.push(v);
+                if search(adj, path, visited | (1 << v), cycle, cancel)? {
+                    return Ok(true);
+                }
+                path.pop();
+            }
+            Ok(false)
+        }
+
+        // A cycle can start anywhere, a path has to try every start
+        let starts = if cycle { 0..1 } else { 0..n };
+        for start in starts {
+            let mut path = vec![start];
+            if search(self, &mut path, 1 << start, cycle, cancel)? {
+                if cycle {
+                    path.push(start);
+                }
+                return Ok(Some(path));
+            }
+        }
+        Ok(None)
+    }
+
+    /// Held–Karp dynamic programming over subsets, finds the cheapest tour
+    /// starting and ending at node 0. Like a Hamiltonian cycle, a tour needs
+    /// at least three nodes unless the graph is directed.
+    pub fn tsp_exact(&self, cancel: &AtomicBool) -> Result<Option<Vec<usize>>, Cancelled> {
+        let n = self.len();
+        if n < self.min_cycle() {
+            return Ok(None);
+        }
+
+        let full = 1usize << n;
+        let mut cost = vec![vec![i64::MAX; n]; full];
+        let mut prev = vec![vec![usize::MAX; n]; full];
+        cost[1][0] = 0;
+
+        for mask in 1..full {
+            if mask & 1 == 0 {
+                continue;
+            }
+            if cancel.load(Ordering::Relaxed) {
+                return Err(Cancelled);
+            }
+            for last in 0..n {
+                if mask & (1 << last) == 0 || cost[mask][last] == i64::MAX {
+                    continue;
+                }
+                for next in 0..n {
+                    if mask & (1 << next) != 0 {
+                        continue;
+                    }
+                    if let Some(w) = self.weight(last, next) {
+                        let (m, c) = (mask | (1 << next), cost[mask][last] + w);
+                        if c < cost[m][next] {
+                            cost[m][next] = c;
+                            prev[m][next] = last;
+                        }
+                    }
+                }
+            }
+        }
+
+        let Some((_, mut last)) = (1..n)
+            .filter_map(|last| {
+                let back = self.weight(last, 0)?;
+                (cost[full - 1][last] != i64::MAX).then(|| (cost[full - 1][last] + back, last))
+            })
+            .min()
+        else {
+            return Ok(None);
+        };
+
+        let mut tour = vec![0];
+        let mut mask = full - 1;
+        while last != 0 {
+            tour.push(last);
+            let p = prev[mask][last];
+            mask &= !(1 << last);
+            last = p;
+        }
+        tour.push(0);
+        tour.reverse();
+        Ok(Some(tour))
+    }
+
+    /// Nearest neighbour tour improved with 2-opt moves until none help,
+    /// expects a complete graph
+    pub fn tsp_heuristic(&self, cancel: &AtomicBool) -> Result<Option<Vec<usize>>, Cancelled> {
+        let n = self.len();
+        if n < self.min_cycle() || !self.is_complete() {
+            return Ok(None);
+        }
+
+        let mut tour = vec![0];
+        let mut visited = vec![false; n];
+        visited[0] = true;
+        while tour.len() < n {
+            let last = *tour.last().unwrap();
+            let next = (0..n)
+                .filter(|&v| !visited[v])
+                .min_by_key(|&v| self.weight(last, v).unwrap())
+                .unwrap();
+            visited[next] = true;
+            tour.push(next);
+        }
+        tour.push(0);
+
+        // Reversing a section changes the direction it's travelled in, so the
+        // whole tour is re-costed to stay correct for directed graphs
+        let mut best = self.cost(&tour).unwrap();
+        let mut improved = true;
+        while improved {
+            improved = false;
+            for i in 1..n - 1 {
+                if cancel.load(Ordering::Relaxed) {
+                    return Err(Cancelled);
+                }
+                for j in (i + 1)..n {
+                    tour[i..=j].reverse();
+                    let cost = self.cost(&tour).unwrap();
+                    if cost < best {
+                        best = cost;
+                        improved = true;
+                    } else {
+                        tour[i..=j].reverse();
+                    }
+                }
+            }
+        }
+        Ok(Some(tour))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A weighted adjacency for the given undirected edges
+    fn adjacency(n: usize, edges: &[(usize, usize, i32)]) -> WeightedAdjacency {
+        let mut matrix = vec![vec![None; n]; n];
+        for (i, &(a, b, w)) in edges.iter().enumerate() {
+            let edge = EdgeE(Entity::from_raw(i as u32));
+            matrix[a][b] = Some((edge, w));
+            matrix[b][a] = Some((edge, w));
+        }
+        WeightedAdjacency {
+            nodes: (0..n).map(|i| NodeE(Entity::from_raw((n + i) as u32))).collect(),
+            matrix,
+            directed: false,
+        }
+    }
+
+    #[test]
+    fn two_nodes_have_a_path_but_no_cycle() {
+        let adj = adjacency(2, &[(0, 1, 1)]);
+        let cancel = AtomicBool::new(false);
+        assert!(matches!(adj.hamiltonian(false, &cancel), Ok(Some(path)) if path.len() == 2));
+        assert!(matches!(adj.hamiltonian(true, &cancel), Ok(None)));
+        assert!(matches!(adj.tsp_exact(&cancel), Ok(None)));
+        assert!(matches!(adj.tsp_heuristic(&cancel), Ok(None)));
+    }
+
+    #[test]
+    fn triangle_has_a_cycle() {
+        let adj = adjacency(3, &[(0, 1, 1), (1, 2, 2), (2, 0, 3)]);
+        let cancel = AtomicBool::new(false);
+        let Ok(Some(cycle)) = adj.hamiltonian(true, &cancel) else {
+            panic!("no cycle found in a triangle");
+        };
+        assert_eq!(cycle.len(), 4);
+        assert_eq!(cycle[0], cycle[3]);
+
+        let Ok(Some(tour)) = adj.tsp_exact(&cancel) else {
+            panic!("no tour found in a triangle");
+        };
+        assert_eq!(adj.cost(&tour), Some(6));
+    }
+
+    #[test]
+    fn path_graph_has_no_cycle() {
+        let adj = adjacency(3, &[(0, 1, 1), (1, 2, 1)]);
+        let cancel = AtomicBool::new(false);
+        assert!(matches!(adj.hamiltonian(true, &cancel), Ok(None)));
+        assert!(matches!(adj.hamiltonian(false, &cancel), Ok(Some(_))));
+    }
+
+    #[test]
+    fn two_opposite_arcs_are_a_directed_cycle() {
+        let mut adj = adjacency(2, &[]);
+        adj.directed = true;
+        adj.matrix[0][1] = Some((EdgeE(Entity::from_raw(0)), 1));
+        let cancel = AtomicBool::new(false);
+        assert!(matches!(adj.hamiltonian(true, &cancel), Ok(None)));
+
+        adj.matrix[1][0] = Some((EdgeE(Entity::from_raw(1)), 2));
+        assert!(matches!(adj.hamiltonian(true, &cancel), Ok(Some(cycle)) if cycle == vec![0, 1, 0]));
+        let Ok(Some(tour)) = adj.tsp_exact(&cancel) else {
+            panic!("no tour found along two opposite arcs");
+        };
+        assert_eq!(adj.cost(&tour), Some(3));
+        assert!(matches!(adj.tsp_heuristic(&cancel), Ok(Some(tour)) if tour == vec![0, 1, 0]));
+    }
+}
@@ -0,0 +1,238 @@
+//! Plain text graph files, one item per line:
+//!
+//! ```text
+//! directed <true|false>
+//! node <x> <y> ["label"]
+//! edge <start node index> <end node index> <weight>
+//! style <node index> <circle|square|diamond|triangle|image> <size> [image path]
+//! ```
+//!
+//! Nodes without a `style` line are drawn as circles of the default size, and
+//! nodes without a label are numbered when loaded. Labels are quoted so an
+//! empty one is kept, with line breaks written as `\n`, quotes as `\"` and
+//! backslashes as `\\`. Blank lines and lines starting with `#` are ignored.
+//!
+//! Only what a [`GraphTemplate`] holds is saved. Edge styles, handle
+//! positions, bends and edge labels are dropped, and loaded edges get the
+//! default style and placement.
+
+use std::path::Path;
+
+use bevy::prelude::*;
+
+use super::{
+    shape::{NodeShape, NodeStyle},
+    template::{GraphTemplate, TemplateEdge, TemplateNode},
+};
+
+pub fn to_string(t: &GraphTemplate, directed: bool) -> String {
+    let mut out = String::from("# graph-plot\n");
+    out += &format!("directed {directed}\n");
+    for node in t.nodes.iter() {
+        out += &format!("node {} {}", node.pos.x, node.pos.y);
+        if let Some(label) = &node.label {
+            out += &format!(" \"{}\"", escape(label));
+        }
+        out += "\n";
+    }
+    for edge in t.edges.iter() {
+        out += &format!("edge {} {} {}\n", edge.start, edge.end, edge.weight);
+    }
+    for (i, node) in t.nodes.iter().enumerate() {
+        if node.style == NodeStyle::default() {
+            continue;
+        }
+        out += &format!("style {i} {} {}", node.style.shape.name(), node.style.size);
+        if let NodeShape::Image(path) = &node.style.shape {
+            out += &format!(" {path}");
+        }
+        out += "\n";
+    }
+    out
+}
+
+fn escape(label: &str) -> String {
+    label.replace('\\', "\\\\").replace('\n', "\\n").replace('"', "\\\"")
+}
+
+fn unescape(label: &str) -> String {
+    let mut out = String::with_capacity(label.len());
+    let mut chars = label.chars();
+    while let Some(c) = chars.next() {
+        match (c, chars.clone().next()) {
+            ('\\', Some('n')) => {
+                out.push('\n');
+                chars.next();
+            }
+            ('\\', Some(escaped @ ('\\' | '"'))) => {
+                out.push(escaped);
+                chars.next();
+            }
+            _ => out.push(c),
+        }
+    }
+    out
+}
+
+pub fn from_str(src: &str) -> Result<(GraphTemplate, bool), String> {
+    let mut t = GraphTemplate::default();
+    let mut directed = false;
+
+    for (i, line) in src.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+
+        let err = |what: &str| format!("Line {}: {what}", i + 1);
+        let (kind, rest) = line.split_once(' ').unwrap_or((line, ""));
+        match kind {
+            "directed" => {
+                directed = rest.trim().parse().map_err(|_| err("expected true or false"))?;
+            }
+            "node" => {
+                let mut parts = rest.splitn(3, ' ');
+                let mut coord = || -> Result<f32, String> {
+                    parts
+                        .next()
+                        .and_then(|p| p.parse().ok())
+                        .ok_or_else(|| err("expected node coordinates"))
+                };
+                let pos = Vec2::new(coord()?, coord()?);
+                let label = match parts.next() {
+                    Some(quoted) => Some(
+                        quoted
+                            .strip_prefix('"')
+                            .and_then(|l| l.strip_suffix('"'))
+                            .map(unescape)
+                            .ok_or_else(|| err("expected the node label in double quotes"))?,
+                    ),
+                    None => None,
+                };
+                t.nodes.push(TemplateNode {
+                    pos,
+                    label,
+                    ..Default::default()
+                });
+            }
+            "edge" => {
+                let parts: Vec<&str> = rest.split_whitespace().collect();
+                let index = |n: usize| -> Result<usize, String> {
+                    parts
+                        .get(n)
+                        .and_then(|p| p.parse().ok())
+                        .filter(|&i| i < t.nodes.len())
+                        .ok_or_else(|| err("expected the indices of two nodes defined above"))
+                };
+                let (start, end) = (index(0)?, index(1)?);
+                let weight = match parts.get(2) {
+                    Some(w) => w.parse().map_err(|_| err("expected an integer weight"))?,
+                    None => 1,
+                };
+                t.edges.push(TemplateEdge { start, end, weight });
+            }
+            "style" => {
+                let mut parts = rest.splitn(4, ' ');
+                let node = parts
+                    .next()
+                    .and_then(|p| p.parse().ok())
+                    .filter(|&i: &usize| i < t.nodes.len())
+                    .ok_or_else(|| err("expected the index of a node defined above"))?;
+                let name = parts.next().unwrap_or_default();
+                let size = parts
+                    .next()
+                    .and_then(|p| p.parse().ok())
+                    .filter(|&s: &f32| s > 0.0)
+                    .ok_or_else(|| err("expected a positive node size"))?;
+                let shape = NodeShape::from_name(name, parts.next().filter(|p| !p.is_empty()))
+                    .ok_or_else(|| err(&format!("unknown node shape `{name}`")))?;
+                t.nodes[node].style = NodeStyle { shape, size };
+            }
+            _ => return Err(err(&format!("unknown item `{kind}`"))),
+        }
+    }
+
+    Ok((t, directed))
+}
+
+pub fn save(path: &Path, t: &GraphTemplate, directed: bool) -> Result<(), String> {
+    std::fs::write(path, to_string(t, directed))
+        .map_err(|e| format!("Failed to save {}: {e}", path.display()))
+}
+
+pub fn load(path: &Path) -> Result<(GraphTemplate, bool), String> {
+    let src = std::fs::read_to_string(path)
+        .map_err(|e| format!("Failed to read {}: {e}", path.display()))?;
+    from_str(&src).map_err(|e| format!("Failed to load {}: {e}", path.display()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn node(pos: Vec2, label: Option<&str>, style: NodeStyle) -> TemplateNode {
+        TemplateNode {
+            pos,
+            label: label.map(String::from),
+            style,
+        }
+    }
+
+    #[test]
+    fn round_trip_keeps_labels_styles_and_weights() {
+        let mut t = GraphTemplate::default();
+        t.nodes.push(node(Vec2::new(1.5, -2.0), Some(""), NodeStyle::default()));
+        t.nodes.push(node(Vec2::ZERO, None, NodeStyle::default()));
+        t.nodes.push(node(
+            Vec2::new(-3.0, 4.25),
+            Some(" two\nlines, \"quoted\" \\ "),
+            NodeStyle {
+                shape: NodeShape::Image("assets/some image.png".into()),
+                size: 40.0,
+            },
+        ));
+        t.nodes.push(node(
+            Vec2::ONE,
+            Some("\""),
+            NodeStyle {
+                shape: NodeShape::Diamond,
+                size: 20.0,
+            },
+        ));
+        t.edges.push(TemplateEdge { start: 0, end: 1, weight: -3 });
+        t.edges.push(TemplateEdge { start: 2, end: 2, weight: 1 });
+        t.edges.push(TemplateEdge { start: 3, end: 0, weight: 7 });
+
+        let (loaded, directed) = from_str(&to_string(&t, true)).unwrap();
+        assert!(directed);
+        assert_eq!(loaded.nodes.len(), t.nodes.len());
+        for (a, b) in loaded.nodes.iter().zip(t.nodes.iter()) {
+            assert_eq!(a.pos, b.pos);
+            assert_eq!(a.label, b.label);
+            assert!(a.style == b.style);
+        }
+        let edges = |t: &GraphTemplate| -> Vec<(usize, usize, i32)> {
+            t.edges.iter().map(|e| (e.start, e.end, e.weight)).collect()
+        };
+        assert_eq!(edges(&loaded), edges(&t));
+    }
+
+    #[test]
+    fn parse_errors_name_the_line() {
+        let cases = [
+            ("directed maybe", "Line 1: expected true or false"),
+            ("node 1", "Line 1: expected node coordinates"),
+            ("node 1 2 unquoted", "Line 1: expected the node label in double quotes"),
+            ("node 0 0\nedge 0 1", "Line 2: expected the indices of two nodes defined above"),
+            ("node 0 0\nedge 0 0 heavy", "Line 2: expected an integer weight"),
+            ("style 0 circle 10", "Line 1: expected the index of a node defined above"),
+            ("node 0 0\n\n# comment\nstyle 0 circle -1", "Line 4: expected a positive node size"),
+            ("node 0 0\nstyle 0 hexagon 10", "Line 2: unknown node shape `hexagon`"),
+            ("node 0 0\nstyle 0 image 10", "Line 2: unknown node shape `image`"),
+            ("vertex 0 0", "Line 1: unknown item `vertex`"),
+        ];
+        for (src, expected) in cases {
+            assert_eq!(from_str(src).err().as_deref(), Some(expected), "parsing {src:?}");
+        }
+    }
+}
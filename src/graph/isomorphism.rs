@@ -0,0 +1,279 @@
+use std::collections::BTreeMap;
+
+use super::template::GraphTemplate;
+
+/// The most edge counts the isomorphism search compares before giving up,
+/// which keeps it to a fraction of a second on the UI thread
+pub static ISOMORPHISM_STEPS: usize = 20_000_000;
+
+/// The isomorphism search hit `ISOMORPHISM_STEPS` without an answer
+#[derive(Debug)]
+pub struct GaveUp;
+
+/// A node's color along with the colors and edge counts of its out and in
+/// neighbours
+type Signature = (usize, Vec<(usize, usize)>, Vec<(usize, usize)>);
+
+/// Edge multiplicities between every ordered pair of nodes, undirected edges
+/// are counted both ways
+fn multiplicities(t: &GraphTemplate, directed: bool) -> Vec<Vec<usize>> {
+    let mut count = vec![vec![0; t.nodes.len()]; t.nodes.len()];
+    for edge in t.edges.iter() {
+        count[edge.start][edge.end] += 1;
+        if !directed && edge.start != edge.end {
+            count[edge.end][edge.start] += 1;
+        }
+    }
+    count
+}
+
+/// Color refinement (1-dimensional Weisfeiler–Leman) over both graphs at once
+/// so their colors can be compared. Nodes that could be mapped to each other
+/// always end up with the same color.
+fn refine_colors(a: &[Vec<usize>], b: &[Vec<usize>]) -> (Vec<usize>, Vec<usize>) {
+    let graphs = [a, b];
+    let mut colors: Vec<Vec<usize>> = graphs.iter().map(|g| vec![0; g.len()]).collect();
+    let mut classes = 1;
+
+    loop {
+        let mut ids: BTreeMap<Signature, usize> = BTreeMap::new();
+        let signatures: Vec<Vec<Signature>> = graphs
+            .iter()
+            .zip(colors.iter())
+            .map(|(g, color)| {
+                (0..g.len())
+                    .map(|u| {
+                        let mut out: Vec<(usize, usize)> = (0..g.len())
+                            .filter(|&v| g[u][v] > 0)
+                            .map(|v| (color[v], g[u][v]))
+                            .collect();
+                        let mut into: Vec<(usize, usize)> = (0..g.len())
+                            .filter(|&v| g[v][u] > 0)
+                            .map(|v| (color[v], g[v][u]))
+                            .collect();
+                        out.sort();
+                        into.sort();
+                        (color[u], out, into)
+                    })
+                    .collect()
+            })
+            .collect();
+        for signature in signatures.iter().flatten() {
+            let next = ids.len();
+            ids.entry(signature.clone()).or_insert(next);
+        }
+
+        colors = signatures
+            .iter()
+            .map(|g| g.iter().map(|signature| ids[signature]).collect())
+            .collect();
+        if ids.len() == classes {
+            break;
+        }
+        classes = ids.len();
+    }
+
+    let b_colors = colors.pop().unwrap();
+    (colors.pop().unwrap(), b_colors)
+}
+
+/// The eigenvalues of the adjacency matrix, with directed edges counted both
+/// ways so the matrix stays symmetric
+pub fn spectrum(t: &GraphTemplate, directed: bool) -> Vec<f64> {
+    let count = multiplicities(t, directed);
+    let n = count.len();
+    let matrix = nalgebra::DMatrix::from_fn(n, n, |i, j| {
+        if directed {
+            (count[i][j] + count[j][i]) as f64
+        } else {
+            count[i][j] as f64
+        }
+    });
+    let mut values: Vec<f64> = nalgebra::linalg::SymmetricEigen::new(matrix).eigenvalues.iter().copied().collect();
+    values.sort_by(|a, b| b.total_cmp(a));
+    values
+}
+
+fn degree_sequence(t: &GraphTemplate) -> Vec<usize> {
+    let mut degree = vec![0; t.nodes.len()];
+    for edge in t.edges.iter() {
+        degree[edge.start] += 1;
+        degree[edge.end] += 1;
+    }
+    degree.sort_by(|a, b| b.cmp(a));
+    degree
+}
+
+fn format_list<T: std::fmt::Display>(list: &[T]) -> String {
+    list.iter().map(|x| format!("{x}")).collect::<Vec<_>>().join(", ")
+}
+
+/// Describe the invariants that tell two graphs apart, empty if none do
+pub fn invariant_differences(a: &GraphTemplate, b: &GraphTemplate, directed: bool) -> Vec<String> {
+    let mut differences = Vec::new();
+    if a.nodes.len() != b.nodes.len() {
+        differences.push(format!("Vertices: {} vs {}", a.nodes.len(), b.nodes.len()));
+    }
+    if a.edges.len() != b.edges.len() {
+        differences.push(format!("Edges: {} vs {}", a.edges.len(), b.edges.len()));
+    }
+
+    let (da, db) = (degree_sequence(a), degree_sequence(b));
+    if da != db {
+        differences.push(format!("Degree sequence: [{}] vs [{}]", format_list(&da), format_list(&db)));
+    }
+
+    let (sa, sb) = (spectrum(a, directed), spectrum(b, directed));
+    let same_spectrum = sa.len() == sb.len() && sa.iter().zip(sb.iter()).all(|(x, y)| (x - y).abs() < 1e-6);
+    if !same_spectrum {
+        let round = |s: &[f64]| s.iter().map(|x| format!("{x:.3}")).collect::<Vec<_>>();
+        differences.push(format!(
+            "Spectrum: [{}] vs [{}]",
+            round(&sa).join(", "),
+            round(&sb).join(", ")
+        ));
+    }
+    differences
+}
+
+/// Search for an isomorphism from `a` to `b`, returning the node of `b` that
+/// each node of `a` maps to. Candidates are narrowed down by color
+/// refinement and extended one node at a time as in VF2, checking edge counts
+/// against every node mapped so far. Gives up on graphs too symmetric for the
+/// colors to tell their nodes apart, where the search can take exponentially
+/// long.
+pub fn isomorphism(a: &GraphTemplate, b: &GraphTemplate, directed: bool) -> Result<Option<Vec<usize>>, GaveUp> {
+    if a.nodes.len() != b.nodes.len() || a.edges.len() != b.edges.len() {
+        return Ok(None);
+    }
+    let n = a.nodes.len();
+    let (ca, cb) = (multiplicities(a, directed), multiplicities(b, directed));
+    let (color_a, color_b) = refine_colors(&ca, &cb);
+
+    let histogram = |colors: &[usize]| {
+        let mut sorted = colors.to_vec();
+        sorted.sort();
+        sorted
+    };
+    if histogram(&color_a) != histogram(&color_b) {
+        return Ok(None);
+    }
+
+    // Map nodes in the rarest color classes first, then keep to nodes
+    // adjacent to those already mapped so inconsistencies show up early
+    let class_size = |color: usize| color_a.iter().filter(|&&c| c == color).count();
+    let mut order: Vec<usize> = Vec::with_capacity(n);
+    let mut placed = vec![false; n];
+    while order.len() < n {
+        let next = (0..n)
+            .filter(|&u| !placed[u])
+            .max_by_key(|&u| {
+                let links = order.iter().filter(|&&v| ca[u][v] + ca[v][u] > 0).count();
+                (links, std::cmp::Reverse(class_size(color_a[u])), std::cmp::Reverse(u))
+            })
+            .unwrap();
+        placed[next] = true;
+        order.push(next);
+    }
+
+    struct State<'a> {
+        ca: &'a [Vec<usize>],
+        cb: &'a [Vec<usize>],
+        color_a: &'a [usize],
+        color_b: &'a [usize],
+        order: &'a [usize],
+        mapping: Vec<Option<usize>>,
+        used: Vec<bool>,
+        steps: usize,
+    }
+
+    fn extend(s: &mut State, depth: usize) -> Result<bool, GaveUp> {
+        let Some(&u) = s.order.get(depth) else {
+            return Ok(true);
+        };
+
+        for v in 0..s.cb.len() {
+            if s.used[v] || s.color_a[u] != s.color_b[v] || s.ca[u][u] != s.cb[v][v] {
+                continue;
+            }
+            s.steps += depth;
+            if s.steps > ISOMORPHISM_STEPS {
+                return Err(GaveUp);
+            }
+            let consistent = s.order[..depth].iter().all(|&w| {
+                let x = s.mapping[w].unwrap();
+                s.ca[u][w] == s.cb[v][x] && s.ca[w][u] == s.cb[x][v]
+            });
+            if !consistent {
+                continue;
+            }
+
+            s.mapping[u] = Some(v);
+            s.used[v] = true;
+            if extend(s, depth + 1)? {
+                return Ok(true);
+            }
+            s.mapping[u] = None;
+            s.used[v] = false;
+        }
+        Ok(false)
+    }
+
+    let mut state = State {
+        ca: &ca,
+        cb: &cb,
+        color_a: &color_a,
+        color_b: &color_b,
+        order: &order,
+        mapping: vec![None; n],
+        used: vec![false; n],
+        steps: 0,
+    };
+    Ok(extend(&mut state, 0)?.then(|| state.mapping.into_iter().map(Option::unwrap).collect()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn template(n: usize, pairs: &[(usize, usize)]) -> GraphTemplate {
+        let mut t = GraphTemplate::default();
+        for _ in 0..n {
+            t.add_node(bevy::prelude::Vec2::ZERO);
+        }
+        for &(start, end) in pairs {
+            t.add_edge(start, end);
+        }
+        t
+    }
+
+    #[test]
+    fn direction_matters_only_for_directed_graphs() {
+        // a -> b -> c against a -> b <- c
+        let path = template(3, &[(0, 1), (1, 2)]);
+        let converging = template(3, &[(0, 1), (2, 1)]);
+        assert!(isomorphism(&path, &converging, false).unwrap().is_some());
+        assert!(isomorphism(&path, &converging, true).unwrap().is_none());
+    }
+
+    #[test]
+    fn mapping_preserves_directed_edges() {
+        let a = template(4, &[(0, 1), (1, 2), (2, 3), (3, 0), (0, 2)]);
+        // The same graph with its nodes numbered backwards
+        let b = template(4, &[(3, 2), (2, 1), (1, 0), (0, 3), (3, 1)]);
+        let mapping = isomorphism(&a, &b, true).unwrap().unwrap();
+        for edge in a.edges.iter() {
+            let (start, end) = (mapping[edge.start], mapping[edge.end]);
+            assert!(b.edges.iter().any(|e| e.start == start && e.end == end));
+        }
+    }
+
+    #[test]
+    fn hexagon_is_not_two_triangles() {
+        // Every node has degree 2 in both, so only the search tells them apart
+        let hexagon = template(6, &[(0, 1), (1, 2), (2, 3), (3, 4), (4, 5), (5, 0)]);
+        let triangles = template(6, &[(0, 1), (1, 2), (2, 0), (3, 4), (4, 5), (5, 3)]);
+        assert!(isomorphism(&hexagon, &triangles, false).unwrap().is_none());
+        assert!(isomorphism(&hexagon, &hexagon, false).unwrap().is_some());
+    }
+}
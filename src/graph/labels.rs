@@ -0,0 +1,108 @@
+use bevy::prelude::*;
+
+/// Space left between a label and the node or handle it belongs to
+static GAP: f32 = 4.0;
+/// What a label crossing an edge costs, as a share of the label's own area
+static CROSSING_COST: f32 = 0.5;
+
+/// A label to be placed around the node or edge handle it belongs to
+pub struct LabelBox {
+    /// The centre of the node or edge handle
+    pub anchor: Vec2,
+    /// How far the label has to stay from the anchor to clear the node or
+    /// handle
+    pub clearance: f32,
+    pub size: Vec2,
+}
+
+/// Where to put the centre of each label so they overlap as little as
+/// possible with each other, the nodes and the edges. Nodes are given as
+/// their centre and radius, edges as the straight pieces they're drawn with.
+///
+/// Labels are placed one at a time, each taking the least crowded of the
+/// spots around its anchor, so the result is good rather than optimal. Spots
+/// above the anchor win ties, as that's where labels start out.
+pub fn place_labels(labels: &[LabelBox], nodes: &[(Vec2, f32)], segments: &[(Vec2, Vec2)]) -> Vec<Vec2> {
+    let node_rects: Vec<Rect> = nodes
+        .iter()
+        .map(|(centre, radius)| Rect::from_center_half_size(*centre, Vec2::splat(*radius)))
+        .collect();
+    let mut placed: Vec<Rect> = Vec::new();
+
+    labels
+        .iter()
+        .map(|label| {
+            let half = label.size / 2.0;
+            let area = (label.size.x * label.size.y).max(1.0);
+            let cost = |(i, centre): (usize, Vec2)| {
+                let rect = Rect::from_center_half_size(centre, half);
+                let overlaps: f32 = placed.iter().chain(node_rects.iter()).map(|other| overlap(rect, *other)).sum();
+                let crossings = segments.iter().filter(|(a, b)| crosses(rect, *a, *b)).count();
+                overlaps / area + crossings as f32 * CROSSING_COST + i as f32 * 0.01
+            };
+
+            let best = spots(label)
+                .into_iter()
+                .enumerate()
+                .min_by(|a, b| cost(*a).total_cmp(&cost(*b)))
+                .map_or(label.anchor, |(_, centre)| centre);
+            placed.push(Rect::from_center_half_size(best, half));
+            best
+        })
+        .collect()
+}
+
+/// Spots around a label's anchor in order of preference, in two rings so a
+/// label can step further out when everything close by is taken
+fn spots(label: &LabelBox) -> Vec<Vec2> {
+    let directions = [
+        Vec2::new(0.0, 1.0),
+        Vec2::new(0.0, -1.0),
+        Vec2::new(1.0, 0.0),
+        Vec2::new(-1.0, 0.0),
+        Vec2::new(1.0, 1.0),
+        Vec2::new(-1.0, 1.0),
+        Vec2::new(1.0, -1.0),
+        Vec2::new(-1.0, -1.0),
+    ];
+    let half = label.size / 2.0;
+
+    [label.clearance + GAP, label.clearance + GAP + label.size.max_element()]
+        .into_iter()
+        .flat_map(|distance| directions.map(|d| label.anchor + d * (Vec2::splat(distance) + half)))
+        .collect()
+}
+
+fn overlap(a: Rect, b: Rect) -> f32 {
+    let shared = a.intersect(b);
+    if shared.is_empty() {
+        0.0
+    } else {
+        shared.width() * shared.height()
+    }
+}
+
+/// Whether the segment from `a` to `b` passes through the rectangle, by
+/// clipping it against each side in turn
+fn crosses(rect: Rect, a: Vec2, b: Vec2) -> bool {
+    let d = b - a;
+    let (mut enter, mut leave) = (0.0f32, 1.0f32);
+    for (p, q) in [
+        (-d.x, a.x - rect.min.x),
+        (d.x, rect.max.x - a.x),
+        (-d.y, a.y - rect.min.y),
+        (d.y, rect.max.y - a.y),
+    ] {
+        if p == 0.0 {
+            // Parallel to this side, and outside it
+            if q < 0.0 {
+                return false;
+            }
+        } else if p < 0.0 {
+            enter = enter.max(q / p);
+        } else {
+            leave = leave.min(q / p);
+        }
+    }
+    enter <= leave
+}
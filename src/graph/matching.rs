@@ -0,0 +1,519 @@
+use std::collections::{HashMap, VecDeque};
+
+use bevy::prelude::*;
+
+use super::{EdgeE, GEdge, Graph, NodeE};
+
+#[derive(Copy, Clone, Default, PartialEq, Eq)]
+pub enum MatchingMethod {
+    /// As many edges as possible, with Hopcroft–Karp on bipartite graphs and
+    /// Edmonds' blossom algorithm otherwise
+    #[default]
+    Maximum,
+    /// A maximum matching of a bipartite graph with the least total weight,
+    /// found with the Hungarian algorithm
+    Assignment,
+}
+
+impl core::fmt::Display for MatchingMethod {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            MatchingMethod::Maximum => write!(f, "Maximum Matching"),
+            MatchingMethod::Assignment => write!(f, "Minimum Weight Assignment"),
+        }
+    }
+}
+
+pub struct Matching {
+    pub edges: Vec<EdgeE>,
+    /// The name of the algorithm that found the matching
+    pub algorithm: &'static str,
+    /// For bipartite graphs, a vertex cover with one node per matched edge,
+    /// which proves the matching is maximum by König's theorem
+    pub cover: Option<Vec<NodeE>>,
+    /// Total weight of the matched edges
+    pub weight: i64,
+}
+
+/// The graph as simple undirected adjacency lists over node indices, keeping
+/// the lightest edge between each pair of nodes
+struct SimpleGraph {
+    nodes: Vec<NodeE>,
+    adj: Vec<Vec<usize>>,
+    edges: HashMap<(usize, usize), (EdgeE, i64)>,
+}
+
+impl SimpleGraph {
+    fn edge(&self, a: usize, b: usize) -> (EdgeE, i64) {
+        self.edges[&(a.min(b), a.max(b))]
+    }
+
+    /// Whether each node is on the left side of a bipartite graph, or `None`
+    /// if the graph isn't bipartite. Loops can't be matched and are already
+    /// left out, so they don't stop a graph counting as bipartite here.
+    fn bipartition(&self) -> Option<Vec<bool>> {
+        let n = self.adj.len();
+        let mut left: Vec<Option<bool>> = vec![None; n];
+        for root in 0..n {
+            if left[root].is_some() {
+                continue;
+            }
+            left[root] = Some(true);
+            let mut queue = VecDeque::from([root]);
+            while let Some(u) = queue.pop_front() {
+                let side = left[u];
+                for &v in self.adj[u].iter() {
+                    match left[v] {
+                        None => {
+                            left[v] = side.map(|s| !s);
+                            queue.push_back(v);
+                        }
+                        same if same == side => return None,
+                        Some(_) => (),
+                    }
+                }
+            }
+        }
+        Some(left.into_iter().map(Option::unwrap).collect())
+    }
+}
+
+impl Graph {
+    fn simple_graph(&self, q_edge: &Query<&GEdge>) -> SimpleGraph {
+        let mut nodes: Vec<NodeE> = self.node_edges.keys().copied().collect();
+        nodes.sort_by_key(|node| node.0);
+        let index: HashMap<NodeE, usize> = nodes.iter().enumerate().map(|(i, node)| (*node, i)).collect();
+
+        let mut edges: HashMap<(usize, usize), (EdgeE, i64)> = HashMap::new();
+        for (edge, (start, end)) in self.edge_nodes.iter() {
+            if start == end {
+                continue;
+            }
+            let (a, b) = (index[start], index[end]);
+            let weight = q_edge.get(**edge).map(|e| e.weight).unwrap_or(1) as i64;
+            let entry = edges.entry((a.min(b), a.max(b))).or_insert((*edge, weight));
+            if weight < entry.1 {
+                *entry = (*edge, weight);
+            }
+        }
+
+        let mut adj = vec![Vec::new(); nodes.len()];
+        for &(a, b) in edges.keys() {
+            adj[a].push(b);
+            adj[b].push(a);
+        }
+        for list in adj.iter_mut() {
+            list.sort();
+        }
+
+        SimpleGraph { nodes, adj, edges }
+    }
+
+    /// Find a matching with the given method, ignoring edge direction and loops
+    pub fn matching(&self, method: MatchingMethod, q_edge: &Query<&GEdge>) -> Result<Matching, String> {
+        let g = self.simple_graph(q_edge);
+        let left = g.bipartition();
+
+        let (mates, algorithm) = match (method, &left) {
+            (MatchingMethod::Maximum, Some(left)) => (hopcroft_karp(&g.adj, left), "Hopcroft–Karp"),
+            (MatchingMethod::Maximum, None) => (blossom(&g.adj), "Edmonds' blossom"),
+            (MatchingMethod::Assignment, Some(left)) => (hungarian(&g, left), "Hungarian"),
+            (MatchingMethod::Assignment, None) => {
+                return Err("The assignment problem needs a bipartite graph".into());
+            }
+        };
+
+        let mut edges = Vec::new();
+        let mut weight = 0;
+        for (a, mate) in mates.iter().enumerate() {
+            if let Some(b) = *mate {
+                if a < b {
+                    let (edge, w) = g.edge(a, b);
+                    edges.push(edge);
+                    weight += w;
+                }
+            }
+        }
+
+        let cover = left.as_ref().map(|left| {
+            konig_cover(&g.adj, left, &mates)
+                .into_iter()
+                .map(|i| g.nodes[i])
+                .collect()
+        });
+
+        Ok(Matching {
+            edges,
+            algorithm,
+            cover,
+            weight,
+        })
+    }
+}
+
+/// Maximum bipartite matching, returning each node's mate
+fn hopcroft_karp(adj: &[Vec<usize>], left: &[bool]) -> Vec<Option<usize>> {
+    let n = adj.len();
+    let mut mate: Vec<Option<usize>> = vec![None; n];
+    let mut dist = vec![usize::MAX; n];
+
+    // Layer the free left nodes and everything reachable from them along
+    // alternating paths, returning whether a free right node was reached
+    let bfs = |mate: &[Option<usize>], dist: &mut [usize]| {
+        let mut queue = VecDeque::new();
+        for u in 0..n {
+            if left[u] && mate[u].is_none() {
+                dist[u] = 0;
+                queue.push_back(u);
+            } else {
+                dist[u] = usize::MAX;
+            }
+        }
+
+        let mut found = false;
+        while let Some(u) = queue.pop_front() {
+            for &v in adj[u].iter() {
+                match mate[v] {
+                    None => found = true,
+                    Some(w) if dist[w] == usize::MAX => {
+                        dist[w] = dist[u] + 1;
+                        queue.push_back(w);
+                    }
+                    _ => (),
+                }
+            }
+        }
+        found
+    };
+
+    fn dfs(u: usize, adj: &[Vec<usize>], mate: &mut [Option<usize>], dist: &mut [usize]) -> bool {
+        for &v in adj[u].iter() {
+            let next = match mate[v] {
+                None => true,
+                Some(w) => dist[w] == dist[u] + 1 && dfs(w, adj, mate, dist),
+            };
+            if next {
+                mate[u] = Some(v);
+                mate[v] = Some(u);
+                return true;
+            }
+        }
+        dist[u] = usize::MAX;
+        false
+    }
+
+    while bfs(&mate, &mut dist) {
+        for u in 0..n {
+            if left[u] && mate[u].is_none() {
+                dfs(u, adj, &mut mate, &mut dist);
+            }
+        }
+    }
+    mate
+}
+
+/// König's theorem: starting from the free left nodes, mark everything reachable
+/// along alternating paths. The unmarked left nodes and the marked right nodes
+/// cover every edge.
+fn konig_cover(adj: &[Vec<usize>], left: &[bool], mate: &[Option<usize>]) -> Vec<usize> {
+    let n = adj.len();
+    let mut marked = vec![false; n];
+    let mut stack: Vec<usize> = (0..n).filter(|&u| left[u] && mate[u].is_none()).collect();
+    for &u in stack.iter() {
+        marked[u] = true;
+    }
+
+    while let Some(u) = stack.pop() {
+        for &v in adj[u].iter() {
+            // Left to right along non-matching edges, back along matching ones
+            if marked[v] || mate[u] == Some(v) {
+                continue;
+            }
+            marked[v] = true;
+            if let Some(w) = mate[v] {
+                if !marked[w] {
+                    marked[w] = true;
+                    stack.push(w);
+                }
+            }
+        }
+    }
+
+    (0..n)
+        .filter(|&u| !adj[u].is_empty() && (left[u] != marked[u]))
+        .collect()
+}
+
+/// Maximum matching in a general graph with Edmonds' blossom algorithm,
+/// returning each node's mate
+fn blossom(adj: &[Vec<usize>]) -> Vec<Option<usize>> {
+    let n = adj.len();
+    let mut mate: Vec<Option<usize>> = vec![None; n];
+
+    struct Search<'a> {
+        adj: &'a [Vec<usize>],
+        parent: Vec<Option<usize>>,
+        base: Vec<usize>,
+        used: Vec<bool>,
+        in_blossom: Vec<bool>,
+        queue: VecDeque<usize>,
+    }
+
+    impl Search<'_> {
+        /// The lowest common ancestor of two nodes in the alternating tree
+        fn lca(&self, mate: &[Option<usize>], mut a: usize, mut b: usize) -> usize {
+            let mut seen = vec![false; self.adj.len()];
+            loop {
+                a = self.base[a];
+                seen[a] = true;
+                match mate[a] {
+                    Some(m) => a = self.parent[m].unwrap(),
+                    None => break,
+                }
+            }
+            loop {
+                b = self.base[b];
+                if seen[b] {
+                    return b;
+                }
+                b = self.parent[mate[b].unwrap()].unwrap();
+            }
+        }
+
+        fn mark_path(&mut self, mate: &[Option<usize>], mut v: usize, b: usize, mut child: usize) {
+            while self.base[v] != b {
+                let m = mate[v].unwrap();
+                self.in_blossom[self.base[v]] = true;
+                self.in_blossom[self.base[m]] = true;
+                self.parent[v] = Some(child);
+                child = m;
+                v = self.parent[m].unwrap();
+            }
+        }
+
+        /// Grow an alternating tree from `root`, contracting blossoms as they
+        /// are found, and return the free node an augmenting path reaches
+        fn find_path(&mut self, mate: &[Option<usize>], root: usize) -> Option<usize> {
+            let (adj, n) = (self.adj, self.adj.len());
+            self.used = vec![false; n];
+            self.parent = vec![None; n];
+            self.base = (0..n).collect();
+            self.used[root] = true;
+            self.queue = VecDeque::from([root]);
+
+            while let Some(v) = self.queue.pop_front() {
+                for &to in adj[v].iter() {
+                    if self.base[v] == self.base[to] || mate[v] == Some(to) {
+                        continue;
+                    }
+
+                    if to == root || mate[to].is_some_and(|m| self.parent[m].is_some()) {
+                        // An odd cycle, contract it into its base
+                        let current = self.lca(mate, v, to);
+                        self.in_blossom = vec![false; n];
+                        self.mark_path(mate, v, current, to);
+                        self.mark_path(mate, to, current, v);
+                        for i in 0..n {
+                            if self.in_blossom[self.base[i]] {
+                                self.base[i] = current;
+                                if !self.used[i] {
+                                    self.used[i] = true;
+                                    self.queue.push_back(i);
+                                }
+                            }
+                        }
+                    } else if self.parent[to].is_none() {
+                        self.parent[to] = Some(v);
+                        match mate[to] {
+                            None => return Some(to),
+                            Some(m) => {
+                                self.used[m] = true;
+                                self.queue.push_back(m);
+                            }
+                        }
+                    }
+                }
+            }
+            None
+        }
+    }
+
+    let mut search = Search {
+        adj,
+        parent: Vec::new(),
+        base: Vec::new(),
+        used: Vec::new(),
+        in_blossom: Vec::new(),
+        queue: VecDeque::new(),
+    };
+
+    for root in 0..n {
+        if mate[root].is_some() {
+            continue;
+        }
+        let Some(mut v) = search.find_path(&mate, root) else {
+            continue;
+        };
+
+        // Flip the matching along the augmenting path back to the root
+        loop {
+            let pv = search.parent[v].unwrap();
+            let next = mate[pv];
+            mate[v] = Some(pv);
+            mate[pv] = Some(v);
+            match next {
+                Some(next) => v = next,
+                None => break,
+            }
+        }
+    }
+    mate
+}
+
+/// Minimum weight maximum matching of a bipartite graph, solved as an
+/// assignment problem where missing edges cost more than any real matching
+fn hungarian(g: &SimpleGraph, left: &[bool]) -> Vec<Option<usize>> {
+    let rows: Vec<usize> = (0..g.nodes.len()).filter(|&i| left[i]).collect();
+    let cols: Vec<usize> = (0..g.nodes.len()).filter(|&i| !left[i]).collect();
+    let size = rows.len().max(cols.len());
+    let mut mate = vec![None; g.nodes.len()];
+    if rows.is_empty() || cols.is_empty() {
+        return mate;
+    }
+
+    let missing = 1 + g.edges.values().map(|(_, w)| w.abs()).sum::<i64>() * 2;
+    let cost = |r: usize, c: usize| -> i64 {
+        match (rows.get(r), cols.get(c)) {
+            (Some(&a), Some(&b)) => g.edges.get(&(a.min(b), a.max(b))).map_or(missing, |(_, w)| *w),
+            _ => missing,
+        }
+    };
+
+    // Potentials for rows (u) and columns (v), 1-indexed with column 0 as a
+    // sentinel, following the usual O(n³) formulation
+    let mut u = vec![0i64; size + 1];
+    let mut v = vec![0i64; size + 1];
+    let mut assigned = vec![0usize; size + 1];
+    let mut way = vec![0usize; size + 1];
+    for row in 1..=size {
+        assigned[0] = row;
+        let mut col0 = 0;
+        let mut min = vec![i64::MAX; size + 1];
+        let mut used = vec![false; size + 1];
+        loop {
+            used[col0] = true;
+            let row0 = assigned[col0];
+            let mut delta = i64::MAX;
+            let mut col1 = 0;
+            for col in 1..=size {
+                if used[col] {
+                    continue;
+                }
+                let reduced = cost(row0 - 1, col - 1) - u[row0] - v[col];
+                if reduced < min[col] {
+                    min[col] = reduced;
+                    way[col] = col0;
+                }
+                if min[col] < delta {
+                    delta = min[col];
+                    col1 = col;
+                }
+            }
+            for col in 0..=size {
+                if used[col] {
+                    u[assigned[col]] += delta;
+                    v[col] -= delta;
+                } else {
+                    min[col] -= delta;
+                }
+            }
+            col0 = col1;
+            if assigned[col0] == 0 {
+                break;
+            }
+        }
+        loop {
+            let col1 = way[col0];
+            assigned[col0] = assigned[col1];
+            col0 = col1;
+            if col0 == 0 {
+                break;
+            }
+        }
+    }
+
+    for (col, &row) in assigned.iter().enumerate().skip(1) {
+        if let (Some(&a), Some(&b)) = (rows.get(row - 1), cols.get(col - 1)) {
+            if g.edges.contains_key(&(a.min(b), a.max(b))) {
+                mate[a] = Some(b);
+                mate[b] = Some(a);
+            }
+        }
+    }
+    mate
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy::ecs::system::SystemState;
+
+    use super::*;
+
+    /// Match a graph whose edges all weigh 1, as there's no edge components
+    /// to read the weights from
+    fn matching(n: usize, pairs: &[(usize, usize)], method: MatchingMethod) -> Result<Matching, String> {
+        let (graph, _) = Graph::from_pairs(n, pairs, false);
+        let mut world = World::new();
+        let mut state: SystemState<Query<&GEdge>> = SystemState::new(&mut world);
+        graph.matching(method, &state.get(&world))
+    }
+
+    #[test]
+    fn loops_dont_block_assignment() {
+        let result = matching(4, &[(0, 1), (1, 2), (2, 3), (0, 0)], MatchingMethod::Assignment).unwrap();
+        assert_eq!(result.algorithm, "Hungarian");
+        assert_eq!(result.edges.len(), 2);
+    }
+
+    #[test]
+    fn odd_cycles_need_a_blossom() {
+        // A 5-cycle with a pendant node only has a perfect matching through
+        // the blossom
+        let pairs = [(0, 1), (1, 2), (2, 3), (3, 4), (4, 0), (0, 5)];
+        let result = matching(6, &pairs, MatchingMethod::Maximum).unwrap();
+        assert_eq!(result.algorithm, "Edmonds' blossom");
+        assert_eq!(result.edges.len(), 3);
+        assert!(result.cover.is_none());
+        assert!(matching(6, &pairs, MatchingMethod::Assignment).is_err());
+    }
+
+    #[test]
+    fn konig_cover_matches_the_matching() {
+        // K2,3
+        let pairs = [(0, 2), (0, 3), (0, 4), (1, 2), (1, 3), (1, 4)];
+        let result = matching(5, &pairs, MatchingMethod::Maximum).unwrap();
+        assert_eq!(result.edges.len(), 2);
+        assert_eq!(result.cover.unwrap().len(), 2);
+    }
+
+    #[test]
+    fn assignment_takes_the_lightest_edges() {
+        let edges: HashMap<(usize, usize), (EdgeE, i64)> = [((0, 2), 1), ((0, 3), 5), ((1, 2), 5), ((1, 3), 1)]
+            .into_iter()
+            .enumerate()
+            .map(|(i, (pair, w))| (pair, (EdgeE(Entity::from_raw(i as u32)), w)))
+            .collect();
+        let mut adj = vec![Vec::new(); 4];
+        for &(a, b) in edges.keys() {
+            adj[a].push(b);
+            adj[b].push(a);
+        }
+        let g = SimpleGraph {
+            nodes: (0..4).map(|i| NodeE(Entity::from_raw(10 + i))).collect(),
+            adj,
+            edges,
+        };
+        let left = g.bipartition().unwrap();
+        assert_eq!(hungarian(&g, &left), vec![Some(2), Some(3), Some(0), Some(1)]);
+    }
+}
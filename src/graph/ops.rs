@@ -0,0 +1,339 @@
+use std::collections::HashSet;
+
+use bevy::prelude::*;
+
+use super::template::{GraphTemplate, TemplateEdge, TemplateNode};
+
+static OPERAND_GAP: f32 = 150.0;
+
+fn bounds(t: &GraphTemplate) -> (Vec2, Vec2) {
+    t.nodes.iter().fold(
+        (Vec2::splat(f32::INFINITY), Vec2::splat(f32::NEG_INFINITY)),
+        |(min, max), node| (min.min(node.pos), max.max(node.pos)),
+    )
+}
+
+fn adjacency(t: &GraphTemplate, directed: bool) -> Vec<Vec<bool>> {
+    let mut adj = vec![vec![false; t.nodes.len()]; t.nodes.len()];
+    for edge in t.edges.iter() {
+        adj[edge.start][edge.end] = true;
+        if !directed {
+            adj[edge.end][edge.start] = true;
+        }
+    }
+    adj
+}
+
+fn copy_nodes(t: &GraphTemplate) -> GraphTemplate {
+    GraphTemplate {
+        nodes: t.nodes.clone(),
+        ..Default::default()
+    }
+}
+
+/// The graph on the same vertices whose edges are exactly the non-edges of `t`
+pub fn complement(t: &GraphTemplate, directed: bool) -> GraphTemplate {
+    let adj = adjacency(t, directed);
+    let mut result = copy_nodes(t);
+    for (i, row) in adj.iter().enumerate() {
+        let others = if directed { 0 } else { i + 1 };
+        for (j, &adjacent) in row.iter().enumerate().skip(others) {
+            if i != j && !adjacent {
+                result.add_edge(i, j);
+            }
+        }
+    }
+    result
+}
+
+/// The graph with a vertex for every edge of `t`, where two vertices are
+/// adjacent if their edges share an endpoint (or, for directed graphs, if the
+/// first edge ends where the second starts)
+pub fn line_graph(t: &GraphTemplate, directed: bool) -> GraphTemplate {
+    let mut result = GraphTemplate::default();
+    for edge in t.edges.iter() {
+        let (a, b) = (&t.nodes[edge.start], &t.nodes[edge.end]);
+        result.nodes.push(TemplateNode {
+            pos: a.pos.lerp(b.pos, 0.5),
+            label: Some(format!("{}{}", label(a, edge.start), label(b, edge.end))),
+            ..Default::default()
+        });
+    }
+
+    for (i, e) in t.edges.iter().enumerate() {
+        for (j, f) in t.edges.iter().enumerate() {
+            let adjacent = if directed {
+                i != j && e.end == f.start
+            } else {
+                i < j && (e.start == f.start || e.start == f.end || e.end == f.start || e.end == f.end)
+            };
+            if adjacent {
+                result.add_edge(i, j);
+            }
+        }
+    }
+    result
+}
+
+fn label(node: &TemplateNode, index: usize) -> String {
+    node.label.clone().unwrap_or_else(|| format!("v{index}"))
+}
+
+/// Reverse the direction of every edge
+pub fn transpose(t: &GraphTemplate) -> GraphTemplate {
+    let mut result = t.clone();
+    for edge in result.edges.iter_mut() {
+        std::mem::swap(&mut edge.start, &mut edge.end);
+    }
+    result
+}
+
+/// Split every edge in two by placing a new vertex on its midpoint
+pub fn subdivide(t: &GraphTemplate) -> GraphTemplate {
+    let mut result = copy_nodes(t);
+    for edge in t.edges.iter() {
+        if edge.start == edge.end {
+            // Subdividing a loop gives a pair of parallel edges
+            let pos = t.nodes[edge.start].pos + Vec2::new(0.0, 80.0);
+            let mid = result.add_node(pos);
+            result.add_edge(edge.start, mid);
+            result.add_edge(mid, edge.start);
+            continue;
+        }
+
+        let mid = result.add_node(t.nodes[edge.start].pos.lerp(t.nodes[edge.end].pos, 0.5));
+        result.add_edge(edge.start, mid);
+        result.add_edge(mid, edge.end);
+    }
+    result
+}
+
+/// Contract the edge at the given index, merging its endpoints into a single
+/// vertex placed between them. Edges that would become loops or duplicates
+/// are dropped so a simple graph stays simple. Edges only count as duplicates
+/// of ones going the same way when the graph is directed.
+pub fn contract(t: &GraphTemplate, edge: usize, directed: bool) -> GraphTemplate {
+    let (keep, gone) = (t.edges[edge].start, t.edges[edge].end);
+    if keep == gone {
+        // Contracting a loop just removes it
+        let mut result = t.clone();
+        result.edges.remove(edge);
+        return result;
+    }
+
+    let mut result = GraphTemplate::default();
+    let mut index = vec![0; t.nodes.len()];
+    for (i, node) in t.nodes.iter().enumerate() {
+        if i == gone {
+            continue;
+        }
+        index[i] = result.nodes.len();
+        let mut node = node.clone();
+        if i == keep {
+            node.pos = node.pos.lerp(t.nodes[gone].pos, 0.5);
+        }
+        result.nodes.push(node);
+    }
+    index[gone] = index[keep];
+
+    // Only edges moved over from the removed vertex can duplicate one that was
+    // already there, parallel edges from before the contraction are kept
+    let key = |a: usize, b: usize| if directed { (a, b) } else { (a.min(b), a.max(b)) };
+    let moved = |e: &TemplateEdge| e.start == gone || e.end == gone;
+    let existing: HashSet<(usize, usize)> = t
+        .edges
+        .iter()
+        .filter(|e| !moved(e))
+        .map(|e| key(index[e.start], index[e.end]))
+        .collect();
+    for (i, e) in t.edges.iter().enumerate() {
+        let (start, end) = (index[e.start], index[e.end]);
+        if i == edge || (e.start != e.end && start == end) {
+            continue;
+        }
+        if moved(e) && existing.contains(&key(start, end)) {
+            continue;
+        }
+        let mut e = e.clone();
+        e.start = start;
+        e.end = end;
+        result.edges.push(e);
+    }
+    result
+}
+
+/// Append a copy of `b` to `a`, placed to the right of it so the two don't
+/// overlap. Returns the index of `b`'s first node in the result.
+fn append(a: &GraphTemplate, b: &GraphTemplate) -> (GraphTemplate, usize) {
+    let mut result = a.clone();
+    let first = result.nodes.len();
+    let offset = if a.nodes.is_empty() || b.nodes.is_empty() {
+        Vec2::ZERO
+    } else {
+        let ((a_min, a_max), (b_min, _)) = (bounds(a), bounds(b));
+        Vec2::new(a_max.x + OPERAND_GAP - b_min.x, a_min.y - b_min.y)
+    };
+
+    result.nodes.extend(b.nodes.iter().map(|node| TemplateNode {
+        pos: node.pos + offset,
+        ..node.clone()
+    }));
+    result.edges.extend(b.edges.iter().map(|edge| {
+        let mut edge = edge.clone();
+        edge.start += first;
+        edge.end += first;
+        edge
+    }));
+    (result, first)
+}
+
+/// The disjoint union of two graphs
+pub fn union(a: &GraphTemplate, b: &GraphTemplate) -> GraphTemplate {
+    append(a, b).0
+}
+
+/// The disjoint union of two graphs with every vertex of `a` joined to every
+/// vertex of `b`
+pub fn join(a: &GraphTemplate, b: &GraphTemplate) -> GraphTemplate {
+    let (mut result, first) = append(a, b);
+    for i in 0..first {
+        for j in first..result.nodes.len() {
+            result.add_edge(i, j);
+        }
+    }
+    result
+}
+
+#[derive(Copy, Clone, PartialEq, Eq)]
+pub enum Product {
+    Cartesian,
+    Tensor,
+}
+
+/// The Cartesian (□) or tensor (×) product of two graphs. The layout places a
+/// copy of `b` at every vertex of `a`, scaling `a` up so the copies don't
+/// overlap.
+pub fn product(a: &GraphTemplate, b: &GraphTemplate, kind: Product, directed: bool) -> GraphTemplate {
+    let mut result = GraphTemplate::default();
+    if a.nodes.is_empty() || b.nodes.is_empty() {
+        return result;
+    }
+
+    let (b_min, b_max) = bounds(b);
+    let b_center = b_min.lerp(b_max, 0.5);
+    let mut closest = f32::INFINITY;
+    for (i, u) in a.nodes.iter().enumerate() {
+        for v in a.nodes.iter().skip(i + 1) {
+            closest = closest.min(u.pos.distance(v.pos));
+        }
+    }
+    let scale = if closest.is_finite() && closest > 0.0 {
+        ((b_max - b_min).max_element() + OPERAND_GAP) / closest
+    } else {
+        1.0
+    };
+
+    let index = |u: usize, v: usize| u * b.nodes.len() + v;
+    for (i, u) in a.nodes.iter().enumerate() {
+        for (j, v) in b.nodes.iter().enumerate() {
+            result.nodes.push(TemplateNode {
+                pos: u.pos * scale + (v.pos - b_center),
+                label: Some(format!("({},{})", label(u, i), label(v, j))),
+                style: u.style.clone(),
+            });
+        }
+    }
+
+    match kind {
+        Product::Cartesian => {
+            for e in a.edges.iter() {
+                for v in 0..b.nodes.len() {
+                    result.add_edge(index(e.start, v), index(e.end, v));
+                }
+            }
+            for f in b.edges.iter() {
+                for u in 0..a.nodes.len() {
+                    result.add_edge(index(u, f.start), index(u, f.end));
+                }
+            }
+        }
+        Product::Tensor => {
+            for e in a.edges.iter() {
+                for f in b.edges.iter() {
+                    let (s, t) = (index(e.start, f.start), index(e.end, f.end));
+                    if directed || !result.has_edge(s, t) {
+                        result.add_edge(s, t);
+                    }
+                    if !directed {
+                        let (s, t) = (index(e.start, f.end), index(e.end, f.start));
+                        if !result.has_edge(s, t) {
+                            result.add_edge(s, t);
+                        }
+                    }
+                }
+            }
+        }
+    }
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn triangle(edges: &[(usize, usize)]) -> GraphTemplate {
+        let mut t = GraphTemplate::default();
+        for _ in 0..3 {
+            t.add_node(Vec2::ZERO);
+        }
+        for &(start, end) in edges {
+            t.add_edge(start, end);
+        }
+        t
+    }
+
+    fn edge_set(t: &GraphTemplate) -> Vec<(usize, usize)> {
+        let mut edges: Vec<_> = t.edges.iter().map(|e| (e.start, e.end)).collect();
+        edges.sort();
+        edges
+    }
+
+    #[test]
+    fn contract_triangle_in_any_edge_order() {
+        let edges = [(0, 1), (0, 2), (1, 2)];
+        let orders = [[0, 1, 2], [0, 2, 1], [1, 0, 2], [1, 2, 0], [2, 0, 1], [2, 1, 0]];
+        for order in orders {
+            let t = triangle(&order.map(|i| edges[i]));
+            let ab = t.edges.iter().position(|e| (e.start, e.end) == (0, 1)).unwrap();
+            let result = contract(&t, ab, false);
+            assert_eq!(result.nodes.len(), 2);
+            assert_eq!(edge_set(&result), vec![(0, 1)], "edge order {order:?}");
+        }
+    }
+
+    #[test]
+    fn contract_keeps_opposite_directed_edges() {
+        // a -> c and c -> b, so contracting a -> b leaves both a -> c and c -> a
+        let t = triangle(&[(0, 2), (2, 1), (0, 1)]);
+        let result = contract(&t, 2, true);
+        assert_eq!(edge_set(&result), vec![(0, 1), (1, 0)]);
+
+        let result = contract(&t, 2, false);
+        assert_eq!(result.edges.len(), 1);
+    }
+
+    #[test]
+    fn contract_drops_parallel_edges_to_loops() {
+        let t = triangle(&[(0, 1), (1, 0), (1, 2)]);
+        let result = contract(&t, 0, false);
+        assert_eq!(edge_set(&result), vec![(0, 1)]);
+    }
+
+    #[test]
+    fn contract_keeps_parallel_edges_from_before() {
+        // a has two edges to c already, b's edge to c is the only duplicate
+        let t = triangle(&[(0, 1), (0, 2), (0, 2), (1, 2)]);
+        let result = contract(&t, 0, false);
+        assert_eq!(edge_set(&result), vec![(0, 1), (0, 1)]);
+    }
+}
@@ -0,0 +1,643 @@
+use std::collections::{HashMap, HashSet, VecDeque};
+
+use bevy::prelude::*;
+
+use super::{connectivity::blocks, EdgeE, Graph, NodeE};
+
+/// Graphs with more nodes than this are only tested, not laid out or searched
+/// for a Kuratowski subgraph
+pub static PLANARITY_LIMIT: usize = 100;
+/// Quadratic edge curves are split into this many straight segments when
+/// counting crossings
+static CURVE_SEGMENTS: usize = 16;
+
+#[derive(Copy, Clone, PartialEq, Eq)]
+pub enum KuratowskiKind {
+    K5,
+    K33,
+}
+
+impl core::fmt::Display for KuratowskiKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            KuratowskiKind::K5 => write!(f, "K₅"),
+            KuratowskiKind::K33 => write!(f, "K₃,₃"),
+        }
+    }
+}
+
+/// A subdivision of K₅ or K₃,₃, which proves a graph isn't planar
+pub struct Kuratowski {
+    pub kind: KuratowskiKind,
+    pub edges: Vec<EdgeE>,
+    /// The nodes of the original K₅ or K₃,₃, the rest only subdivide edges
+    pub branch: Vec<NodeE>,
+}
+
+fn key(a: usize, b: usize) -> (usize, usize) {
+    (a.min(b), a.max(b))
+}
+
+/// Embed a biconnected simple graph in the plane with the algorithm of
+/// Demoucron, Malgrange and Pertuiset, returning the vertices around each
+/// face or `None` if the graph isn't planar.
+///
+/// Starting from a cycle, the pieces of the graph not yet embedded are split
+/// into fragments: single edges between embedded vertices, or components of
+/// the unembedded vertices together with their attachments. A fragment fits
+/// in a face containing all its attachments. Fragments that fit in only one
+/// face are embedded first, one path at a time, which never rules out an
+/// embedding that exists.
+fn embed(n: usize, edges: &[(usize, usize)]) -> Option<Vec<Vec<usize>>> {
+    let mut adj = vec![Vec::new(); n];
+    for &(a, b) in edges.iter() {
+        adj[a].push(b);
+        adj[b].push(a);
+    }
+
+    // Any edge lies on a cycle, close the first one with a path around it
+    let &(a, b) = edges.first()?;
+    let mut prev = vec![None; n];
+    prev[a] = Some(a);
+    let mut queue = VecDeque::from([a]);
+    while let Some(u) = queue.pop_front() {
+        for &v in adj[u].iter() {
+            if prev[v].is_none() && !(u == a && v == b) {
+                prev[v] = Some(u);
+                queue.push_back(v);
+            }
+        }
+    }
+    let mut cycle = vec![b];
+    while let Some(&u) = cycle.last().filter(|u| **u != a) {
+        cycle.push(prev[u]?);
+    }
+
+    let mut embedded = vec![false; n];
+    let mut done: HashSet<(usize, usize)> = HashSet::new();
+    for (i, &u) in cycle.iter().enumerate() {
+        embedded[u] = true;
+        done.insert(key(u, cycle[(i + 1) % cycle.len()]));
+    }
+    let mut faces = vec![cycle.clone(), cycle];
+
+    while done.len() < edges.len() {
+        // Each fragment is a list of attachments and, for the larger kind, the
+        // unembedded vertices inside it
+        let mut fragments: Vec<(Vec<usize>, Vec<usize>)> = edges
+            .iter()
+            .filter(|(a, b)| embedded[*a] && embedded[*b] && !done.contains(&key(*a, *b)))
+            .map(|&(a, b)| (vec![a, b], Vec::new()))
+            .collect();
+        let mut seen = vec![false; n];
+        for start in (0..n).filter(|u| !embedded[*u]) {
+            if seen[start] {
+                continue;
+            }
+            seen[start] = true;
+            let (mut inside, mut attachments) = (vec![start], HashSet::new());
+            let mut i = 0;
+            while let Some(&u) = inside.get(i) {
+                i += 1;
+                for &v in adj[u].iter() {
+                    if embedded[v] {
+                        attachments.insert(v);
+                    } else if !seen[v] {
+                        seen[v] = true;
+                        inside.push(v);
+                    }
+                }
+            }
+            fragments.push((attachments.into_iter().collect(), inside));
+        }
+
+        let face_sets: Vec<HashSet<usize>> = faces.iter().map(|face| face.iter().copied().collect()).collect();
+        let mut choice = None;
+        for (i, (attachments, _)) in fragments.iter().enumerate() {
+            let admissible: Vec<usize> = (0..faces.len())
+                .filter(|f| attachments.iter().all(|a| face_sets[*f].contains(a)))
+                .collect();
+            match admissible.len() {
+                0 => return None,
+                1 => {
+                    choice = Some((i, admissible[0]));
+                    break;
+                }
+                _ if choice.is_none() => choice = Some((i, admissible[0])),
+                _ => (),
+            }
+        }
+        let (fragment, face) = choice?;
+        let (attachments, inside) = &fragments[fragment];
+
+        // Embed a path through the fragment between two of its attachments
+        let (start, end) = (attachments[0], attachments[1]);
+        let mut path = vec![start];
+        if !inside.is_empty() {
+            let inside: HashSet<usize> = inside.iter().copied().collect();
+            let mut prev: HashMap<usize, usize> = HashMap::new();
+            let first = *adj[start].iter().find(|v| inside.contains(v))?;
+            prev.insert(first, start);
+            let mut queue = VecDeque::from([first]);
+            let mut last = None;
+            'search: while let Some(u) = queue.pop_front() {
+                for &v in adj[u].iter() {
+                    if embedded[v] && v != start && attachments.contains(&v) {
+                        last = Some((u, v));
+                        break 'search;
+                    }
+                    if inside.contains(&v) && !prev.contains_key(&v) {
+                        prev.insert(v, u);
+                        queue.push_back(v);
+                    }
+                }
+            }
+            let (mut u, end) = last?;
+            let mut interior = vec![u];
+            while prev[&u] != start {
+                u = prev[&u];
+                interior.push(u);
+            }
+            interior.reverse();
+            path.extend(interior);
+            path.push(end);
+        } else {
+            path.push(end);
+        }
+
+        for pair in path.windows(2) {
+            done.insert(key(pair[0], pair[1]));
+        }
+        for &u in path.iter() {
+            embedded[u] = true;
+        }
+
+        // Split the face along the path
+        let face = faces.swap_remove(face);
+        let i = face.iter().position(|u| *u == path[0]).unwrap();
+        let j = face.iter().position(|u| u == path.last().unwrap()).unwrap();
+        let rotated: Vec<usize> = face[i..].iter().chain(face[..i].iter()).copied().collect();
+        let j = (j + face.len() - i) % face.len();
+        let interior = &path[1..path.len() - 1];
+
+        let mut one = rotated[..=j].to_vec();
+        one.extend(interior.iter().rev());
+        let mut other = rotated[j..].to_vec();
+        other.push(rotated[0]);
+        other.extend(interior.iter());
+        faces.push(one);
+        faces.push(other);
+    }
+    Some(faces)
+}
+
+/// Remove loops and parallel edges
+fn simplify(edges: &[(usize, usize)]) -> Vec<(usize, usize)> {
+    let mut seen = HashSet::new();
+    edges
+        .iter()
+        .filter(|(a, b)| a != b && seen.insert(key(*a, *b)))
+        .copied()
+        .collect()
+}
+
+/// Whether a graph over node indices can be drawn without edge crossings,
+/// testing each biconnected block on its own
+pub fn is_planar(n: usize, edges: &[(usize, usize)]) -> bool {
+    let edges = simplify(edges);
+    if n >= 3 && edges.len() > 3 * n - 6 {
+        return false;
+    }
+
+    blocks(n, &edges).components.iter().filter(|block| block.len() > 2).all(|block| {
+        let mut index: HashMap<usize, usize> = HashMap::new();
+        let local: Vec<(usize, usize)> = block
+            .iter()
+            .map(|i| {
+                let (a, b) = edges[*i];
+                let next = index.len();
+                let a = *index.entry(a).or_insert(next);
+                let next = index.len();
+                (a, *index.entry(b).or_insert(next))
+            })
+            .collect();
+        embed(index.len(), &local).is_some()
+    })
+}
+
+/// Find a subdivision of K₅ or K₃,₃ in a non-planar simple graph by deleting
+/// every edge the graph stays non-planar without. Returns the indices of the
+/// remaining edges and the branch vertices.
+fn kuratowski(n: usize, edges: &[(usize, usize)]) -> Option<(KuratowskiKind, Vec<usize>, Vec<usize>)> {
+    if is_planar(n, edges) {
+        return None;
+    }
+
+    let mut kept: Vec<usize> = (0..edges.len()).collect();
+    let mut i = 0;
+    while i < kept.len() {
+        let without: Vec<(usize, usize)> = kept
+            .iter()
+            .enumerate()
+            .filter(|(j, _)| *j != i)
+            .map(|(_, e)| edges[*e])
+            .collect();
+        if is_planar(n, &without) {
+            i += 1;
+        } else {
+            kept.remove(i);
+        }
+    }
+
+    let mut degree = vec![0; n];
+    for e in kept.iter() {
+        degree[edges[*e].0] += 1;
+        degree[edges[*e].1] += 1;
+    }
+    let branch: Vec<usize> = (0..n).filter(|u| degree[*u] > 2).collect();
+    let kind = if branch.len() == 5 { KuratowskiKind::K5 } else { KuratowskiKind::K33 };
+    Some((kind, kept, branch))
+}
+
+/// Add edges to a planar simple graph until it's biconnected, keeping it
+/// planar. Separate components can always be joined at any vertex, and at a
+/// cut vertex two neighbours in different blocks that are next to each other
+/// around it can always be joined.
+fn make_biconnected(n: usize, edges: &mut Vec<(usize, usize)>) {
+    let mut component: Vec<Option<usize>> = vec![None; n];
+    let mut adj = vec![Vec::new(); n];
+    for &(a, b) in edges.iter() {
+        adj[a].push(b);
+        adj[b].push(a);
+    }
+    let mut roots = Vec::new();
+    for root in 0..n {
+        if component[root].is_some() {
+            continue;
+        }
+        component[root] = Some(roots.len());
+        let mut stack = vec![root];
+        while let Some(u) = stack.pop() {
+            for &v in adj[u].iter() {
+                if component[v].is_none() {
+                    component[v] = Some(roots.len());
+                    stack.push(v);
+                }
+            }
+        }
+        roots.push(root);
+    }
+    for pair in roots.windows(2) {
+        edges.push((pair[0], pair[1]));
+    }
+
+    loop {
+        let blocks = blocks(n, edges);
+        let Some(&cut) = blocks.cut_vertices.first() else {
+            return;
+        };
+        let mut block_of = vec![0; edges.len()];
+        for (i, block) in blocks.components.iter().enumerate() {
+            for e in block.iter() {
+                block_of[*e] = i;
+            }
+        }
+        let around: Vec<(usize, usize)> = edges
+            .iter()
+            .enumerate()
+            .filter_map(|(i, &(a, b))| match (a == cut, b == cut) {
+                (true, _) => Some((b, block_of[i])),
+                (_, true) => Some((a, block_of[i])),
+                _ => None,
+            })
+            .collect();
+
+        let mut added = false;
+        'pairs: for (i, &(u, bu)) in around.iter().enumerate() {
+            for &(w, bw) in around[i + 1..].iter() {
+                if bu == bw {
+                    continue;
+                }
+                edges.push((u, w));
+                if is_planar(n, edges) {
+                    added = true;
+                    break 'pairs;
+                }
+                edges.pop();
+            }
+        }
+        if !added {
+            return;
+        }
+    }
+}
+
+/// Order the vertices of a triangulation so each one after the first two is
+/// attached to a contiguous stretch of the outer boundary of those before it,
+/// ending with the third vertex of the outer triangle. Works backwards,
+/// peeling off a boundary vertex that no boundary chord touches.
+fn canonical_order(n: usize, triangles: &[[usize; 3]]) -> Option<Vec<usize>> {
+    // The neighbours of each vertex in order around it, from the triangles
+    // it's part of
+    let mut link: Vec<HashMap<usize, Vec<usize>>> = vec![HashMap::new(); n];
+    for &[a, b, c] in triangles.iter() {
+        for (u, v, w) in [(a, b, c), (b, c, a), (c, a, b)] {
+            link[u].entry(v).or_default().push(w);
+            link[u].entry(w).or_default().push(v);
+        }
+    }
+    let around: Vec<Vec<usize>> = link
+        .iter()
+        .map(|link| {
+            let Some(&first) = link.keys().min() else {
+                return Vec::new();
+            };
+            let mut cycle = vec![first];
+            while cycle.len() < link.len() {
+                let last = cycle[cycle.len() - 1];
+                let before = cycle.len().checked_sub(2).map(|i| cycle[i]);
+                match link[&last].iter().find(|v| Some(**v) != before && !cycle.contains(v)) {
+                    Some(&next) => cycle.push(next),
+                    None => break,
+                }
+            }
+            cycle
+        })
+        .collect();
+
+    let [first, second, last] = triangles[0];
+    let mut removed = vec![false; n];
+    let mut order = Vec::with_capacity(n);
+    let mut contour = vec![first, last, second];
+    while contour.len() > 2 {
+        let position: HashMap<usize, usize> = contour.iter().enumerate().map(|(i, u)| (*u, i)).collect();
+        let i = (1..contour.len() - 1).find(|&i| {
+            around[contour[i]]
+                .iter()
+                .all(|w| position.get(w).is_none_or(|j| j.abs_diff(i) <= 1))
+        })?;
+        let v = contour[i];
+        removed[v] = true;
+        order.push(v);
+
+        // The neighbours of v between its two neighbours on the boundary take
+        // its place, going the way round that doesn't pass through vertices
+        // already removed
+        let cycle = &around[v];
+        let k = cycle.len();
+        let from = cycle.iter().position(|u| *u == contour[i - 1])?;
+        let to = cycle.iter().position(|u| *u == contour[i + 1])?;
+        let arcs = [
+            (1..(to + k - from) % k).map(|s| cycle[(from + s) % k]).collect::<Vec<_>>(),
+            (1..(from + k - to) % k).rev().map(|s| cycle[(to + s) % k]).collect::<Vec<_>>(),
+        ];
+        let arc = arcs
+            .into_iter()
+            .filter(|arc| arc.iter().all(|u| !removed[*u]))
+            .max_by_key(Vec::len)?;
+        contour.splice(i..=i, arc);
+    }
+    order.extend([second, first]);
+    order.reverse();
+    Some(order)
+}
+
+/// A straight line drawing of a planar graph without crossings, using the
+/// shift method of de Fraysseix, Pach and Pollack. The graph is triangulated,
+/// then vertices are added in canonical order above the boundary of the
+/// drawing so far, shifting parts of it sideways to make room.
+fn layout(n: usize, edges: &[(usize, usize)]) -> Option<Vec<Vec2>> {
+    let corners = [Vec2::new(0.0, 1.0), Vec2::new(-0.866, -0.5), Vec2::new(0.866, -0.5)];
+    if n <= 3 {
+        return Some(corners[..n].to_vec());
+    }
+    let mut edges = simplify(edges);
+    if !is_planar(n, &edges) {
+        return None;
+    }
+
+    make_biconnected(n, &mut edges);
+    let mut faces = embed(n, &edges)?;
+    let mut adjacent: HashSet<(usize, usize)> = edges.iter().map(|(a, b)| key(*a, *b)).collect();
+
+    // Split faces into triangles. Of the two diagonals across any four
+    // consecutive vertices at least one is missing, or they would cross.
+    let mut triangles = Vec::new();
+    while let Some(mut face) = faces.pop() {
+        while face.len() > 3 {
+            let k = face.len();
+            let i = (0..k).find(|i| !adjacent.contains(&key(face[*i], face[(i + 2) % k])))?;
+            let (a, b) = (face[i], face[(i + 2) % k]);
+            adjacent.insert(key(a, b));
+            triangles.push([a, face[(i + 1) % k], b]);
+            face.remove((i + 1) % k);
+        }
+        triangles.push([face[0], face[1], face[2]]);
+    }
+
+    let order = canonical_order(n, &triangles)?;
+    let mut position = vec![(0i64, 0i64); n];
+    // The vertices that move along with each boundary vertex
+    let mut dependents: Vec<Vec<usize>> = (0..n).map(|u| vec![u]).collect();
+    position[order[0]] = (0, 0);
+    position[order[1]] = (2, 0);
+    position[order[2]] = (1, 1);
+    let mut contour = vec![order[0], order[2], order[1]];
+
+    for &v in order[3..].iter() {
+        let touching: Vec<usize> = (0..contour.len())
+            .filter(|i| adjacent.contains(&key(v, contour[*i])))
+            .collect();
+        let (p, q) = (*touching.first()?, *touching.last()?);
+
+        for (i, &w) in contour.iter().enumerate().skip(p + 1) {
+            let shift = if i < q { 1 } else { 2 };
+            for &u in dependents[w].iter() {
+                position[u].0 += shift;
+            }
+        }
+
+        // Where the lines of slope 1 and -1 through the ends of the stretch meet
+        let ((xp, yp), (xq, yq)) = (position[contour[p]], position[contour[q]]);
+        position[v] = ((xp + xq + yq - yp) / 2, (xq - xp + yp + yq) / 2);
+        let covered: Vec<usize> = contour.splice(p + 1..q, [v]).collect();
+        for w in covered {
+            let moved = std::mem::take(&mut dependents[w]);
+            dependents[v].extend(moved);
+        }
+    }
+
+    // Fit the grid into the unit circle
+    let width = (2 * n - 4) as f32;
+    Some(
+        position
+            .iter()
+            .map(|(x, y)| Vec2::new(*x as f32 / width * 1.732 - 0.866, *y as f32 / width * 1.732 - 0.5))
+            .collect(),
+    )
+}
+
+/// A quadratic Bézier curve, the shape of a drawn edge
+pub struct Curve {
+    pub start: Vec2,
+    pub control: Vec2,
+    pub end: Vec2,
+}
+
+impl Curve {
+    /// The curve split into straight segments, for counting crossings
+    pub fn polyline(&self) -> Vec<Vec2> {
+        (0..=CURVE_SEGMENTS)
+            .map(|i| {
+                // Written out so the ends land exactly on the nodes
+                let t = i as f32 / CURVE_SEGMENTS as f32;
+                (1.0 - t) * (1.0 - t) * self.start + 2.0 * t * (1.0 - t) * self.control + t * t * self.end
+            })
+            .collect()
+    }
+}
+
+/// Whether two segments cross at a point inside both of them. Segments that
+/// only touch, like two edges meeting at a shared node, don't count.
+fn segments_cross(a: Vec2, b: Vec2, c: Vec2, d: Vec2) -> bool {
+    let orient = |p: Vec2, q: Vec2, r: Vec2| (q - p).perp_dot(r - p);
+    orient(a, b, c) * orient(a, b, d) < 0.0 && orient(c, d, a) * orient(c, d, b) < 0.0
+}
+
+/// Every point where two edges cross, as the pair of edges involved. Each
+/// edge is given as the points of the straight segments it's drawn with, or
+/// close enough to them for curves. Two edges can cross more than once.
+pub fn crossings(lines: &[Vec<Vec2>]) -> Vec<(usize, usize)> {
+    let bounds: Vec<(Vec2, Vec2)> = lines
+        .iter()
+        .map(|line| line.iter().fold((line[0], line[0]), |(min, max), p| (min.min(*p), max.max(*p))))
+        .collect();
+
+    let mut found = Vec::new();
+    for i in 0..lines.len() {
+        for j in i + 1..lines.len() {
+            let ((min_i, max_i), (min_j, max_j)) = (bounds[i], bounds[j]);
+            if min_i.cmpgt(max_j).any() || min_j.cmpgt(max_i).any() {
+                continue;
+            }
+            for s in lines[i].windows(2) {
+                for t in lines[j].windows(2) {
+                    if segments_cross(s[0], s[1], t[0], t[1]) {
+                        found.push((i, j));
+                    }
+                }
+            }
+        }
+    }
+    found
+}
+
+impl Graph {
+    /// The graph as simple undirected edges over node indices, along with one
+    /// edge entity for each
+    fn simple_edges(&self) -> (Vec<NodeE>, Vec<(usize, usize)>, Vec<EdgeE>) {
+        let mut nodes: Vec<NodeE> = self.node_edges.keys().copied().collect();
+        nodes.sort_by_key(|node| node.0);
+        let index: HashMap<NodeE, usize> = nodes.iter().enumerate().map(|(i, node)| (*node, i)).collect();
+
+        let mut edges: Vec<EdgeE> = self.edge_nodes.keys().copied().collect();
+        edges.sort_by_key(|edge| edge.0);
+        let mut seen = HashSet::new();
+        let (pairs, entities): (Vec<_>, Vec<_>) = edges
+            .iter()
+            .filter_map(|edge| {
+                let (start, end) = self.edge_nodes[edge];
+                let (a, b) = (index[&start], index[&end]);
+                (a != b && seen.insert(key(a, b))).then_some(((a, b), *edge))
+            })
+            .unzip();
+        (nodes, pairs, entities)
+    }
+
+    /// Whether the graph can be drawn without edge crossings, ignoring edge
+    /// direction
+    pub fn is_planar(&self) -> bool {
+        let (nodes, edges, _) = self.simple_edges();
+        is_planar(nodes.len(), &edges)
+    }
+
+    /// A subdivision of K₅ or K₃,₃ in the graph, if it isn't planar
+    pub fn kuratowski(&self) -> Option<Kuratowski> {
+        let (nodes, edges, entities) = self.simple_edges();
+        let (kind, kept, branch) = kuratowski(nodes.len(), &edges)?;
+        Some(Kuratowski {
+            kind,
+            edges: kept.iter().map(|e| entities[*e]).collect(),
+            branch: branch.iter().map(|u| nodes[*u]).collect(),
+        })
+    }
+
+    /// Positions for a straight line drawing without crossings, inside the
+    /// triangle with corners on the unit circle
+    pub fn planar_layout(&self) -> Option<HashMap<NodeE, Vec2>> {
+        let (nodes, edges, _) = self.simple_edges();
+        let positions = layout(nodes.len(), &edges)?;
+        Some(nodes.into_iter().zip(positions).collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn complete(n: usize) -> Vec<(usize, usize)> {
+        (0..n).flat_map(|a| (a + 1..n).map(move |b| (a, b))).collect()
+    }
+
+    fn k33() -> Vec<(usize, usize)> {
+        (0..3).flat_map(|a| (3..6).map(move |b| (a, b))).collect()
+    }
+
+    #[test]
+    fn k4_is_drawn_without_crossings() {
+        let edges = complete(4);
+        assert!(is_planar(4, &edges));
+        assert!(kuratowski(4, &edges).is_none());
+
+        let positions = layout(4, &edges).unwrap();
+        let lines: Vec<Vec<Vec2>> = edges.iter().map(|(a, b)| vec![positions[*a], positions[*b]]).collect();
+        assert!(crossings(&lines).is_empty());
+    }
+
+    #[test]
+    fn k5_is_not_planar() {
+        let edges = complete(5);
+        assert!(!is_planar(5, &edges));
+        assert!(layout(5, &edges).is_none());
+
+        let (kind, kept, branch) = kuratowski(5, &edges).unwrap();
+        assert!(kind == KuratowskiKind::K5);
+        assert_eq!(kept.len(), 10);
+        assert_eq!(branch, vec![0, 1, 2, 3, 4]);
+    }
+
+    #[test]
+    fn k33_is_not_planar() {
+        let edges = k33();
+        assert!(!is_planar(6, &edges));
+
+        let (kind, kept, branch) = kuratowski(6, &edges).unwrap();
+        assert!(kind == KuratowskiKind::K33);
+        assert_eq!(kept.len(), 9);
+        assert_eq!(branch.len(), 6);
+    }
+
+    #[test]
+    fn subdivided_k33_is_found() {
+        // Split the edge 0-3 with a new node 6 and add a chord between two
+        // nodes on the same side, which the subdivision doesn't need
+        let mut edges: Vec<(usize, usize)> = k33().into_iter().filter(|e| *e != (0, 3)).collect();
+        edges.extend([(0, 6), (6, 3), (0, 1)]);
+        assert!(!is_planar(7, &edges));
+
+        let (kind, kept, branch) = kuratowski(7, &edges).unwrap();
+        assert!(kind == KuratowskiKind::K33);
+        assert_eq!(kept.len(), 10);
+        assert_eq!(branch, vec![0, 1, 2, 3, 4, 5]);
+    }
+}
@@ -10,12 +10,33 @@ use crate::{materials, ui};
 
 use super::event;
 use super::event::*;
+use super::shape::{self, NodeShape};
 use super::Graph;
 
 #[derive(Resource, Default, Deref)]
 pub struct ImageCache(HashMap<String, Handle<Image>>);
 
-#[derive(Resource, Default, Deref)]
+impl ImageCache {
+    /// The texture to draw a node with, falling back to the circle for images
+    /// that failed to load
+    pub fn texture(&self, shape: &NodeShape, selected: bool) -> Handle<Image> {
+        self.get(&shape.texture_key(selected))
+            .or_else(|| self.get(if selected { "node-sel" } else { "node" }))
+            .unwrap()
+            .clone()
+    }
+
+    /// Load an image file for nodes to use, unless it's already loaded
+    pub fn insert_file(&mut self, path: &str, images: &mut Assets<Image>) -> Result<(), String> {
+        if !self.contains_key(path) {
+            let image = shape::load_image_file(path)?;
+            self.0.insert(path.to_string(), images.add(image));
+        }
+        Ok(())
+    }
+}
+
+#[derive(Resource, Default, Deref, DerefMut)]
 pub struct DefaultTextStyle(TextStyle);
 
 pub struct GraphPlugin;
@@ -23,11 +44,21 @@ impl Plugin for GraphPlugin {
     fn build(&self, app: &mut App) {
         app.add_plugins(Material2dPlugin::<materials::CurveMaterial>::default())
             // .add_asset::<materials::CurveMaterial>()
-            .add_systems(Startup, (GraphPlugin::init, GraphPlugin::init_graph))
+            .add_systems(Startup, (GraphPlugin::init, GraphPlugin::init_graph, super::grid::spawn_grid))
             .init_resource::<ImageCache>()
+            .init_resource::<crate::theme::Theme>()
+            .init_resource::<super::grid::Grid>()
+            .init_resource::<event::animate::Animation>()
+            .init_resource::<event::search::BackgroundSearch>()
+            .init_resource::<super::flow::FlowSettings>()
+            .init_resource::<super::community::CommunityLayout>()
             .init_resource::<crate::ui::Alerts>()
             .init_resource::<crate::ui::UiItemInfo>()
-            .init_resource::<crate::ui::GraphInfoWindow>();
+            .init_resource::<crate::ui::LabelEditor>()
+            .init_resource::<crate::ui::GraphInfoWindow>()
+            .init_resource::<crate::ui::GeneratorWindow>()
+            .init_resource::<crate::ui::OperationsWindow>()
+            .init_resource::<crate::ui::CentralityWindow>();
 
         app.add_event::<GraphEvent>()
             .add_event::<ItemMovedEvent>()
@@ -38,17 +69,55 @@ impl Plugin for GraphPlugin {
                 (
                     event::add_node_event,
                     event::add_edge_event,
+                    event::spawn_template_event,
                     event::remove_item_event,
                     event::move_item_event,
                     event::item_selected_event,
-                    event::draw::draw_spanning_tree,
-                    event::draw::draw_bipartite,
-                    event::draw::draw_shortest_path,
+                    event::align_event,
+                    event::add_bend_event,
+                    event::straighten_event,
+                    event::place_labels_event,
                     event::reset_colors_event,
+                    event::annotate_event,
+                    event::export_event,
+                    event::animate::animation_system,
                     event::phys::physics_init_event,
                     event::phys::physics_sim_system,
+                    crate::theme::apply_theme,
+                    super::grid::draw_grid,
+                ),
+            )
+            .add_systems(
+                Update,
+                (
+                    event::draw::draw_spanning_tree,
+                    event::draw::draw_bipartite,
+                    event::draw::draw_shortest_path,
+                    event::draw::draw_max_flow,
+                    event::draw::draw_matching,
+                    event::draw::draw_communities,
+                    event::draw::draw_coloring,
+                    event::draw::check_coloring,
+                    event::draw::draw_edge_coloring,
+                    event::draw::draw_euler,
+                    event::draw::draw_biconnected,
+                    event::draw::draw_dag,
+                    event::draw::draw_planarity,
+                    event::draw::draw_crossings,
+                    event::update_labels,
+                    event::search::start_search,
+                    event::search::finish_search,
+                ),
+            )
+            .add_systems(
+                Update,
+                (
                     ui::egui_sys,
                     ui::egui_show_graph_info,
+                    ui::egui_show_generator,
+                    ui::egui_show_operations,
+                    ui::egui_show_centrality,
+                    ui::egui_label_editor,
                 ),
             )
             .add_systems(PostUpdate, event::regen_edge_mesh);
@@ -59,7 +128,9 @@ impl GraphPlugin {
     #[rustfmt::skip]
     fn init(
         assets: ResMut<AssetServer>,
+        theme: Res<crate::theme::Theme>,
         mut img_cache: ResMut<ImageCache>,
+        mut images: ResMut<Assets<Image>>,
         mut commands: Commands,
     ) {
         img_cache.0.insert("node".into(), assets.load("sprites/node.png"));
@@ -68,11 +139,16 @@ impl GraphPlugin {
         img_cache.0.insert("handle-dir".into(), assets.load("sprites/handle-dir.png"));
         img_cache.0.insert("handle-sel".into(), assets.load("sprites/handle-sel.png"));
         img_cache.0.insert("handle-dir-sel".into(), assets.load("sprites/handle-dir-sel.png"));
+        for shape in NodeShape::BUILT_IN.iter().skip(1) {
+            for selected in [false, true] {
+                img_cache.0.insert(shape.texture_key(selected), images.add(shape::shape_image(shape, selected)));
+            }
+        }
 
         let text_style = TextStyle {
             font: assets.load("fonts/FiraSans-Regular.ttf"),
             font_size: 25.0,
-            color: Color::WHITE,
+            color: theme.label,
         };
         commands.insert_resource(DefaultTextStyle(text_style));
     }
@@ -101,21 +177,21 @@ impl GraphPlugin {
                 [0.0, 0.0, 1.0, 1.0],
             ],
         );
+        mesh.insert_attribute(materials::ATTRIBUTE_EDGE_STYLE, vec![[2.0, 0.0, 1.0, 1.0]; 3]);
         mesh.set_indices(Some(Indices::U32(vec![0, 1, 2])));
 
-        let mat_test = curve_mats.add(materials::CurveMaterial { thickness: 2.0 });
+        let mat_test = curve_mats.add(materials::CurveMaterial { thickness: 1.0 });
 
         let edge_mesh_handle: Mesh2dHandle = meshes.add(mesh).into();
-        let edge_mesh = commands
+        commands
             .spawn((MaterialMesh2dBundle {
                 material: mat_test,
                 mesh: edge_mesh_handle.clone(),
                 transform: Transform::default().with_translation(Vec3::new(0.0, 0.0, -1.0)),
                 ..Default::default()
-            }, NoFrustumCulling))
-            .id();
+            }, NoFrustumCulling));
 
-        let graph = Graph::new(edge_mesh_handle, edge_mesh);
+        let graph = Graph::new(edge_mesh_handle);
         commands.insert_resource(graph);
     }
 }
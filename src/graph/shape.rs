@@ -0,0 +1,169 @@
+use bevy::{
+    prelude::*,
+    render::{
+        render_resource::{Extent3d, TextureDimension, TextureFormat},
+        texture::{CompressedImageFormats, ImageSampler, ImageType},
+    },
+};
+
+pub static DEFAULT_NODE_SIZE: f32 = 30.0;
+/// Width and height of the generated shape textures, larger than nodes are
+/// usually drawn so they stay sharp when scaled up
+static TEXTURE_SIZE: u32 = 64;
+/// Samples taken along each axis of a pixel when generating textures
+static SUPERSAMPLING: u32 = 4;
+
+#[derive(Clone, Default, PartialEq)]
+pub enum NodeShape {
+    #[default]
+    Circle,
+    Square,
+    Diamond,
+    Triangle,
+    /// An image file, drawn as is
+    Image(String),
+}
+
+impl core::fmt::Display for NodeShape {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            NodeShape::Circle => write!(f, "Circle"),
+            NodeShape::Square => write!(f, "Square"),
+            NodeShape::Diamond => write!(f, "Diamond"),
+            NodeShape::Triangle => write!(f, "Triangle"),
+            NodeShape::Image(_) => write!(f, "Image"),
+        }
+    }
+}
+
+impl NodeShape {
+    pub const BUILT_IN: [NodeShape; 4] = [
+        NodeShape::Circle,
+        NodeShape::Square,
+        NodeShape::Diamond,
+        NodeShape::Triangle,
+    ];
+
+    /// The name used for the shape in saved graphs
+    pub fn name(&self) -> &'static str {
+        match self {
+            NodeShape::Circle => "circle",
+            NodeShape::Square => "square",
+            NodeShape::Diamond => "diamond",
+            NodeShape::Triangle => "triangle",
+            NodeShape::Image(_) => "image",
+        }
+    }
+
+    pub fn from_name(name: &str, path: Option<&str>) -> Option<NodeShape> {
+        match name {
+            "circle" => Some(NodeShape::Circle),
+            "square" => Some(NodeShape::Square),
+            "diamond" => Some(NodeShape::Diamond),
+            "triangle" => Some(NodeShape::Triangle),
+            "image" => path.map(|path| NodeShape::Image(path.to_string())),
+            _ => None,
+        }
+    }
+
+    /// The key of the shape's texture in the
+    /// [`ImageCache`](super::plugin::ImageCache). Images look the same when
+    /// selected.
+    pub fn texture_key(&self, selected: bool) -> String {
+        let sel = if selected { "-sel" } else { "" };
+        match self {
+            NodeShape::Circle => format!("node{sel}"),
+            NodeShape::Image(path) => path.clone(),
+            shape => format!("node-{}{sel}", shape.name()),
+        }
+    }
+
+    /// Whether a point is inside the shape, in coordinates running from -1 to
+    /// 1 across the texture
+    fn contains(&self, p: Vec2) -> bool {
+        match self {
+            NodeShape::Circle | NodeShape::Image(_) => p.length_squared() <= 1.0,
+            NodeShape::Square => p.abs().max_element() <= 0.9,
+            NodeShape::Diamond => p.x.abs() + p.y.abs() <= 1.0,
+            NodeShape::Triangle => p.y >= -0.75 && 1.9 * p.x.abs() + p.y <= 0.95,
+        }
+    }
+}
+
+#[derive(Component, Clone, PartialEq)]
+pub struct NodeStyle {
+    pub shape: NodeShape,
+    /// Width and height the node is drawn at, it can be grabbed anywhere
+    /// within half of this from its centre
+    pub size: f32,
+}
+
+impl Default for NodeStyle {
+    fn default() -> Self {
+        NodeStyle {
+            shape: NodeShape::Circle,
+            size: DEFAULT_NODE_SIZE,
+        }
+    }
+}
+
+/// Draw a built in shape in white so sprite colors can tint it. Selected
+/// shapes get a black outline and are hatched like the selected circle.
+pub fn shape_image(shape: &NodeShape, selected: bool) -> Image {
+    let mut data = Vec::with_capacity((TEXTURE_SIZE * TEXTURE_SIZE * 4) as usize);
+    let samples = (SUPERSAMPLING * SUPERSAMPLING) as f32;
+
+    for y in 0..TEXTURE_SIZE {
+        for x in 0..TEXTURE_SIZE {
+            let (mut coverage, mut ink) = (0.0, 0.0);
+            for sy in 0..SUPERSAMPLING {
+                for sx in 0..SUPERSAMPLING {
+                    let sample = |i: u32, s: u32| (i as f32 + (s as f32 + 0.5) / SUPERSAMPLING as f32) / TEXTURE_SIZE as f32;
+                    // Textures are stored top row first
+                    let p = Vec2::new(sample(x, sx), 1.0 - sample(y, sy)) * 2.0 - 1.0;
+                    if !shape.contains(p) {
+                        continue;
+                    }
+                    coverage += 1.0;
+
+                    let outline = !shape.contains(p / 0.8);
+                    let hatched = ((p.x + p.y) * 4.0).rem_euclid(1.0) < 0.5;
+                    if selected && (outline || hatched) {
+                        ink += 1.0;
+                    }
+                }
+            }
+
+            let shade = if coverage > 0.0 { 255.0 * (1.0 - ink / coverage) } else { 255.0 };
+            data.extend_from_slice(&[shade as u8, shade as u8, shade as u8, (255.0 * coverage / samples) as u8]);
+        }
+    }
+
+    Image::new(
+        Extent3d {
+            width: TEXTURE_SIZE,
+            height: TEXTURE_SIZE,
+            depth_or_array_layers: 1,
+        },
+        TextureDimension::D2,
+        data,
+        TextureFormat::Rgba8UnormSrgb,
+    )
+}
+
+/// Read an image file to use as a node's texture
+pub fn load_image_file(path: &str) -> Result<Image, String> {
+    let bytes = std::fs::read(path).map_err(|e| format!("Failed to read {path}: {e}"))?;
+    let extension = std::path::Path::new(path)
+        .extension()
+        .and_then(|ext| ext.to_str())
+        .unwrap_or("png");
+    Image::from_buffer(
+        &bytes,
+        ImageType::Extension(extension),
+        CompressedImageFormats::NONE,
+        true,
+        ImageSampler::Default,
+    )
+    .map_err(|e| format!("Failed to load {path}: {e}"))
+}
@@ -0,0 +1,161 @@
+use bevy::prelude::*;
+
+use super::{shape::NodeStyle, Annotation, EdgeE, GEdge, GNode, Graph, NodeE};
+
+/// A node to be spawned as part of a [`GraphTemplate`]
+#[derive(Clone, Default)]
+pub struct TemplateNode {
+    pub pos: Vec2,
+    pub label: Option<String>,
+    pub style: NodeStyle,
+}
+
+/// An edge to be spawned as part of a [`GraphTemplate`], referring to its
+/// endpoints by their index in the template's node list
+#[derive(Clone)]
+pub struct TemplateEdge {
+    pub start: usize,
+    pub end: usize,
+    pub weight: i32,
+}
+
+/// A description of a whole graph that can be spawned into the world at once
+/// through [`GraphEvent::Spawn`](super::event::GraphEvent::Spawn)
+#[derive(Clone, Default)]
+pub struct GraphTemplate {
+    pub nodes: Vec<TemplateNode>,
+    pub edges: Vec<TemplateEdge>,
+}
+
+impl GraphTemplate {
+    /// Capture the current graph as a template. Also returns the entity that
+    /// each of the template's nodes and edges came from.
+    pub fn from_graph(
+        graph: &Graph,
+        q_nodes: &Query<(&Transform, &Children, &NodeStyle), With<GNode>>,
+        q_edges: &Query<&GEdge>,
+        q_text: &Query<&Text, Without<Annotation>>,
+    ) -> (Self, Vec<NodeE>, Vec<EdgeE>) {
+        let mut t = GraphTemplate::default();
+
+        let mut nodes: Vec<NodeE> = graph.node_edges.keys().copied().collect();
+        nodes.sort_by_key(|node| node.0);
+        let mut index = std::collections::HashMap::new();
+        for (i, node) in nodes.iter().enumerate() {
+            index.insert(*node, i);
+            let (transform, children, style) = q_nodes.get(**node).unwrap();
+            let label = children
+                .iter()
+                .find_map(|child| q_text.get(*child).ok())
+                .map(|text| text.sections[0].value.clone());
+            t.nodes.push(TemplateNode {
+                pos: transform.translation.truncate(),
+                label,
+                style: style.clone(),
+            });
+        }
+
+        let mut edges: Vec<EdgeE> = graph.edge_nodes.keys().copied().collect();
+        edges.sort_by_key(|edge| edge.0);
+        for edge in edges.iter() {
+            let (start, end) = graph.edge_nodes[edge];
+            t.edges.push(TemplateEdge {
+                start: index[&start],
+                end: index[&end],
+                weight: q_edges.get(**edge).map(|e| e.weight).unwrap_or(1),
+            });
+        }
+
+        (t, nodes, edges)
+    }
+
+    pub fn add_node(&mut self, pos: Vec2) -> usize {
+        self.nodes.push(TemplateNode {
+            pos,
+            ..Default::default()
+        });
+        self.nodes.len() - 1
+    }
+
+    pub fn add_edge(&mut self, start: usize, end: usize) {
+        self.edges.push(TemplateEdge { start, end, weight: 1 });
+    }
+
+    pub fn has_edge(&self, a: usize, b: usize) -> bool {
+        self.edges
+            .iter()
+            .any(|e| (e.start == a && e.end == b) || (e.start == b && e.end == a))
+    }
+
+    /// The node indices of each connected component, ignoring edge direction
+    pub fn components(&self) -> Vec<Vec<usize>> {
+        let mut adj = vec![Vec::new(); self.nodes.len()];
+        for edge in self.edges.iter() {
+            adj[edge.start].push(edge.end);
+            adj[edge.end].push(edge.start);
+        }
+
+        let mut seen = vec![false; self.nodes.len()];
+        let mut components = Vec::new();
+        for root in 0..self.nodes.len() {
+            if seen[root] {
+                continue;
+            }
+            seen[root] = true;
+            let mut component = vec![root];
+            let mut stack = vec![root];
+            while let Some(node) = stack.pop() {
+                for &adj in adj[node].iter() {
+                    if !seen[adj] {
+                        seen[adj] = true;
+                        component.push(adj);
+                        stack.push(adj);
+                    }
+                }
+            }
+            component.sort();
+            components.push(component);
+        }
+        components
+    }
+
+    /// The subgraph made of the given nodes and the edges between them
+    pub fn induced(&self, nodes: &[usize]) -> GraphTemplate {
+        let mut index = vec![None; self.nodes.len()];
+        for (i, node) in nodes.iter().enumerate() {
+            index[*node] = Some(i);
+        }
+
+        GraphTemplate {
+            nodes: nodes.iter().map(|node| self.nodes[*node].clone()).collect(),
+            edges: self
+                .edges
+                .iter()
+                .filter_map(|edge| {
+                    Some(TemplateEdge {
+                        start: index[edge.start]?,
+                        end: index[edge.end]?,
+                        ..edge.clone()
+                    })
+                })
+                .collect(),
+        }
+    }
+
+    /// Move every node so that the template's bounding box is centered on
+    /// the given point
+    pub fn center_on(&mut self, center: Vec2) {
+        if self.nodes.is_empty() {
+            return;
+        }
+
+        let (min, max) = self.nodes.iter().fold(
+            (Vec2::splat(f32::INFINITY), Vec2::splat(f32::NEG_INFINITY)),
+            |(min, max), node| (min.min(node.pos), max.max(node.pos)),
+        );
+        let offset = center - min.lerp(max, 0.5);
+        for node in self.nodes.iter_mut() {
+            node.pos += offset;
+        }
+    }
+}
@@ -7,9 +7,14 @@ use bevy::{
 };
 use bevy_egui::EguiContext;
 
-use crate::{graph::{event::*, NodeE}, types::*};
+use crate::{graph::{event::*, grid::Grid, NodeE}, types::*, ui::LabelEditor};
 use crate::{graph::Grabbable, MainCamera};
 
+/// The furthest the camera zooms out, in world units per pixel
+pub(crate) static MAX_ZOOM: f32 = 3.0;
+/// Longest time between the clicks of a double-click, in seconds
+static DOUBLE_CLICK_TIME: f64 = 0.4;
+
 #[derive(Copy, Clone, Default, PartialEq, Eq)]
 pub enum CursorMode {
     #[default]
@@ -21,7 +26,8 @@ pub enum CursorMode {
     Info,
     SpanningTree,
     Bipartite,
-    Dijkstra
+    Dijkstra,
+    MaxFlow,
 }
 
 impl core::fmt::Display for CursorMode {
@@ -36,6 +42,7 @@ impl core::fmt::Display for CursorMode {
             CursorMode::SpanningTree => write!(f, "Draw Spanning Tree"),
             CursorMode::Bipartite => write!(f, "Color Bipartite"),
             CursorMode::Dijkstra => write!(f, "Shortest Path"),
+            CursorMode::MaxFlow => write!(f, "Maximum Flow"),
         }
     }
 }
@@ -47,6 +54,12 @@ pub struct CursorInfo {
     pub mode: CursorMode,
     pub grabbed: Option<Entity>,
     pub selected: Option<Entity>,
+    /// Nodes picked with shift-click, which are dragged together and lined
+    /// up by the alignment commands
+    pub group: Vec<NodeE>,
+    /// Where the grabbed node is relative to the cursor, so snapping to the
+    /// grid doesn't jump it under the cursor
+    pub grab_offset: Vec2,
     pub paint_color: Color,
 }
 
@@ -112,6 +125,8 @@ pub fn mouse_movement_sys(
         (Entity, &Camera, &mut Transform, &OrthographicProjection, &GlobalTransform),
         With<crate::MainCamera>,
     >,
+    q_nodes: Query<&Transform, (GNodeExclusive, Without<MainCamera>)>,
+    grid: Res<Grid>,
     mut ev_move_item: EventWriter<ItemMovedEvent>,
 ) {
     let (camera_e, camera, mut camera_tf, proj, camera_global_tf) = q_camera.single_mut();
@@ -130,10 +145,24 @@ pub fn mouse_movement_sys(
                 camera_tf.translation.x -= cursor_delta.x;
                 camera_tf.translation.y += cursor_delta.y;
             } else {
-                ev_move_item.send(ItemMovedEvent(
-                    entity,
-                    Vec3::new(cursor_delta.x, -cursor_delta.y, 0.0),
-                ));
+                let mut delta = Vec3::new(cursor_delta.x, -cursor_delta.y, 0.0);
+                if let Ok(node_t) = q_nodes.get(entity) {
+                    if grid.snap {
+                        let target = grid.snap_point(cursor.world_pos + cursor.grab_offset);
+                        delta = (target - node_t.translation.truncate()).extend(0.0);
+                    }
+                    if delta == Vec3::ZERO {
+                        continue;
+                    }
+                    // Nodes picked together move together
+                    if cursor.group.contains(&NodeE(entity)) {
+                        for node in cursor.group.iter() {
+                            ev_move_item.send(ItemMovedEvent(**node, delta));
+                        }
+                        continue;
+                    }
+                }
+                ev_move_item.send(ItemMovedEvent(entity, delta));
             }
         }
     }
@@ -162,6 +191,7 @@ where
     closest_grab
 }
 
+#[allow(clippy::type_complexity)]
 pub(crate) fn mouse_button_sys(
     mut events: EventReader<MouseButtonInput>,
     mut cursor: ResMut<CursorInfo>,
@@ -169,14 +199,17 @@ pub(crate) fn mouse_button_sys(
         Query<&mut EguiContext>,
         Query<(Entity, &crate::graph::Grabbable, &mut Transform), GNodeExclusive>,
         Query<(Entity, &crate::graph::Grabbable, &mut Transform), GEdgeExclusive>,
+        Query<(Entity, &crate::graph::Grabbable, &mut Transform), GBendExclusive>,
         Query<(Entity, &mut Sprite)>,
         Query<Entity, With<MainCamera>>,
     ),
     mut ev_graph: EventWriter<GraphEvent>,
     mut ev_regen_mesh: EventWriter<RegenEdgeMesh>,
     mut ev_analyze: EventWriter<AnalyzeGraphEvent>,
+    keys: Res<Input<KeyCode>>,
 ) {
-    let (mut q_egui, q_node, q_handle, mut q_sprite, q_camera) = query;
+    let (mut q_egui, q_node, q_handle, q_bend, mut q_sprite, q_camera) = query;
+    let shift = keys.any_pressed([KeyCode::ShiftLeft, KeyCode::ShiftRight]);
 
     for MouseButtonInput { button, state, .. } in events.read() {
         if *button == MouseButton::Left && !state.is_pressed() {
@@ -191,10 +224,20 @@ pub(crate) fn mouse_button_sys(
         if *button == MouseButton::Left && state.is_pressed() {
             match cursor.mode {
                 CursorMode::Normal => {
-                    if let Some(entity) = get_closest_grab(&cursor, q_grab_combined) {
+                    if shift {
+                        if let Some(entity) = get_closest_grab(&cursor, q_node.iter()) {
+                            ev_graph.send(GraphEvent::GroupToggled(NodeE(entity)));
+                        }
+                    } else if let Some(entity) = get_closest_grab(&cursor, q_grab_combined.chain(q_bend.iter())) {
                         cursor.grabbed = Some(entity);
+                        if let Ok((_, _, node_t)) = q_node.get(entity) {
+                            cursor.grab_offset = node_t.translation.truncate() - cursor.world_pos;
+                        }
                     } else {
                         cursor.grabbed = Some(q_camera.single());
+                        if !cursor.group.is_empty() {
+                            ev_graph.send(GraphEvent::ItemDeselected);
+                        }
                     }
                 }
                 CursorMode::CreateNode => {
@@ -212,7 +255,7 @@ pub(crate) fn mouse_button_sys(
                     }
                 }
                 CursorMode::Remove => {
-                    if let Some(entity) = get_closest_grab(&cursor, q_grab_combined) {
+                    if let Some(entity) = get_closest_grab(&cursor, q_grab_combined.chain(q_bend.iter())) {
                         ev_graph.send(GraphEvent::RemoveItem(entity));
                     }
                 }
@@ -221,6 +264,9 @@ pub(crate) fn mouse_button_sys(
                         if let Ok((_, mut sprite)) = q_sprite.get_mut(entity) {
                             sprite.color = cursor.paint_color;
                             ev_regen_mesh.send(RegenEdgeMesh());
+                            if q_node.contains(entity) {
+                                ev_analyze.send(AnalyzeGraphEvent::CheckColoring(NodeE(entity)));
+                            }
                         }
                     }
                 }
@@ -250,7 +296,67 @@ pub(crate) fn mouse_button_sys(
                         }
                     }
                 }
+                CursorMode::MaxFlow => {
+                    // The first click picks the source, the second the sink
+                    if let Some(entity) = get_closest_grab(&cursor, q_node.iter()) {
+                        if let Some(selected_entity) = cursor.selected {
+                            ev_analyze.send(AnalyzeGraphEvent::MaxFlow(NodeE(selected_entity), NodeE(entity)));
+                            ev_graph.send(GraphEvent::ItemDeselected);
+                        } else {
+                            cursor.selected = Some(entity);
+                            ev_graph.send(GraphEvent::ItemSelected(entity));
+                        }
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// Open the in-place editor on a label when it, or the node or edge it
+/// belongs to, is double-clicked
+pub(crate) fn double_click_sys(
+    mut events: EventReader<MouseButtonInput>,
+    (cursor, time): (Res<CursorInfo>, Res<Time>),
+    mut editor: ResMut<LabelEditor>,
+    mut q_egui: Query<&mut EguiContext>,
+    q_items: Query<(Entity, &Grabbable, &Transform, &Children)>,
+    q_labels: Query<(Entity, &GlobalTransform, &bevy::text::TextLayoutInfo, &Visibility), EditableLabel>,
+    mut last_click: Local<Option<(Entity, f64)>>,
+) {
+    for MouseButtonInput { button, state, .. } in events.read() {
+        if *button != MouseButton::Left || !state.is_pressed() || egui_has_pointer(&mut q_egui) {
+            continue;
+        }
+        if !matches!(cursor.mode, CursorMode::Normal | CursorMode::Info) {
+            continue;
+        }
+
+        // The label under the cursor, or else the label of the closest node
+        // or edge handle
+        let hit = q_labels
+            .iter()
+            .find(|(_, label_t, layout, visibility)| {
+                let (scale, _, pos) = label_t.to_scale_rotation_translation();
+                **visibility != Visibility::Hidden
+                    && Rect::from_center_size(pos.truncate(), layout.logical_size * scale.truncate())
+                        .contains(cursor.world_pos)
+            })
+            .map(|(label_e, ..)| label_e)
+            .or_else(|| {
+                let grab = get_closest_grab(&cursor, q_items.iter().map(|(e, grab, t, _)| (e, grab, t)))?;
+                let (_, _, _, children) = q_items.get(grab).ok()?;
+                children.iter().copied().find(|child| q_labels.contains(*child))
+            });
+
+        let now = time.elapsed_seconds_f64();
+        match (hit, *last_click) {
+            (Some(label_e), Some((last_e, last_time))) if label_e == last_e && now - last_time < DOUBLE_CLICK_TIME => {
+                editor.label = Some(label_e);
+                editor.focus = true;
+                *last_click = None;
             }
+            _ => *last_click = hit.map(|label_e| (label_e, now)),
         }
     }
 }
@@ -278,8 +384,8 @@ pub fn mouse_scroll_input(
         if proj.scale < 0.8 {
             proj.scale = 0.8;
         }
-        else if proj.scale > 3.0 {
-            proj.scale = 3.0;
+        else if proj.scale > MAX_ZOOM {
+            proj.scale = MAX_ZOOM;
         }
     }
 }
@@ -1,3 +1,5 @@
+use std::path::Path;
+
 use bevy::{
     prelude::*,
     render::{RenderPlugin, settings::{WgpuSettings, PowerPreference, RenderCreation}},
@@ -7,6 +9,7 @@ use bevy_egui::EguiPlugin;
 mod graph;
 mod input;
 mod materials;
+mod theme;
 mod ui;
 pub mod types;
 
@@ -28,6 +31,20 @@ fn setup(
 }
 
 fn main() {
+    // Draw a saved graph to an image and exit without opening a window
+    let args: Vec<String> = std::env::args().collect();
+    if let Some(i) = args.iter().position(|arg| arg == "--export") {
+        let (Some(input), Some(output)) = (args.get(i + 1), args.get(i + 2)) else {
+            eprintln!("Usage: {} --export <graph file> <image.svg|image.png|picture.tex>", args[0]);
+            std::process::exit(2);
+        };
+        if let Err(e) = graph::export::convert(Path::new(input), Path::new(output)) {
+            eprintln!("{e}");
+            std::process::exit(1);
+        }
+        return;
+    }
+
     App::new()
         .add_plugins(DefaultPlugins.set(RenderPlugin {
             render_creation: RenderCreation::Automatic(WgpuSettings {
@@ -45,6 +62,7 @@ fn main() {
                 input::key_input_sys,
                 input::mouse_movement_sys,
                 input::mouse_button_sys,
+                input::double_click_sys,
                 input::mouse_scroll_input,
             ),
         )
@@ -1,18 +1,43 @@
-use bevy::{render::render_resource::{AsBindGroup, ShaderRef}, reflect::{TypePath, TypeUuid}, sprite::Material2d, asset::Asset};
+use bevy::{render::{mesh::{Mesh, MeshVertexAttribute, MeshVertexBufferLayout}, render_resource::{AsBindGroup, RenderPipelineDescriptor, ShaderRef, SpecializedMeshPipelineError, VertexFormat}}, reflect::{TypePath, TypeUuid}, sprite::{Material2d, Material2dKey}, asset::Asset};
+
+/// The style of the edge each vertex belongs to: thickness, pattern, opacity
+/// and the approximate length of the curve, for spacing out dashes
+pub const ATTRIBUTE_EDGE_STYLE: MeshVertexAttribute =
+	MeshVertexAttribute::new("Vertex_EdgeStyle", 988540917, VertexFormat::Float32x4);
 
 #[derive(Default, Asset, AsBindGroup, TypePath, TypeUuid, Debug, Clone)]
 #[uuid = "172eee85-2e56-4e77-972a-6c040d366ccb"]
 pub struct CurveMaterial {
+	/// Scale applied to the thickness of every edge
 	#[uniform(0)]
 	pub thickness: f32,
 }
 
 impl Material2d for CurveMaterial {
+	fn vertex_shader() -> ShaderRef {
+		"shaders/edge.wgsl".into()
+	}
+
 	fn fragment_shader() -> ShaderRef {
 		"shaders/edge.wgsl".into()
 	}
 
+	fn specialize(
+		descriptor: &mut RenderPipelineDescriptor,
+		layout: &MeshVertexBufferLayout,
+		_key: Material2dKey<Self>,
+	) -> Result<(), SpecializedMeshPipelineError> {
+		let vertex_layout = layout.get_layout(&[
+			Mesh::ATTRIBUTE_POSITION.at_shader_location(0),
+			Mesh::ATTRIBUTE_UV_0.at_shader_location(1),
+			Mesh::ATTRIBUTE_COLOR.at_shader_location(2),
+			ATTRIBUTE_EDGE_STYLE.at_shader_location(3),
+		])?;
+		descriptor.vertex.buffers = vec![vertex_layout];
+		Ok(())
+	}
+
 	// fn alpha_mode(&self) -> AlphaMode {
     //     AlphaMode::Blend
     // }
-}
\ No newline at end of file
+}
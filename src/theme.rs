@@ -0,0 +1,254 @@
+use bevy::{ecs::system::SystemParam, prelude::*};
+use bevy_egui::{egui, EguiContexts};
+
+use crate::{
+    graph::{event::RegenEdgeMesh, plugin::DefaultTextStyle, Annotation},
+    input::CursorInfo,
+    types::{GEdgeOrBend, GNodeExclusive},
+};
+
+#[derive(Copy, Clone, Default, PartialEq, Eq)]
+pub enum ThemePreset {
+    #[default]
+    Dark,
+    Light,
+    HighContrast,
+    Colorblind,
+}
+
+impl core::fmt::Display for ThemePreset {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ThemePreset::Dark => write!(f, "Dark"),
+            ThemePreset::Light => write!(f, "Light"),
+            ThemePreset::HighContrast => write!(f, "High Contrast"),
+            ThemePreset::Colorblind => write!(f, "Colorblind Safe"),
+        }
+    }
+}
+
+impl ThemePreset {
+    pub const ALL: [ThemePreset; 4] = [
+        ThemePreset::Dark,
+        ThemePreset::Light,
+        ThemePreset::HighContrast,
+        ThemePreset::Colorblind,
+    ];
+}
+
+/// Colors used to draw the graph and the results of algorithms
+#[derive(Resource, Clone, PartialEq)]
+pub struct Theme {
+    pub preset: ThemePreset,
+    pub background: Color,
+    /// Nodes and edges are drawn in these colors until something paints them
+    pub node: Color,
+    pub edge: Color,
+    /// Results of algorithms, unless a paint color has been picked
+    pub highlight: Color,
+    /// Things an algorithm wants to single out as a problem, like unmatched
+    /// nodes or the branch vertices of a Kuratowski subgraph
+    pub warning: Color,
+    /// The two sides of a bipartite graph or a minimum cut
+    pub sides: [Color; 2],
+    pub label: Color,
+    pub annotation: Color,
+    /// Colors used when an algorithm needs to tell an arbitrary number of
+    /// classes apart, classes past the end of the palette wrap around
+    pub palette: Vec<Color>,
+    /// The ends of the centrality heatmap gradient
+    pub heat: (Color, Color),
+    pub dark_ui: bool,
+}
+
+impl Default for Theme {
+    fn default() -> Self {
+        Theme::preset(ThemePreset::default())
+    }
+}
+
+impl Theme {
+    pub fn preset(preset: ThemePreset) -> Self {
+        match preset {
+            ThemePreset::Dark => Theme {
+                preset,
+                background: Color::rgb(0.15, 0.15, 0.17),
+                node: Color::WHITE,
+                edge: Color::WHITE,
+                highlight: Color::GREEN,
+                warning: Color::RED,
+                sides: [Color::rgb(1.0, 0.0, 0.0), Color::rgb(0.0, 0.0, 1.0)],
+                label: Color::WHITE,
+                annotation: Color::YELLOW,
+                palette: vec![
+                    Color::rgb(0.90, 0.10, 0.29),
+                    Color::rgb(0.24, 0.71, 0.29),
+                    Color::rgb(0.26, 0.39, 0.85),
+                    Color::rgb(1.00, 0.88, 0.10),
+                    Color::rgb(0.96, 0.51, 0.19),
+                    Color::rgb(0.57, 0.12, 0.71),
+                    Color::rgb(0.27, 0.94, 0.94),
+                    Color::rgb(0.94, 0.20, 0.90),
+                    Color::rgb(0.74, 0.96, 0.05),
+                    Color::rgb(0.98, 0.75, 0.83),
+                    Color::rgb(0.00, 0.50, 0.50),
+                    Color::rgb(0.67, 0.43, 0.16),
+                ],
+                heat: (Color::rgb(0.0, 0.2, 1.0), Color::rgb(1.0, 0.2, 0.0)),
+                dark_ui: true,
+            },
+            ThemePreset::Light => Theme {
+                preset,
+                background: Color::rgb(0.96, 0.96, 0.94),
+                node: Color::rgb(0.20, 0.27, 0.40),
+                edge: Color::rgb(0.35, 0.35, 0.35),
+                highlight: Color::rgb(0.0, 0.55, 0.25),
+                warning: Color::rgb(0.80, 0.10, 0.10),
+                sides: [Color::rgb(0.85, 0.15, 0.15), Color::rgb(0.15, 0.30, 0.85)],
+                label: Color::rgb(0.1, 0.1, 0.1),
+                annotation: Color::rgb(0.65, 0.35, 0.0),
+                palette: vec![
+                    Color::rgb(0.80, 0.08, 0.24),
+                    Color::rgb(0.16, 0.55, 0.22),
+                    Color::rgb(0.20, 0.32, 0.75),
+                    Color::rgb(0.80, 0.65, 0.0),
+                    Color::rgb(0.90, 0.42, 0.10),
+                    Color::rgb(0.50, 0.10, 0.62),
+                    Color::rgb(0.0, 0.60, 0.65),
+                    Color::rgb(0.80, 0.15, 0.75),
+                    Color::rgb(0.45, 0.60, 0.0),
+                    Color::rgb(0.85, 0.45, 0.60),
+                    Color::rgb(0.0, 0.40, 0.40),
+                    Color::rgb(0.55, 0.35, 0.12),
+                ],
+                heat: (Color::rgb(0.0, 0.2, 0.9), Color::rgb(0.9, 0.15, 0.0)),
+                dark_ui: false,
+            },
+            ThemePreset::HighContrast => Theme {
+                preset,
+                background: Color::BLACK,
+                node: Color::WHITE,
+                edge: Color::WHITE,
+                highlight: Color::rgb(1.0, 1.0, 0.0),
+                warning: Color::rgb(1.0, 0.2, 0.2),
+                sides: [Color::rgb(1.0, 1.0, 0.0), Color::rgb(0.0, 1.0, 1.0)],
+                label: Color::WHITE,
+                annotation: Color::rgb(0.0, 1.0, 1.0),
+                palette: vec![
+                    Color::rgb(1.0, 1.0, 0.0),
+                    Color::rgb(0.0, 1.0, 1.0),
+                    Color::rgb(1.0, 0.0, 1.0),
+                    Color::rgb(0.0, 1.0, 0.0),
+                    Color::rgb(1.0, 0.5, 0.0),
+                    Color::rgb(0.4, 0.6, 1.0),
+                    Color::rgb(1.0, 0.4, 0.4),
+                    Color::rgb(0.7, 1.0, 0.7),
+                ],
+                heat: (Color::rgb(0.2, 0.4, 1.0), Color::rgb(1.0, 0.2, 0.2)),
+                dark_ui: true,
+            },
+            // The Okabe–Ito palette, told apart with any kind of color blindness
+            ThemePreset::Colorblind => Theme {
+                preset,
+                background: Color::rgb(0.15, 0.15, 0.17),
+                node: Color::WHITE,
+                edge: Color::WHITE,
+                highlight: Color::rgb(0.90, 0.62, 0.0),
+                warning: Color::rgb(0.84, 0.37, 0.0),
+                sides: [Color::rgb(0.90, 0.62, 0.0), Color::rgb(0.34, 0.71, 0.91)],
+                label: Color::WHITE,
+                annotation: Color::rgb(0.94, 0.89, 0.26),
+                palette: vec![
+                    Color::rgb(0.90, 0.62, 0.0),
+                    Color::rgb(0.34, 0.71, 0.91),
+                    Color::rgb(0.0, 0.62, 0.45),
+                    Color::rgb(0.94, 0.89, 0.26),
+                    Color::rgb(0.0, 0.45, 0.70),
+                    Color::rgb(0.84, 0.37, 0.0),
+                    Color::rgb(0.80, 0.47, 0.65),
+                ],
+                heat: (Color::rgb(0.0, 0.45, 0.70), Color::rgb(0.90, 0.62, 0.0)),
+                dark_ui: true,
+            },
+        }
+    }
+
+    pub fn palette_color(&self, class: usize) -> Color {
+        self.palette[class % self.palette.len()]
+    }
+
+    /// Map a value from 0 to 1 onto the heatmap gradient
+    pub fn heat(&self, t: f32) -> Color {
+        let (a, b): (Vec4, Vec4) = (self.heat.0.into(), self.heat.1.into());
+        let c = a.lerp(b, t);
+        Color::rgba(c.x, c.y, c.z, c.w)
+    }
+}
+
+/// The theme together with the paint color picked by the user, for systems
+/// that draw the results of algorithms
+#[derive(SystemParam)]
+pub(crate) struct Colors<'w> {
+    cursor: Res<'w, CursorInfo>,
+    pub theme: Res<'w, Theme>,
+}
+
+impl Colors<'_> {
+    /// The paint color if one has been picked, otherwise the theme's
+    /// highlight
+    pub fn highlight(&self) -> Color {
+        if self.cursor.paint_color != Color::WHITE {
+            self.cursor.paint_color
+        } else {
+            self.theme.highlight
+        }
+    }
+}
+
+/// Switch the background, UI and text over to the current theme, and repaint
+/// anything still in the previous theme's default colors
+pub(crate) fn apply_theme(
+    theme: Res<Theme>,
+    mut previous: Local<Option<Theme>>,
+    mut contexts: EguiContexts,
+    (mut clear_color, mut text_style): (ResMut<ClearColor>, ResMut<DefaultTextStyle>),
+    (mut q_node, mut q_edge): (
+        Query<&mut Sprite, GNodeExclusive>,
+        Query<&mut Sprite, GEdgeOrBend>,
+    ),
+    mut q_text: Query<(&mut Text, Option<&Annotation>)>,
+    mut ev_regen: EventWriter<RegenEdgeMesh>,
+) {
+    if previous.as_ref() == Some(&*theme) {
+        return;
+    }
+
+    clear_color.0 = theme.background;
+    contexts.ctx_mut().set_visuals(if theme.dark_ui {
+        egui::Visuals::dark()
+    } else {
+        egui::Visuals::light()
+    });
+    text_style.color = theme.label;
+
+    if let Some(previous) = previous.as_ref() {
+        for mut sprite in q_node.iter_mut().filter(|sprite| sprite.color == previous.node) {
+            sprite.color = theme.node;
+        }
+        for mut sprite in q_edge.iter_mut().filter(|sprite| sprite.color == previous.edge) {
+            sprite.color = theme.edge;
+        }
+        for (mut text, annotation) in q_text.iter_mut() {
+            let (old, new) = match annotation {
+                Some(_) => (previous.annotation, theme.annotation),
+                None => (previous.label, theme.label),
+            };
+            for section in text.sections.iter_mut().filter(|section| section.style.color == old) {
+                section.style.color = new;
+            }
+        }
+        ev_regen.send(RegenEdgeMesh());
+    }
+
+    *previous = Some(theme.clone());
+}
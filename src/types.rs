@@ -1,6 +1,13 @@
 use bevy::prelude::*;
 
-use crate::graph::{GEdge, GNode};
+use crate::graph::{Annotation, EdgeBend, GEdge, GNode, WeightLabel};
 
 pub type GNodeExclusive = (With<GNode>, Without<GEdge>);
-pub type GEdgeExclusive = (With<GEdge>, Without<GNode>);
\ No newline at end of file
+pub type GEdgeExclusive = (With<GEdge>, Without<GNode>);
+pub type GBendExclusive = (With<EdgeBend>, Without<GNode>, Without<GEdge>);
+pub type GEdgeOrBend = (Or<(With<GEdge>, With<EdgeBend>)>, Without<GNode>);
+/// Node and edge labels, kept apart from annotations and from the nodes and
+/// edges they're children of so their transforms can be moved on their own
+pub type GLabelExclusive = (Without<Annotation>, Without<GNode>, Without<GEdge>);
+/// Node and edge labels, which can be typed in unlike weight labels
+pub type EditableLabel = (With<Parent>, Without<Annotation>, Without<WeightLabel>);
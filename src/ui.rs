@@ -3,14 +3,29 @@ use bevy_egui::{egui, EguiContexts};
 
 use crate::{
     graph::{
-        event::{get_visibility, GraphEvent},
-        plugin::ImageCache,
-        EdgeE, GEdge, GNode, Graph, LabeledMatrix, NodeE,
+        coloring::{ColoringMethod, EdgeColoringMethod},
+        community::{CommunityLayout, CommunityMethod},
+        align::Alignment,
+        flow::{FlowMethod, FlowSettings},
+        grid::Grid,
+        matching::MatchingMethod,
+        event::{draw::Labels, search::BackgroundSearch, AnalyzeGraphEvent, GraphEvent, RegenEdgeMesh},
+        plugin::{DefaultTextStyle, ImageCache},
+        shape::{NodeShape, NodeStyle},
+        Annotation, EdgeE, EdgePattern, EdgeRoute, GEdge, GNode, Grabbable, Graph, LabeledMatrix, NodeE,
     },
     input::{CursorInfo, CursorMode},
-    types::{GEdgeExclusive, GNodeExclusive},
+    theme::{Theme, ThemePreset},
+    types::{EditableLabel, GEdgeExclusive, GNodeExclusive},
+    MainCamera,
 };
 
+mod centrality;
+mod generate;
+mod operations;
+
+pub(crate) use {centrality::*, generate::*, operations::*};
+
 #[derive(Default, Resource)]
 pub(crate) struct Alerts(pub Vec<String>);
 
@@ -20,52 +35,122 @@ pub(crate) enum UiItemInfo {
     None,
     Node {
         node_e: Entity,
+        is_cut_vertex: bool,
+        clustering: f64,
         label: Option<Entity>,
+        /// The file typed in to use as the node's image
+        image_path: String,
     },
     Edge {
-        edge: GEdge,
         edge_e: Entity,
         is_bridge: bool,
         label: Option<Entity>,
     },
 }
 
+/// The label being edited in place, opened by double-clicking it
+#[derive(Default, Resource)]
+pub(crate) struct LabelEditor {
+    pub label: Option<Entity>,
+    /// Whether the text box still has to take the keyboard focus, which it
+    /// does once when opened
+    pub focus: bool,
+}
+
 #[derive(Default, Resource)]
 pub(crate) struct GraphInfoWindow {
     pub open: bool,
     adj_matrix: LabeledMatrix,
     max_col_width: usize,
     eigen: Option<nalgebra::SymmetricEigen<f32, nalgebra::Dyn>>,
+    /// Transitivity and average local clustering coefficient
+    clustering: (f64, f64),
+    community_method: CommunityMethod,
+}
+
+/// Show a running background search with a button to cancel it, returning
+/// whether there is one so the buttons starting new searches can be hidden
+fn show_search(ui: &mut egui::Ui, search: &BackgroundSearch) -> bool {
+    if search.is_running() {
+        ui.horizontal(|ui| {
+            ui.spinner();
+            ui.label("Searching...");
+            if ui.button("Cancel").clicked() {
+                search.cancel();
+            }
+        });
+    }
+    search.is_running()
 }
 
+#[allow(clippy::type_complexity)]
 pub(crate) fn egui_sys(
     mut contexts: EguiContexts,
     mut graph_ev: EventWriter<GraphEvent>,
+    mut analyze_ev: EventWriter<AnalyzeGraphEvent>,
+    mut ev_regen: EventWriter<RegenEdgeMesh>,
+    mut coloring_method: Local<(ColoringMethod, EdgeColoringMethod)>,
     resources: (
         ResMut<Graph>,
         ResMut<CursorInfo>,
         ResMut<UiItemInfo>,
         ResMut<Alerts>,
         ResMut<GraphInfoWindow>,
-        Res<ImageCache>,
+        ResMut<GeneratorWindow>,
+        ResMut<OperationsWindow>,
+        ResMut<CentralityWindow>,
+        ResMut<ImageCache>,
+        Res<BackgroundSearch>,
+        ResMut<FlowSettings>,
+        ResMut<Assets<Image>>,
+        ResMut<Theme>,
+        ResMut<Grid>,
+        ResMut<DefaultTextStyle>,
     ),
     queries: (
-        Query<(&GNode, &Children), GNodeExclusive>,
-        Query<(&GEdge, &mut Handle<Image>, &Children), GEdgeExclusive>,
-        Query<(&mut Text, &mut Visibility), With<Parent>>,
+        Query<(&Children, &mut NodeStyle, &mut Handle<Image>, &mut Sprite, &mut Grabbable), GNodeExclusive>,
+        Query<(&mut GEdge, &mut Handle<Image>, &Children), GEdgeExclusive>,
+        Query<(&mut Text, &mut Visibility), EditableLabel>,
     ),
 ) {
-    let (q_node, mut q_edge, mut q_labels) = queries;
-    let (mut graph, mut cursor, mut info_item, mut alerts, mut info_win, img_cache) = resources;
+    let (mut q_node, mut q_edge, mut q_labels) = queries;
+    let (
+        mut graph,
+        mut cursor,
+        mut info_item,
+        mut alerts,
+        mut info_win,
+        mut gen_win,
+        mut ops_win,
+        mut cent_win,
+        mut img_cache,
+        search,
+        mut flow_settings,
+        mut images,
+        mut theme,
+        mut grid,
+        mut text_style,
+    ) = resources;
 
     show_alerts(contexts.ctx_mut(), &mut alerts.0);
 
     egui::Window::new("Graph Plotter").show(contexts.ctx_mut(), |ui| {
         egui_graph_info(ui, &graph);
 
-        if ui.button("Graph Info").clicked() {
-            info_win.open = true;
-        }
+        ui.horizontal(|ui| {
+            if ui.button("Graph Info").clicked() {
+                info_win.open = true;
+            }
+            if ui.button("Generate").clicked() {
+                gen_win.open = true;
+            }
+            if ui.button("Operations").clicked() {
+                ops_win.open = true;
+            }
+            if ui.button("Centrality").clicked() {
+                cent_win.open = true;
+            }
+        });
 
         let mut directed = graph.directed;
         if ui.checkbox(&mut directed, "Directed").changed() {
@@ -78,13 +163,39 @@ pub(crate) fn egui_sys(
                     img_cache.get("handle").unwrap().clone()
                 };
             }
+            ev_regen.send(RegenEdgeMesh());
         }
+        let arrow_slider = egui::Slider::new(&mut graph.arrow_size, 4.0..=40.0).text("Arrow Size");
+        if ui.add_enabled(directed, arrow_slider).changed() {
+            ev_regen.send(RegenEdgeMesh());
+        }
+        if ui.checkbox(&mut graph.thickness_from_weight, "Thickness From Weight").changed() {
+            ev_regen.send(RegenEdgeMesh());
+        }
+        ui.horizontal(|ui| {
+            if ui.checkbox(&mut graph.straight_only, "Straight Edges Only").changed() {
+                ev_regen.send(RegenEdgeMesh());
+            }
+            if ui.button("Straighten All").clicked() {
+                graph_ev.send(GraphEvent::Straighten(None));
+            }
+        });
 
-        if ui.checkbox(&mut graph.show_labels, "Show Labels").changed() {
-            for (_, mut label_vis) in q_labels.iter_mut() {
-                *label_vis = get_visibility(graph.show_labels);
+        ui.collapsing("Labels", |ui| {
+            ui.horizontal(|ui| {
+                ui.checkbox(&mut graph.show_node_labels, "Node Labels");
+                ui.checkbox(&mut graph.show_edge_labels, "Edge Labels");
+                ui.checkbox(&mut graph.show_weights, "Weights");
+            });
+            let mut font_size = text_style.font_size;
+            if ui.add(egui::Slider::new(&mut font_size, 8.0..=64.0).text("Label Size")).changed() {
+                text_style.font_size = font_size;
             }
-        }
+            if ui.button("Place Labels").on_hover_text("Move labels out of the way of each other and the edges").clicked() {
+                graph_ev.send(GraphEvent::PlaceLabels);
+            }
+            ui.label("Double-click a label to edit it");
+        });
 
         if ui.checkbox(&mut graph.do_physics, "Physics").changed() {
             graph_ev.send(GraphEvent::PhysicsInit);
@@ -94,6 +205,139 @@ pub(crate) fn egui_sys(
             graph_ev.send(GraphEvent::ResetColors);
         }
 
+        let mut preset = theme.preset;
+        egui::ComboBox::from_label("Theme")
+            .selected_text(format!("{preset}"))
+            .show_ui(ui, |ui| {
+                for p in ThemePreset::ALL {
+                    ui.selectable_value(&mut preset, p, format!("{p}"));
+                }
+            });
+        if preset != theme.preset {
+            *theme = Theme::preset(preset);
+        }
+
+        ui.collapsing("Grid", |ui| {
+            ui.horizontal(|ui| {
+                ui.checkbox(&mut grid.show, "Show Grid");
+                ui.checkbox(&mut grid.snap, "Snap to Grid");
+            });
+            ui.add(egui::Slider::new(&mut grid.spacing, 10.0..=200.0).text("Spacing"));
+
+            ui.label(format!("{} nodes picked (shift-click in {} mode)", cursor.group.len(), CursorMode::Normal));
+            ui.add_enabled_ui(cursor.group.len() > 1, |ui| {
+                ui.horizontal_wrapped(|ui| {
+                    for alignment in Alignment::ALIGN {
+                        if ui.button(format!("{alignment}")).clicked() {
+                            graph_ev.send(GraphEvent::Align(alignment));
+                        }
+                    }
+                });
+            });
+            ui.add_enabled_ui(cursor.group.len() > 2, |ui| {
+                ui.horizontal(|ui| {
+                    for alignment in Alignment::DISTRIBUTE {
+                        if ui.button(format!("{alignment}")).clicked() {
+                            graph_ev.send(GraphEvent::Align(alignment));
+                        }
+                    }
+                });
+            });
+        });
+
+        ui.collapsing("Coloring", |ui| {
+            if show_search(ui, &search) {
+                return;
+            }
+            let (vertex_method, edge_method) = &mut *coloring_method;
+            egui::ComboBox::from_id_source("vertex_coloring")
+                .selected_text(format!("{vertex_method}"))
+                .show_ui(ui, |ui| {
+                    for method in [
+                        ColoringMethod::WelshPowell,
+                        ColoringMethod::DSatur,
+                        ColoringMethod::Exact,
+                    ] {
+                        ui.selectable_value(vertex_method, method, format!("{method}"));
+                    }
+                });
+            if ui.button("Color Vertices").clicked() {
+                analyze_ev.send(AnalyzeGraphEvent::Coloring(*vertex_method));
+            }
+
+            egui::ComboBox::from_id_source("edge_coloring")
+                .selected_text(format!("{edge_method}"))
+                .show_ui(ui, |ui| {
+                    for method in [EdgeColoringMethod::MisraGries, EdgeColoringMethod::Exact] {
+                        ui.selectable_value(edge_method, method, format!("{method}"));
+                    }
+                });
+            if ui.button("Color Edges").clicked() {
+                analyze_ev.send(AnalyzeGraphEvent::EdgeColoring(*edge_method));
+            }
+        });
+
+        ui.collapsing("Analysis", |ui| {
+            if ui.button("Eulerian Trail").clicked() {
+                analyze_ev.send(AnalyzeGraphEvent::Euler);
+            }
+            if ui.button("Biconnected Components").clicked() {
+                analyze_ev.send(AnalyzeGraphEvent::Biconnected);
+            }
+            ui.horizontal(|ui| {
+                if ui.button("Planarity").clicked() {
+                    analyze_ev.send(AnalyzeGraphEvent::Planarity);
+                }
+                if ui.button("Count Crossings").clicked() {
+                    analyze_ev.send(AnalyzeGraphEvent::Crossings);
+                }
+            });
+            ui.horizontal(|ui| {
+                for method in [MatchingMethod::Maximum, MatchingMethod::Assignment] {
+                    if ui.button(format!("{method}")).clicked() {
+                        analyze_ev.send(AnalyzeGraphEvent::Matching(method));
+                    }
+                }
+            });
+
+            ui.add_enabled_ui(graph.directed, |ui| {
+                ui.horizontal(|ui| {
+                    if ui.button("Topological Sort").clicked() {
+                        analyze_ev.send(AnalyzeGraphEvent::TopologicalSort);
+                    }
+                    if ui.button("Critical Path").clicked() {
+                        analyze_ev.send(AnalyzeGraphEvent::CriticalPath);
+                    }
+                });
+                ui.horizontal(|ui| {
+                    if ui.button("Transitive Closure").clicked() {
+                        analyze_ev.send(AnalyzeGraphEvent::TransitiveClosure);
+                    }
+                    if ui.button("Transitive Reduction").clicked() {
+                        analyze_ev.send(AnalyzeGraphEvent::TransitiveReduction);
+                    }
+                });
+            });
+
+            if !show_search(ui, &search) {
+                ui.horizontal(|ui| {
+                    if ui.button("Hamiltonian Cycle").clicked() {
+                        analyze_ev.send(AnalyzeGraphEvent::Hamiltonian { cycle: true });
+                    }
+                    if ui.button("Hamiltonian Path").clicked() {
+                        analyze_ev.send(AnalyzeGraphEvent::Hamiltonian { cycle: false });
+                    }
+                });
+                if ui
+                    .button("Travelling Salesman")
+                    .on_hover_text("Shortest tour of a complete graph by edge weight")
+                    .clicked()
+                {
+                    analyze_ev.send(AnalyzeGraphEvent::Tsp);
+                }
+            }
+        });
+
         let mut mode = cursor.mode;
         egui::ComboBox::from_label("Mode")
             .selected_text(format!("{mode}"))
@@ -140,6 +384,11 @@ pub(crate) fn egui_sys(
                     CursorMode::Dijkstra,
                     format!("{}", CursorMode::Dijkstra),
                 );
+                ui.selectable_value(
+                    &mut mode,
+                    CursorMode::MaxFlow,
+                    format!("{}", CursorMode::MaxFlow),
+                );
             });
         if mode != cursor.mode {
             cursor.set_mode(&mode, &mut graph_ev);
@@ -149,11 +398,25 @@ pub(crate) fn egui_sys(
             let mut color: [u8; 3] = cursor.paint_color.as_rgba_u8()[0..3].try_into().unwrap();
             egui::color_picker::color_edit_button_srgb(ui, &mut color);
             cursor.paint_color = Color::rgb_u8(color[0], color[1], color[2]);
+        } else if mode == CursorMode::MaxFlow {
+            ui.label("Click the source, then the sink. Edge weights are capacities.");
+            egui::ComboBox::from_label("Method")
+                .selected_text(format!("{}", flow_settings.method))
+                .show_ui(ui, |ui| {
+                    for method in [FlowMethod::EdmondsKarp, FlowMethod::Dinic] {
+                        ui.selectable_value(&mut flow_settings.method, method, format!("{method}"));
+                    }
+                });
+            ui.checkbox(&mut flow_settings.animate, "Animate augmenting paths");
         } else if mode == CursorMode::Info {
-            match &*info_item {
+            match &mut *info_item {
                 UiItemInfo::None => {
                     if let Some(entity) = cursor.selected {
-                        if let Ok((_node, children)) = q_node.get(entity) {
+                        if let Ok((children, style, ..)) = q_node.get(entity) {
+                            let image_path = match &style.shape {
+                                NodeShape::Image(path) => path.clone(),
+                                _ => String::new(),
+                            };
                             let label = children
                                 .iter()
                                 .find(|&child| q_labels.get(*child).is_ok())
@@ -161,29 +424,40 @@ pub(crate) fn egui_sys(
 
                             *info_item = UiItemInfo::Node {
                                 node_e: entity,
+                                is_cut_vertex: graph
+                                    .biconnectivity()
+                                    .cut_vertices
+                                    .contains(&NodeE(entity)),
+                                clustering: graph.local_clustering(&NodeE(entity)),
                                 label,
+                                image_path,
                             };
-                        } else if let Ok((edge, _, children)) = q_edge.get_mut(entity) {
+                        } else if let Ok((_, _, children)) = q_edge.get(entity) {
                             let label = children
                                 .iter()
                                 .find(|&child| q_labels.get(*child).is_ok())
                                 .copied();
 
                             *info_item = UiItemInfo::Edge {
-                                edge: edge.clone(),
                                 edge_e: entity,
                                 label,
-                                is_bridge: graph.is_bridge(&EdgeE(entity)),
+                                is_bridge: graph.biconnectivity().bridges.contains(&EdgeE(entity)),
                             };
                         }
                     }
                 }
-                UiItemInfo::Node { node_e, label } => {
+                UiItemInfo::Node {
+                    node_e,
+                    is_cut_vertex,
+                    clustering,
+                    label,
+                    image_path,
+                } => {
                     ui.label(format!("Node: ID = {}", node_e.index()));
 
                     if let Some(label) = label {
                         if let Ok((mut label, _)) = q_labels.get_mut(*label) {
-                            ui.text_edit_singleline(&mut label.sections[0].value);
+                            ui.text_edit_multiline(&mut label.sections[0].value);
                         }
                     }
 
@@ -191,9 +465,38 @@ pub(crate) fn egui_sys(
                         "Degree: {}",
                         graph.node_edges.get(&NodeE(*node_e)).unwrap().len()
                     ));
+                    ui.label(format!("Is Cut Vertex: {}", is_cut_vertex));
+                    ui.label(format!("Clustering Coefficient: {:.4}", clustering));
+
+                    if let Ok((_, mut style, mut texture, mut sprite, mut grab)) = q_node.get_mut(*node_e) {
+                        let old = style.clone();
+                        egui::ComboBox::from_label("Shape")
+                            .selected_text(format!("{}", style.shape))
+                            .show_ui(ui, |ui| {
+                                for shape in NodeShape::BUILT_IN {
+                                    let text = format!("{shape}");
+                                    ui.selectable_value(&mut style.shape, shape, text);
+                                }
+                            });
+                        ui.add(egui::Slider::new(&mut style.size, 10.0..=120.0).text("Size"));
+                        ui.horizontal(|ui| {
+                            ui.text_edit_singleline(image_path);
+                            if ui.button("Load Image").clicked() {
+                                match img_cache.insert_file(image_path, &mut images) {
+                                    Ok(()) => style.shape = NodeShape::Image(image_path.clone()),
+                                    Err(e) => alerts.0.push(e),
+                                }
+                            }
+                        });
+                        if *style != old {
+                            *texture = img_cache.texture(&style.shape, cursor.selected == Some(*node_e));
+                            sprite.custom_size = Some(Vec2::splat(style.size));
+                            *grab = Grabbable::Circle { radius: style.size / 2.0 };
+                            ev_regen.send(RegenEdgeMesh());
+                        }
+                    }
                 }
                 UiItemInfo::Edge {
-                    edge,
                     edge_e,
                     is_bridge,
                     label,
@@ -202,11 +505,49 @@ pub(crate) fn egui_sys(
 
                     if let Some(label) = label {
                         if let Ok((mut label, _)) = q_labels.get_mut(*label) {
-                            ui.text_edit_singleline(&mut label.sections[0].value);
+                            ui.text_edit_multiline(&mut label.sections[0].value);
                         }
                     }
 
-                    ui.label(format!("Weight: {}", edge.weight));
+                    if let Ok((mut edge, _, _)) = q_edge.get_mut(*edge_e) {
+                        let style = edge.style;
+                        ui.horizontal(|ui| {
+                            ui.label("Weight:");
+                            if ui.add(egui::DragValue::new(&mut edge.weight)).changed() && graph.thickness_from_weight {
+                                ev_regen.send(RegenEdgeMesh());
+                            }
+                        });
+                        ui.add(egui::Slider::new(&mut edge.style.thickness, 0.5..=10.0).text("Thickness"));
+                        ui.add(egui::Slider::new(&mut edge.style.opacity, 0.0..=1.0).text("Opacity"));
+                        egui::ComboBox::from_label("Pattern")
+                            .selected_text(format!("{}", edge.style.pattern))
+                            .show_ui(ui, |ui| {
+                                for pattern in [EdgePattern::Solid, EdgePattern::Dashed, EdgePattern::Dotted] {
+                                    ui.selectable_value(&mut edge.style.pattern, pattern, format!("{pattern}"));
+                                }
+                            });
+                        ui.add_enabled_ui(!edge.is_loop(), |ui| {
+                            egui::ComboBox::from_label("Route")
+                                .selected_text(format!("{}", edge.style.route))
+                                .show_ui(ui, |ui| {
+                                    for route in [EdgeRoute::Curved, EdgeRoute::Polyline, EdgeRoute::Orthogonal] {
+                                        ui.selectable_value(&mut edge.style.route, route, format!("{route}"));
+                                    }
+                                });
+                            ui.horizontal(|ui| {
+                                if ui.button("Add Bend").clicked() {
+                                    graph_ev.send(GraphEvent::AddBend(EdgeE(*edge_e)));
+                                }
+                                if ui.button("Straighten").clicked() {
+                                    graph_ev.send(GraphEvent::Straighten(Some(EdgeE(*edge_e))));
+                                }
+                                ui.label(format!("Bends: {}", edge.bend_count()));
+                            });
+                        });
+                        if edge.style != style {
+                            ev_regen.send(RegenEdgeMesh());
+                        }
+                    }
                     ui.label(format!("Is Bridge: {}", is_bridge));
                 }
             }
@@ -243,8 +584,10 @@ pub(crate) fn egui_show_graph_info(
     mut contexts: EguiContexts,
     mut info_win: ResMut<GraphInfoWindow>,
     q_nodes: Query<(Entity, &Children), With<GNode>>,
-    q_text: Query<&Text>,
-    graph: Res<Graph>,
+    q_text: Query<&Text, Without<Annotation>>,
+    (graph, mut layout, theme): (Res<Graph>, ResMut<CommunityLayout>, Res<Theme>),
+    mut analyze_ev: EventWriter<AnalyzeGraphEvent>,
+    labels: Labels,
 ) {
     let mut open = info_win.open;
     egui::Window::new("Graph Info")
@@ -264,6 +607,7 @@ pub(crate) fn egui_show_graph_info(
                             info_win.adj_matrix.data.iter().flatten().cloned().collect(),
                         );
                         info_win.eigen = Some(nalgebra::linalg::SymmetricEigen::new(matrix));
+                        info_win.clustering = graph.global_clustering();
 
                         // Get the maximum column width for the matrix
                         let mut max_width = 0;
@@ -278,8 +622,7 @@ pub(crate) fn egui_show_graph_info(
                     ui.separator();
 
                     ui.label("Eigenvalues:");
-                    ui.label(format!(
-                        "{}",
+                    ui.label(
                         info_win
                             .eigen
                             .as_ref()
@@ -288,8 +631,8 @@ pub(crate) fn egui_show_graph_info(
                             .iter()
                             .map(|e| format!("{:.4}", e))
                             .collect::<Vec<_>>()
-                            .join(", ")
-                    ));
+                            .join(", "),
+                    );
                     ui.separator();
 
                     ui.label("Eigenvectors (iterative estimation):");
@@ -303,6 +646,39 @@ pub(crate) fn egui_show_graph_info(
                     {
                         ui.label(format!("{}: [{}]", i, vec.iter().map(|e| format!("{:.4}", e)).collect::<Vec<_>>().join(", ")));
                     }
+                    ui.separator();
+
+                    let (transitivity, average) = info_win.clustering;
+                    ui.label(format!("Transitivity: {:.4}", transitivity));
+                    ui.label(format!("Average Clustering: {:.4}", average));
+                    ui.separator();
+
+                    ui.label("Communities");
+                    let method = &mut info_win.community_method;
+                    egui::ComboBox::from_label("Method")
+                        .selected_text(format!("{method}"))
+                        .show_ui(ui, |ui| {
+                            for m in [CommunityMethod::Louvain, CommunityMethod::LabelPropagation] {
+                                ui.selectable_value(method, m, format!("{m}"));
+                            }
+                        });
+                    if ui.button("Detect").clicked() {
+                        analyze_ev.send(AnalyzeGraphEvent::Communities(*method));
+                    }
+                    ui.checkbox(&mut layout.forces, "Separate communities in physics");
+
+                    if let Some(communities) = &layout.communities {
+                        ui.label(format!("Modularity: {:.4}", communities.modularity));
+                        for (i, members) in communities.members().iter().enumerate() {
+                            let mut names: Vec<String> = members.iter().map(|node| labels.get(**node)).collect();
+                            names.sort();
+                            let [r, g, b, _] = theme.palette_color(i).as_rgba_u8();
+                            ui.colored_label(
+                                egui::Color32::from_rgb(r, g, b),
+                                format!("{}: {}", i, names.join(", ")),
+                            );
+                        }
+                    }
                 });
         });
 
@@ -322,3 +698,45 @@ fn show_alerts(ctx: &mut egui::Context, alerts: &mut Vec<String>) {
 
     alerts.retain(|a| !closed.contains(a));
 }
+
+/// A text box over the label being edited, which closes when it loses focus
+/// or on Escape. Labels can span several lines.
+pub(crate) fn egui_label_editor(
+    mut contexts: EguiContexts,
+    mut editor: ResMut<LabelEditor>,
+    q_camera: Query<(&Camera, &GlobalTransform), With<MainCamera>>,
+    mut q_labels: Query<(&mut Text, &GlobalTransform), EditableLabel>,
+) {
+    let Some(label_e) = editor.label else {
+        return;
+    };
+    let (Ok((camera, camera_t)), Ok((mut text, label_t))) = (q_camera.get_single(), q_labels.get_mut(label_e)) else {
+        editor.label = None;
+        return;
+    };
+    let Some(pos) = camera.world_to_viewport(camera_t, label_t.translation()) else {
+        return;
+    };
+
+    let focus = std::mem::take(&mut editor.focus);
+    let mut close = false;
+    egui::Area::new("label_editor")
+        .fixed_pos(egui::pos2(pos.x, pos.y))
+        .pivot(egui::Align2::CENTER_CENTER)
+        .show(contexts.ctx_mut(), |ui| {
+            egui::Frame::popup(ui.style()).show(ui, |ui| {
+                let response = ui.add(
+                    egui::TextEdit::multiline(&mut text.sections[0].value)
+                        .desired_rows(1)
+                        .desired_width(160.0),
+                );
+                if focus {
+                    response.request_focus();
+                }
+                close = response.lost_focus() || ui.input(|i| i.key_pressed(egui::Key::Escape));
+            });
+        });
+    if close {
+        editor.label = None;
+    }
+}
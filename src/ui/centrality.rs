@@ -0,0 +1,149 @@
+use bevy::prelude::*;
+use bevy_egui::{egui, EguiContexts};
+
+use crate::{
+    graph::{
+        centrality::{Centrality, CentralityTable},
+        event::draw::Labels,
+        shape::NodeStyle,
+        Graph,
+    },
+    theme::Theme,
+    types::GNodeExclusive,
+};
+
+/// The range of scales applied to nodes by the heatmap
+static HEATMAP_SCALE: (f32, f32) = (0.7, 1.8);
+
+#[derive(Default, Resource)]
+pub(crate) struct CentralityWindow {
+    pub open: bool,
+    table: CentralityTable,
+    labels: Vec<String>,
+    /// The column to sort by, `None` sorts by label
+    sort: Option<Centrality>,
+    descending: bool,
+    heatmap: Centrality,
+}
+
+fn heat_egui(theme: &Theme, t: f32) -> egui::Color32 {
+    let [r, g, b, _] = theme.heat(t).as_rgba_u8();
+    egui::Color32::from_rgb(r, g, b)
+}
+
+pub(crate) fn egui_show_centrality(
+    mut contexts: EguiContexts,
+    mut cent_win: ResMut<CentralityWindow>,
+    (graph, theme): (Res<Graph>, Res<Theme>),
+    mut q_node: Query<(&mut Sprite, &NodeStyle), GNodeExclusive>,
+    labels: Labels,
+) {
+    let mut open = cent_win.open;
+    egui::Window::new("Centrality")
+        .open(&mut open)
+        .show(contexts.ctx_mut(), |ui| {
+            let CentralityWindow {
+                table,
+                labels: names,
+                sort,
+                descending,
+                heatmap,
+                ..
+            } = &mut *cent_win;
+
+            if ui.button("Compute").clicked() || table.nodes.len() != graph.node_count() {
+                *table = graph.centrality();
+                *names = table.nodes.iter().map(|node| labels.get(**node)).collect();
+            }
+
+            ui.horizontal(|ui| {
+                egui::ComboBox::from_label("Heatmap")
+                    .selected_text(format!("{heatmap}"))
+                    .show_ui(ui, |ui| {
+                        for measure in Centrality::ALL {
+                            ui.selectable_value(heatmap, measure, format!("{measure}"));
+                        }
+                    });
+
+                if ui.button("Apply").clicked() {
+                    let values: Vec<f64> = table.column(*heatmap).collect();
+                    let (min, max) = values
+                        .iter()
+                        .fold((f64::INFINITY, f64::NEG_INFINITY), |(min, max), &x| (min.min(x), max.max(x)));
+                    for (node, value) in table.nodes.iter().zip(values) {
+                        let t = if max > min { ((value - min) / (max - min)) as f32 } else { 0.5 };
+                        if let Ok((mut sprite, style)) = q_node.get_mut(**node) {
+                            sprite.color = theme.heat(t);
+                            let scale = HEATMAP_SCALE.0 + (HEATMAP_SCALE.1 - HEATMAP_SCALE.0) * t;
+                            sprite.custom_size = Some(Vec2::splat(style.size * scale));
+                        }
+                    }
+                }
+            });
+
+            // Legend for the heatmap, low values are small and high values
+            // large
+            let values: Vec<f64> = table.column(*heatmap).collect();
+            let (min, max) = values
+                .iter()
+                .fold((f64::INFINITY, f64::NEG_INFINITY), |(min, max), &x| (min.min(x), max.max(x)));
+            if min.is_finite() {
+                ui.horizontal(|ui| {
+                    ui.label(format!("{min:.3}"));
+                    let (rect, _) = ui.allocate_exact_size(egui::vec2(150.0, 12.0), egui::Sense::hover());
+                    let steps = 30;
+                    for i in 0..steps {
+                        let x = |i: usize| rect.left() + rect.width() * i as f32 / steps as f32;
+                        let segment = egui::Rect::from_x_y_ranges(x(i)..=x(i + 1), rect.y_range());
+                        ui.painter()
+                            .rect_filled(segment, 0.0, heat_egui(&theme, i as f32 / (steps - 1) as f32));
+                    }
+                    ui.label(format!("{max:.3}"));
+                });
+            }
+            ui.separator();
+
+            let mut rows: Vec<usize> = (0..table.nodes.len()).collect();
+            match sort {
+                Some(measure) => {
+                    let column: Vec<f64> = table.column(*measure).collect();
+                    rows.sort_by(|a, b| column[*a].total_cmp(&column[*b]));
+                }
+                None => rows.sort_by(|a, b| names[*a].cmp(&names[*b])),
+            }
+            if *descending {
+                rows.reverse();
+            }
+
+            egui::ScrollArea::both().max_height(400.0).show(ui, |ui| {
+                egui::Grid::new("centrality_table").striped(true).show(ui, |ui| {
+                    let mut header = |ui: &mut egui::Ui, text: String, column: Option<Centrality>| {
+                        let arrow = match (*sort == column, *descending) {
+                            (true, true) => " ⏷",
+                            (true, false) => " ⏶",
+                            _ => "",
+                        };
+                        if ui.button(format!("{text}{arrow}")).clicked() {
+                            *descending = *sort == column && !*descending;
+                            *sort = column;
+                        }
+                    };
+                    header(ui, "Node".into(), None);
+                    for measure in Centrality::ALL {
+                        header(ui, format!("{measure}"), Some(measure));
+                    }
+                    ui.end_row();
+
+                    for row in rows {
+                        ui.label(&names[row]);
+                        for value in table.values[row].iter() {
+                            ui.monospace(format!("{value:.4}"));
+                        }
+                        ui.end_row();
+                    }
+                });
+            });
+        });
+
+    cent_win.open = open;
+}
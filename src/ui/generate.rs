@@ -0,0 +1,118 @@
+use bevy::prelude::*;
+use bevy_egui::{egui, EguiContexts};
+
+use crate::{
+    graph::{
+        event::GraphEvent,
+        generate::{generate, GeneratorParams, GraphFamily},
+        Graph,
+    },
+    MainCamera,
+};
+
+#[derive(Default, Resource)]
+pub(crate) struct GeneratorWindow {
+    pub open: bool,
+    family: GraphFamily,
+    params: GeneratorParams,
+    replace: bool,
+}
+
+pub(crate) fn egui_show_generator(
+    mut contexts: EguiContexts,
+    mut gen_win: ResMut<GeneratorWindow>,
+    mut graph_ev: EventWriter<GraphEvent>,
+    graph: Res<Graph>,
+    q_camera: Query<&Transform, With<MainCamera>>,
+) {
+    let mut open = gen_win.open;
+    egui::Window::new("Generate Graph")
+        .open(&mut open)
+        .show(contexts.ctx_mut(), |ui| {
+            let GeneratorWindow {
+                family,
+                params,
+                replace,
+                ..
+            } = &mut *gen_win;
+
+            egui::ComboBox::from_label("Family")
+                .selected_text(format!("{family}"))
+                .width(180.0)
+                .show_ui(ui, |ui| {
+                    for f in GraphFamily::ALL {
+                        ui.selectable_value(family, f, format!("{f}"));
+                    }
+                });
+
+            match family {
+                GraphFamily::CompleteBipartite => {
+                    ui.add(egui::Slider::new(&mut params.m, 1..=12).text("m"));
+                    ui.add(egui::Slider::new(&mut params.n, 1..=12).text("n"));
+                }
+                GraphFamily::Grid | GraphFamily::Torus => {
+                    ui.add(egui::Slider::new(&mut params.n, 1..=15).text("Width"));
+                    ui.add(egui::Slider::new(&mut params.m, 1..=15).text("Height"));
+                }
+                GraphFamily::Hypercube => {
+                    params.n = params.n.clamp(1, 7);
+                    ui.add(egui::Slider::new(&mut params.n, 1..=7).text("Dimension"));
+                }
+                GraphFamily::BinaryTree => {
+                    params.n = params.n.clamp(1, 7);
+                    ui.add(egui::Slider::new(&mut params.n, 1..=7).text("Depth"));
+                }
+                GraphFamily::Petersen => (),
+                GraphFamily::ErdosRenyi => {
+                    ui.add(egui::Slider::new(&mut params.n, 1..=60).text("n"));
+                    ui.add(egui::Slider::new(&mut params.p, 0.0..=1.0).text("Edge probability"));
+                }
+                GraphFamily::BarabasiAlbert => {
+                    ui.add(egui::Slider::new(&mut params.n, 2..=60).text("n"));
+                    ui.add(egui::Slider::new(&mut params.m, 1..=6).text("Edges per node"));
+                }
+                GraphFamily::WattsStrogatz => {
+                    ui.add(egui::Slider::new(&mut params.n, 3..=60).text("n"));
+                    ui.add(egui::Slider::new(&mut params.k, 1..=6).text("Neighbours per side"));
+                    ui.add(egui::Slider::new(&mut params.p, 0.0..=1.0).text("Rewire probability"));
+                }
+                GraphFamily::Cycle | GraphFamily::Wheel => {
+                    params.n = params.n.max(3);
+                    ui.add(egui::Slider::new(&mut params.n, 3..=40).text("n"));
+                }
+                _ => {
+                    ui.add(egui::Slider::new(&mut params.n, 1..=40).text("n"));
+                }
+            }
+
+            if family.is_random() {
+                ui.horizontal(|ui| {
+                    ui.label("Seed");
+                    ui.add(egui::DragValue::new(&mut params.seed));
+                    if ui.button("Randomize").clicked() {
+                        params.seed = std::time::SystemTime::now()
+                            .duration_since(std::time::UNIX_EPOCH)
+                            .map(|d| d.as_nanos() as u64)
+                            .unwrap_or_default();
+                    }
+                });
+            }
+
+            ui.checkbox(replace, "Replace current graph");
+
+            if ui.button("Generate").clicked() {
+                if *replace {
+                    for node in graph.node_edges.keys() {
+                        graph_ev.send(GraphEvent::RemoveItem(**node));
+                    }
+                }
+
+                let mut template = generate(*family, params);
+                let center = q_camera.single().translation.truncate();
+                template.center_on(center);
+                graph_ev.send(GraphEvent::Spawn(template));
+            }
+        });
+
+    gen_win.open = open;
+}
@@ -0,0 +1,269 @@
+use std::path::Path;
+
+use bevy::prelude::*;
+use bevy_egui::{egui, EguiContexts};
+
+use crate::{
+    graph::{
+        event::GraphEvent,
+        io,
+        isomorphism::{self, GaveUp, ISOMORPHISM_STEPS},
+        ops::{self, Product},
+        shape::NodeStyle,
+        template::GraphTemplate,
+        Annotation, EdgeE, GEdge, GNode, Graph,
+    },
+    input::CursorInfo,
+    theme::Theme,
+    types::GNodeExclusive,
+};
+
+use super::Alerts;
+
+#[derive(Resource)]
+pub(crate) struct OperationsWindow {
+    pub open: bool,
+    path: String,
+}
+
+impl Default for OperationsWindow {
+    fn default() -> Self {
+        OperationsWindow {
+            open: false,
+            path: "graph.txt".into(),
+        }
+    }
+}
+
+/// Remove every node in the graph (and with them every edge)
+pub(crate) fn clear_graph(graph: &Graph, cursor: &mut CursorInfo, graph_ev: &mut EventWriter<GraphEvent>) {
+    graph_ev.send(GraphEvent::ItemDeselected);
+    cursor.group.clear();
+    for node in graph.node_edges.keys() {
+        graph_ev.send(GraphEvent::RemoveItem(**node));
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
+pub(crate) fn egui_show_operations(
+    mut contexts: EguiContexts,
+    mut ops_win: ResMut<OperationsWindow>,
+    mut graph_ev: EventWriter<GraphEvent>,
+    mut graph: ResMut<Graph>,
+    mut alerts: ResMut<Alerts>,
+    (mut cursor, theme): (ResMut<CursorInfo>, Res<Theme>),
+    q_nodes: Query<(&Transform, &Children, &NodeStyle), With<GNode>>,
+    q_edges: Query<&GEdge>,
+    q_text: Query<&Text, Without<Annotation>>,
+    mut q_sprite: Query<&mut Sprite, GNodeExclusive>,
+) {
+    let mut open = ops_win.open;
+    egui::Window::new("Graph Operations")
+        .open(&mut open)
+        .show(contexts.ctx_mut(), |ui| {
+            let snapshot = |graph: &Graph| GraphTemplate::from_graph(graph, &q_nodes, &q_edges, &q_text);
+            let mut result = None;
+
+            ui.label("Replace the current graph with its:");
+            ui.horizontal_wrapped(|ui| {
+                if ui.button("Complement").clicked() {
+                    result = Some(ops::complement(&snapshot(&graph).0, graph.directed));
+                }
+                if ui.button("Line Graph").clicked() {
+                    result = Some(ops::line_graph(&snapshot(&graph).0, graph.directed));
+                }
+                if ui
+                    .add_enabled(graph.directed, egui::Button::new("Transpose"))
+                    .clicked()
+                {
+                    result = Some(ops::transpose(&snapshot(&graph).0));
+                }
+                if ui.button("Subdivision").clicked() {
+                    result = Some(ops::subdivide(&snapshot(&graph).0));
+                }
+
+                let selected_edge = cursor
+                    .selected
+                    .filter(|e| graph.edge_nodes.contains_key(&EdgeE(*e)));
+                if ui
+                    .add_enabled(selected_edge.is_some(), egui::Button::new("Contract Selected Edge"))
+                    .on_disabled_hover_text("Select an edge in Info mode first")
+                    .clicked()
+                {
+                    let (t, _, edges) = snapshot(&graph);
+                    let index = edges.iter().position(|e| Some(**e) == selected_edge).unwrap();
+                    result = Some(ops::contract(&t, index, graph.directed));
+                }
+            });
+            ui.separator();
+
+            ui.horizontal(|ui| {
+                ui.label("File");
+                ui.text_edit_singleline(&mut ops_win.path);
+            });
+            let path = Path::new(&ops_win.path);
+
+            ui.horizontal(|ui| {
+                if ui.button("Save").clicked() {
+                    if let Err(e) = io::save(path, &snapshot(&graph).0, graph.directed) {
+                        alerts.0.push(e);
+                    }
+                }
+                if ui.button("Load").clicked() {
+                    match io::load(path) {
+                        Ok((t, directed)) => {
+                            graph.directed = directed;
+                            result = Some(t);
+                        }
+                        Err(e) => alerts.0.push(e),
+                    }
+                }
+                if ui.button("Export SVG").clicked() {
+                    graph_ev.send(GraphEvent::Export(path.with_extension("svg")));
+                }
+                if ui.button("Export PNG").clicked() {
+                    graph_ev.send(GraphEvent::Export(path.with_extension("png")));
+                }
+                if ui.button("Export TikZ").clicked() {
+                    graph_ev.send(GraphEvent::Export(path.with_extension("tex")));
+                }
+            });
+
+            ui.label("Combine the current graph with the file:");
+            ui.horizontal_wrapped(|ui| {
+                let mut combine = |f: &dyn Fn(&GraphTemplate, &GraphTemplate) -> GraphTemplate| {
+                    match io::load(path) {
+                        Ok((other, _)) => result = Some(f(&snapshot(&graph).0, &other)),
+                        Err(e) => alerts.0.push(e),
+                    }
+                };
+                let directed = graph.directed;
+
+                if ui.button("Union").clicked() {
+                    combine(&ops::union);
+                }
+                if ui.button("Join").clicked() {
+                    combine(&ops::join);
+                }
+                if ui.button("Cartesian Product").clicked() {
+                    combine(&|a, b| ops::product(a, b, Product::Cartesian, directed));
+                }
+                if ui.button("Tensor Product").clicked() {
+                    combine(&|a, b| ops::product(a, b, Product::Tensor, directed));
+                }
+            });
+
+            ui.separator();
+
+            ui.label("Isomorphism:");
+            // The two graphs to compare, along with the canvas nodes of each
+            // (the file's nodes aren't on the canvas)
+            let mut compare = None;
+            ui.horizontal_wrapped(|ui| {
+                if ui
+                    .button("Compare Components")
+                    .on_hover_text(
+                        "Compares the two components of the graph, or the component of the \
+                         node selected in Info mode with the rest of the graph",
+                    )
+                    .clicked()
+                {
+                    let (t, nodes, _) = snapshot(&graph);
+                    let components = t.components();
+                    let selected = cursor
+                        .selected
+                        .and_then(|e| nodes.iter().position(|node| **node == e))
+                        .and_then(|i| components.iter().position(|c| c.contains(&i)));
+
+                    match (selected, components.len()) {
+                        (_, 2) | (Some(_), 3..) => {
+                            let first = selected.unwrap_or(0);
+                            let a = components[first].clone();
+                            let mut b: Vec<usize> = components
+                                .iter()
+                                .enumerate()
+                                .filter(|(i, _)| *i != first)
+                                .flat_map(|(_, c)| c.iter().copied())
+                                .collect();
+                            b.sort();
+                            let canvas = |c: &[usize]| c.iter().map(|i| nodes[*i]).collect::<Vec<_>>();
+                            compare = Some((t.induced(&a), t.induced(&b), canvas(&a), Some(canvas(&b))));
+                        }
+                        _ => alerts.0.push(
+                            "Select a node to compare its component with the rest of the graph, \
+                             or have exactly two components"
+                                .into(),
+                        ),
+                    }
+                }
+                if ui.button("Compare With File").clicked() {
+                    match io::load(path) {
+                        Ok((_, directed)) if directed != graph.directed => {
+                            let kind = |directed: bool| if directed { "directed" } else { "undirected" };
+                            alerts.0.push(format!(
+                                "The graphs are not isomorphic: the file's graph is {} and this one is {}",
+                                kind(directed),
+                                kind(graph.directed)
+                            ));
+                        }
+                        Ok((other, _)) => {
+                            let (t, nodes, _) = snapshot(&graph);
+                            compare = Some((t, other, nodes, None));
+                        }
+                        Err(e) => alerts.0.push(e),
+                    }
+                }
+            });
+
+            if let Some((a, b, a_nodes, b_nodes)) = compare {
+                match isomorphism::isomorphism(&a, &b, graph.directed) {
+                    Ok(Some(mapping)) => {
+                        // Matched nodes share a color and a number, or are
+                        // labeled with the file node they map to
+                        for (u, &v) in mapping.iter().enumerate() {
+                            let text = match &b_nodes {
+                                Some(_) => format!("{}", u + 1),
+                                None => format!(
+                                    "→ {}",
+                                    b.nodes[v].label.clone().unwrap_or_else(|| format!("v{v}"))
+                                ),
+                            };
+                            let mut entities = vec![a_nodes[u]];
+                            entities.extend(b_nodes.as_ref().map(|nodes| nodes[v]));
+                            for node in entities {
+                                if let Ok(mut sprite) = q_sprite.get_mut(*node) {
+                                    sprite.color = theme.palette_color(u);
+                                }
+                                graph_ev.send(GraphEvent::Annotate(*node, text.clone()));
+                            }
+                        }
+                        alerts.0.push(format!("The graphs are isomorphic ({} vertices)", a.nodes.len()));
+                    }
+                    result => {
+                        let differences = isomorphism::invariant_differences(&a, &b, graph.directed);
+                        if !differences.is_empty() {
+                            alerts.0.push(format!("The graphs are not isomorphic:\n{}", differences.join("\n")));
+                        } else if let Err(GaveUp) = result {
+                            alerts.0.push(format!(
+                                "Gave up after {ISOMORPHISM_STEPS} comparisons, the graphs agree on \
+                                 every invariant but are too symmetric to compare quickly"
+                            ));
+                        } else {
+                            alerts.0.push(
+                                "The graphs are not isomorphic, though their vertex and edge counts, \
+                                 degree sequences and spectra agree"
+                                    .into(),
+                            );
+                        }
+                    }
+                }
+            }
+
+            if let Some(t) = result {
+                clear_graph(&graph, &mut cursor, &mut graph_ev);
+                graph_ev.send(GraphEvent::Spawn(t));
+            }
+        });
+
+    ops_win.open = open;
+}